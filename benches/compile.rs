@@ -0,0 +1,76 @@
+//! Measures how long it takes to load, desugar and compile large synthetic books.
+//!
+//! Run with `cargo bench`. Each phase is run a few times and the fastest run is shown,
+//! so the numbers can be compared before and after a change to the compiler.
+
+use bend::{
+  compile_book, desugar_book, diagnostics::DiagnosticsConfig, fun::Term, imports::DefaultLoader,
+  load_to_book, CompileOpts,
+};
+use std::{
+  fmt::Write,
+  path::Path,
+  time::{Duration, Instant},
+};
+
+/// How many times each phase is run.
+const RUNS: usize = 5;
+
+/// The number of definitions of each generated book.
+const SIZES: [usize; 2] = [1000, 3000];
+
+fn main() {
+  println!("size of Term: {} bytes", std::mem::size_of::<Term>());
+  for size in SIZES {
+    let code = synthetic_book(size);
+    let path = Path::new("bench.bend");
+    let load = || load_to_book(path, &code, DefaultLoader::new(path), DiagnosticsConfig::default()).unwrap();
+    let book = load();
+
+    println!("book with {size} definitions ({} lines):", code.lines().count());
+    bench("load", || {
+      load();
+    });
+    bench("desugar", || {
+      desugar_book(&mut book.clone(), CompileOpts::default(), DiagnosticsConfig::default(), None).unwrap();
+    });
+    bench("compile", || {
+      compile_book(&mut book.clone(), CompileOpts::default(), DiagnosticsConfig::default(), None).unwrap();
+    });
+  }
+}
+
+fn bench(name: &str, mut run: impl FnMut()) {
+  let mut best = Duration::MAX;
+  for _ in 0..RUNS {
+    let start = Instant::now();
+    run();
+    best = best.min(start.elapsed());
+  }
+  println!("  {name:<8} {:>10.2?}", best);
+}
+
+/// A book with `size` definitions that match on lists and numbers and call each other,
+/// half of them in each syntax.
+fn synthetic_book(size: usize) -> String {
+  let mut code = String::new();
+  for i in 0..size {
+    if i % 2 == 0 {
+      writeln!(code, "(Sum{i} (List/Cons h t)) = (+ (* h {i}) (Sum{i} t))").unwrap();
+      writeln!(code, "(Sum{i} List/Nil) = 0\n").unwrap();
+    } else {
+      writeln!(code, "def Sum{i}(xs: List(u24)) -> u24:").unwrap();
+      writeln!(code, "  fold xs:").unwrap();
+      writeln!(code, "    case List/Cons:").unwrap();
+      writeln!(code, "      switch xs.head:").unwrap();
+      writeln!(code, "        case 0:").unwrap();
+      writeln!(code, "          return xs.tail").unwrap();
+      writeln!(code, "        case _:").unwrap();
+      writeln!(code, "          return xs.head-1 + {i} + xs.tail").unwrap();
+      writeln!(code, "    case List/Nil:").unwrap();
+      writeln!(code, "      return Sum{}([1, 2, 3])\n", i - 1).unwrap();
+    }
+  }
+  writeln!(code, "main = (Sum{} [1, 2, 3])", size - 1).unwrap();
+  code
+}
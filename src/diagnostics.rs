@@ -26,6 +26,13 @@ pub struct DiagnosticsConfig {
   pub recursion_cycle: Severity,
   pub missing_main: Severity,
   pub import_shadow: Severity,
+  pub non_exhaustive_match: Severity,
+  pub rules_out_of_order: Severity,
+  pub redefinition: Severity,
+  pub division_by_zero: Severity,
+  pub self_application: Severity,
+  pub deprecated: Severity,
+  pub keyword_name: Severity,
 }
 
 #[derive(Debug, Clone)]
@@ -66,6 +73,13 @@ pub enum WarningType {
   RecursionCycle,
   MissingMain,
   ImportShadow,
+  NonExhaustiveMatch,
+  RulesOutOfOrder,
+  Redefinition,
+  DivisionByZero,
+  SelfApplication,
+  Deprecated,
+  KeywordName,
 }
 
 impl Diagnostics {
@@ -77,6 +91,16 @@ impl Diagnostics {
     self.add_diagnostic(err, Severity::Error, DiagnosticOrigin::Parsing, source);
   }
 
+  pub fn add_parsing_warning(
+    &mut self,
+    warn: impl std::fmt::Display,
+    warn_type: WarningType,
+    source: Source,
+  ) {
+    let severity = self.config.warning_severity(warn_type);
+    self.add_diagnostic(warn, severity, DiagnosticOrigin::Parsing, source);
+  }
+
   pub fn add_book_error(&mut self, err: impl std::fmt::Display) {
     self.add_diagnostic(err, Severity::Error, DiagnosticOrigin::Book, Default::default());
   }
@@ -154,6 +178,11 @@ impl Diagnostics {
     }
   }
 
+  /// All the diagnostics with where they come from, grouped by origin.
+  pub fn iter(&self) -> impl Iterator<Item = (&DiagnosticOrigin, &Diagnostic)> {
+    self.diagnostics.iter().flat_map(|(origin, diags)| diags.iter().map(move |diag| (origin, diag)))
+  }
+
   pub fn has_severity(&self, severity: Severity) -> bool {
     self.diagnostics.values().any(|errs| errs.iter().any(|e| e.severity == severity))
   }
@@ -337,8 +366,19 @@ impl DiagnosticsConfig {
       repeated_bind: severity,
       recursion_cycle: severity,
       import_shadow: severity,
+      self_application: severity,
+      deprecated: severity,
+      keyword_name: severity,
       // Should only be changed manually, as a missing main is always a error to hvm
       missing_main: Severity::Error,
+      // Also only changed manually, the missing cases of a non-exhaustive match become runtime errors
+      non_exhaustive_match: Severity::Error,
+      // Also only changed manually, the out of order rules of a function are gathered together
+      rules_out_of_order: Severity::Error,
+      // Also only changed manually, only the last definition of a function defined more than once is kept
+      redefinition: Severity::Error,
+      // Also only changed manually, an integer division by zero is a runtime error
+      division_by_zero: Severity::Error,
       verbose,
     }
   }
@@ -353,6 +393,13 @@ impl DiagnosticsConfig {
       WarningType::UnreachableMatch => self.unreachable_match,
       WarningType::MissingMain => self.missing_main,
       WarningType::ImportShadow => self.import_shadow,
+      WarningType::NonExhaustiveMatch => self.non_exhaustive_match,
+      WarningType::RulesOutOfOrder => self.rules_out_of_order,
+      WarningType::Redefinition => self.redefinition,
+      WarningType::DivisionByZero => self.division_by_zero,
+      WarningType::SelfApplication => self.self_application,
+      WarningType::Deprecated => self.deprecated,
+      WarningType::KeywordName => self.keyword_name,
     }
   }
 }
@@ -473,4 +520,53 @@ impl TextSpan {
 
     TextSpan::new(TextLocation::new(start_line, start_char), TextLocation::new(end_line, end_char))
   }
+
+  /// Transforms a span made by [`TextSpan::from_byte_span`] back into the byte range on `code`.
+  ///
+  /// The columns count the bytes of the line, not its characters, so the range is exact after non-ASCII text.
+  pub fn to_byte_span(&self, code: &str) -> Range<usize> {
+    let byte_idx = |loc: TextLocation| {
+      let line_start: usize = code.split_inclusive('\n').take(loc.line).map(str::len).sum();
+      (line_start + loc.char).min(code.len())
+    };
+    byte_idx(self.start)..byte_idx(self.end)
+  }
+}
+
+/// The candidate most similar to `name`, if one is close enough to be a likely misspelling of it.
+///
+/// Two names are close when they are at most one edit apart for every three characters of `name`,
+/// so names shorter than three characters never have a suggestion.
+pub fn closest_name<'a>(name: &str, candidates: impl IntoIterator<Item = &'a Name>) -> Option<&'a Name> {
+  let max_dist = name.chars().count() / 3;
+  candidates
+    .into_iter()
+    .filter(|candidate| candidate.as_ref() != name)
+    .map(|candidate| (edit_distance(name, candidate), candidate))
+    .filter(|(dist, _)| *dist <= max_dist)
+    .min_by_key(|(dist, _)| *dist)
+    .map(|(_, candidate)| candidate)
+}
+
+/// The number of character insertions, deletions, substitutions and swaps of adjacent characters that turn `a` into `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+  let (a, b) = (a.chars().collect::<Vec<_>>(), b.chars().collect::<Vec<_>>());
+  // The distances between the prefixes of `a` and `b`.
+  let mut dist = vec![vec![0; b.len() + 1]; a.len() + 1];
+  for (i, row) in dist.iter_mut().enumerate() {
+    row[0] = i;
+  }
+  for (j, cell) in dist[0].iter_mut().enumerate() {
+    *cell = j;
+  }
+  for i in 1..=a.len() {
+    for j in 1..=b.len() {
+      let subst = dist[i - 1][j - 1] + usize::from(a[i - 1] != b[j - 1]);
+      dist[i][j] = subst.min(dist[i - 1][j] + 1).min(dist[i][j - 1] + 1);
+      if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+        dist[i][j] = dist[i][j].min(dist[i - 2][j - 2] + 1);
+      }
+    }
+  }
+  dist[a.len()][b.len()]
 }
@@ -40,11 +40,32 @@ pub const IO: &str = "IO";
 pub const IO_DONE: &str = "IO/Done";
 pub const IO_CALL: &str = "IO/Call";
 
+pub const UNREACHABLE: &str = "unreachable";
+/// The body of the arms that a guarded match adds for the cases where the last guard failed.
+/// It's replaced by [UNREACHABLE] when fixing the match terms, where these arms are told apart from the user's.
+pub const GUARD_UNREACHABLE: &str = "%guard_unreachable";
+
+pub const MATCH_ERROR: &str = "MatchError/non_exhaustive";
+
 pub const BUILTIN_CTRS: &[&str] =
   &[LCONS, LNIL, SCONS, SNIL, NAT_SUCC, NAT_ZERO, TREE_NODE, TREE_LEAF, MAP_NODE, MAP_LEAF, IO_DONE, IO_CALL];
 
 pub const BUILTIN_TYPES: &[&str] = &[LIST, STRING, NAT, TREE, MAP, IO];
 
+/// Builtin functions that a program can define again, replacing the builtin one.
+/// These are the `bind` and `wrap` of the `with` blocks, which programs used to define themselves.
+pub const SHADOWABLE_DEFS: &[&str] = &["Result/bind", "Result/wrap", "Maybe/bind", "Maybe/wrap"];
+
+/// Builtin types that a program can define again, replacing the builtin type,
+/// its constructors and the builtin functions in its namespace.
+/// Many programs define their own `Bool`, with other constructors or functions.
+pub const SHADOWABLE_TYPES: &[&str] = &["Bool"];
+
+/// If the name is in the namespace of one of the [`SHADOWABLE_TYPES`], like `Bool/not`.
+pub fn is_in_shadowable_type(name: &Name) -> bool {
+  name.split_once('/').is_some_and(|(typ, _)| SHADOWABLE_TYPES.contains(&typ))
+}
+
 impl ParseBook {
   pub fn builtins() -> Self {
     let book =
@@ -54,6 +75,7 @@ impl ParseBook {
 }
 
 impl Book {
+  #[tracing::instrument(level = "debug", skip_all)]
   pub fn encode_builtins(&mut self) {
     for def in self.defs.values_mut() {
       for rule in def.rules.iter_mut() {
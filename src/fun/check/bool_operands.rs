@@ -0,0 +1,88 @@
+use crate::{
+  diagnostics::Diagnostics,
+  fun::{Book, Ctx, Name, Term, Type},
+  maybe_grow,
+};
+
+const BOOL: &str = "Bool";
+
+impl Ctx<'_> {
+  /// Checks that the operands of the logical operators `&&` and `||` aren't values of the `Bool` type.
+  ///
+  /// The operators work on number conditions, like the results of the comparisons,
+  /// so a `Bool` constructor, or a call to a function typed as returning a `Bool`, can't be used there.
+  #[tracing::instrument(level = "debug", skip_all)]
+  pub fn check_bool_operands(&mut self) -> Result<(), Diagnostics> {
+    for def in self.book.defs.values() {
+      let mut errs = vec![];
+      for rule in def.rules.iter() {
+        rule.body.check_bool_operands(self.book, &mut errs);
+      }
+      for (op, operand) in errs {
+        let msg = self.book.bool_operand_msg(op, &operand);
+        self.info.add_function_error(msg, def.name.clone(), def.source.clone());
+      }
+    }
+
+    self.info.fatal(())
+  }
+}
+
+impl Term {
+  fn check_bool_operands(&self, book: &Book, errs: &mut Vec<(&'static str, Term)>) {
+    maybe_grow(|| {
+      if let Term::Swt(swt) = self {
+        // The logical operators are desugared to switches with a fixed bind name.
+        let logic_op = match swt.bnd.as_deref() {
+          Some("%and") => Some(("&&", &swt.arms[1])),
+          Some("%or") => Some(("||", &swt.arms[0])),
+          _ => None,
+        };
+        if let Some((op, rhs)) = logic_op {
+          for operand in [&swt.arg, rhs] {
+            if book.is_bool(operand) {
+              errs.push((op, operand.clone()));
+            }
+          }
+        }
+      }
+      for child in self.children() {
+        child.check_bool_operands(book, errs);
+      }
+    })
+  }
+}
+
+impl Book {
+  /// Whether the term is a constructor of `Bool`, or a call to a function typed as returning a `Bool`.
+  fn is_bool(&self, term: &Term) -> bool {
+    let mut head = term;
+    let mut args = 0;
+    while let Term::App { fun, .. } = head {
+      head = fun;
+      args += 1;
+    }
+    let Term::Ref { nam } = head else { return false };
+    if let Some(typ) = self.ctrs.get(nam) {
+      return typ == BOOL;
+    }
+    let Some(def) = self.defs.get(nam) else { return false };
+    let mut typ = &def.typ;
+    for _ in 0..args {
+      let Type::Arr(_, ret) = typ else { return false };
+      typ = ret;
+    }
+    // The names of the types aren't resolved yet, so `Bool` can still be a type variable.
+    matches!(typ, Type::Ctr(name, _) | Type::Var(name) if name == BOOL && self.adts.contains_key(name))
+  }
+
+  fn bool_operand_msg(&self, op: &str, operand: &Term) -> String {
+    let fun = if op == "&&" { "Bool/and" } else { "Bool/or" };
+    let mut msg = format!("The operands of '{op}' are numbers, but '{operand}' is a 'Bool'.");
+    // A program that defines its own `Bool` doesn't have the builtin functions.
+    if self.defs.contains_key(&Name::new("Bool/to_u24")) && self.defs.contains_key(&Name::new(fun)) {
+      msg.push_str(&format!(" Convert it with 'Bool/to_u24', or use '{fun}' to combine two 'Bool's."));
+    }
+    msg
+  }
+}
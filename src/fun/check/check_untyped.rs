@@ -6,6 +6,7 @@ use crate::{
 
 impl Ctx<'_> {
   /// Checks that terms that cannot be typed are only used inside untyped functions.
+  #[tracing::instrument(level = "debug", skip_all)]
   pub fn check_untyped_terms(&mut self) -> Result<(), Diagnostics> {
     for def in self.book.defs.values() {
       if def.check {
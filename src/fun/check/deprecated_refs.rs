@@ -0,0 +1,50 @@
+use crate::{
+  diagnostics::WarningType,
+  fun::{Ctx, Name, Term},
+  maybe_grow,
+};
+use indexmap::IndexMap;
+
+impl Ctx<'_> {
+  /// Warns about every reference to a definition marked with `#[deprecated]`,
+  /// with the message of the attribute, which usually says what to use instead.
+  ///
+  /// References from definitions that are deprecated themselves are not reported.
+  #[tracing::instrument(level = "debug", skip_all)]
+  pub fn check_deprecated_refs(&mut self) {
+    if self.book.deprecated.is_empty() {
+      return;
+    }
+    for def in self.book.defs.values() {
+      if self.book.deprecated.contains_key(&def.name) {
+        continue;
+      }
+      let mut refs = vec![];
+      for rule in def.rules.iter() {
+        rule.body.deprecated_refs(&self.book.deprecated, &mut refs);
+      }
+      for nam in refs {
+        let msg = match &self.book.deprecated[nam] {
+          Some(msg) => format!("Reference to deprecated definition '{nam}': {msg}"),
+          None => format!("Reference to deprecated definition '{nam}'."),
+        };
+        self.info.add_function_warning(msg, WarningType::Deprecated, def.name.clone(), def.source.clone());
+      }
+    }
+  }
+}
+
+impl Term {
+  fn deprecated_refs<'a>(&'a self, deprecated: &IndexMap<Name, Option<String>>, refs: &mut Vec<&'a Name>) {
+    maybe_grow(|| {
+      if let Term::Ref { nam } = self {
+        if deprecated.contains_key(nam) {
+          refs.push(nam);
+        }
+      }
+      for child in self.children() {
+        child.deprecated_refs(deprecated, refs);
+      }
+    })
+  }
+}
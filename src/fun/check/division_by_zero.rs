@@ -0,0 +1,51 @@
+use crate::{
+  diagnostics::{Diagnostics, WarningType},
+  fun::{Ctx, Num, Op, Term},
+  maybe_grow,
+};
+
+impl Ctx<'_> {
+  /// Checks for integer divisions and remainders by a literal zero.
+  ///
+  /// Dividing an integer by zero is a runtime error, so it's better to catch the obvious cases early.
+  /// For divisors that are only known at runtime, the `checked_div` and `checked_rem` builtins return a `Maybe`.
+  #[tracing::instrument(level = "debug", skip_all)]
+  pub fn check_division_by_zero(&mut self) -> Result<(), Diagnostics> {
+    for def in self.book.defs.values() {
+      for rule in def.rules.iter() {
+        let mut count = 0;
+        rule.body.count_division_by_zero(&mut count);
+        if count > 0 {
+          let msg = if count == 1 {
+            "Integer division by zero.".to_string()
+          } else {
+            format!("{count} integer divisions by zero.")
+          };
+          self.info.add_function_warning(
+            msg,
+            WarningType::DivisionByZero,
+            def.name.clone(),
+            def.source.clone(),
+          );
+        }
+      }
+    }
+
+    self.info.fatal(())
+  }
+}
+
+impl Term {
+  fn count_division_by_zero(&self, count: &mut usize) {
+    maybe_grow(|| {
+      if let Term::Oper { opr: Op::DIV | Op::REM, snd, .. } = self {
+        if let Term::Num { val: Num::U24(0) | Num::I24(0) } = snd.as_ref() {
+          *count += 1;
+        }
+      }
+      for child in self.children() {
+        child.count_division_by_zero(count);
+      }
+    })
+  }
+}
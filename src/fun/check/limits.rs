@@ -0,0 +1,88 @@
+use crate::{
+  diagnostics::Diagnostics,
+  fun::{Ctx, Pattern, Term},
+  Limits,
+};
+
+impl Ctx<'_> {
+  /// Checks that the program doesn't go over the structural limits of the compiler,
+  /// like the maximum nesting of terms and patterns of its definitions.
+  ///
+  /// The limit on the size of the generated nets is checked by [crate::hvm::check_net_size].
+  #[tracing::instrument(level = "debug", skip_all)]
+  pub fn check_limits(&mut self, limits: &Limits) -> Result<(), Diagnostics> {
+    if let Some(max) = limits.max_book_size {
+      let size = self.book.defs.len() + self.book.hvm_defs.len();
+      if size > max {
+        self.info.add_book_error(format!(
+          "The program has {size} definitions, more than the limit of {max} definitions."
+        ));
+      }
+    }
+
+    // The builtin definitions are trusted to be within the limits.
+    for def in self.book.defs.values().filter(|def| !def.is_builtin()) {
+      if let Some(max) = limits.max_term_depth {
+        if def.rules.iter().any(|rule| rule.body.depth_exceeds(max)) {
+          self.info.add_function_error(
+            format!("Definition body is nested more than {max} levels deep."),
+            def.name.clone(),
+            def.source.clone(),
+          );
+        }
+      }
+
+      if let Some(max) = limits.max_pattern_depth {
+        let mut pats = def.rules.iter().flat_map(|rule| &rule.pats).collect::<Vec<_>>();
+        pats.extend(def.rules.iter().flat_map(|rule| rule.body.binder_patterns()));
+        if pats.into_iter().any(|pat| pat.depth_exceeds(max)) {
+          self.info.add_function_error(
+            format!("Pattern is nested more than {max} levels deep."),
+            def.name.clone(),
+            def.source.clone(),
+          );
+        }
+      }
+    }
+
+    self.info.fatal(())
+  }
+}
+
+impl Term {
+  /// Whether the term has more than `max` nested levels, without recursion on the term.
+  fn depth_exceeds(&self, max: usize) -> bool {
+    let mut to_visit = vec![(self, 1)];
+    while let Some((term, depth)) = to_visit.pop() {
+      if depth > max {
+        return true;
+      }
+      to_visit.extend(term.children().map(|child| (child, depth + 1)));
+    }
+    false
+  }
+
+  /// The patterns of the lambdas and lets inside the term.
+  fn binder_patterns(&self) -> Vec<&Pattern> {
+    let mut pats = vec![];
+    let mut to_visit = vec![self];
+    while let Some(term) = to_visit.pop() {
+      pats.extend(term.pattern());
+      to_visit.extend(term.children());
+    }
+    pats
+  }
+}
+
+impl Pattern {
+  fn depth_exceeds(&self, max: usize) -> bool {
+    let mut to_visit = vec![(self, 1)];
+    while let Some((pat, depth)) = to_visit.pop() {
+      if depth > max {
+        return true;
+      }
+      to_visit.extend(pat.children().map(|child| (child, depth + 1)));
+    }
+    false
+  }
+}
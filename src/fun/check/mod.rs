@@ -1,4 +1,8 @@
+pub mod bool_operands;
 pub mod check_untyped;
+pub mod deprecated_refs;
+pub mod division_by_zero;
+pub mod limits;
 pub mod set_entrypoint;
 pub mod shared_names;
 pub mod type_check;
@@ -12,6 +12,7 @@ pub enum EntryErr {
 }
 
 impl Ctx<'_> {
+  #[tracing::instrument(level = "debug", skip_all)]
   pub fn set_entrypoint(&mut self) {
     let mut entrypoint = None;
 
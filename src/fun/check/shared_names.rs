@@ -12,6 +12,7 @@ pub struct RepeatedTopLevelNameErr {
 impl Ctx<'_> {
   /// Checks if there are any repeated top level names. Constructors
   /// and functions can't share names and adts can't share names.
+  #[tracing::instrument(level = "debug", skip_all)]
   pub fn check_shared_names(&mut self) {
     let mut names = NameInfo::default();
 
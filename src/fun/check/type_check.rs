@@ -4,12 +4,16 @@
 //! and https://github.com/mgrabmueller/AlgorithmW.
 use crate::{
   diagnostics::Diagnostics,
-  fun::{num_to_name, Adt, Book, Ctx, FanKind, MatchRule, Name, Num, Op, Pattern, Tag, Term, Type},
+  fun::{
+    num_to_name, Adt, Book, Ctx, FanKind, MatchRule, MatchTerm, Name, Num, Op, Pattern, SwitchTerm, Tag,
+    Term, Type,
+  },
   maybe_grow,
 };
 use std::collections::{BTreeMap, BTreeSet, HashMap};
 
 impl Ctx<'_> {
+  #[tracing::instrument(level = "debug", skip_all)]
   pub fn type_check(&mut self) -> Result<(), Diagnostics> {
     let types = infer_book(self.book, &mut self.info)?;
 
@@ -442,7 +446,8 @@ fn infer(
       _ => unreachable!(),
     },
 
-    Term::Mat { bnd: _, arg, with_bnd: _, with_arg: _, arms } => {
+    Term::Mat(mat) => {
+      let MatchTerm { bnd: _, arg, with_bnd: _, with_arg: _, arms } = mat.as_ref();
       // Infer type of the scrutinee
       let (s1, t1) = infer(env, book, types, arg, var_gen)?;
 
@@ -469,6 +474,14 @@ fn infer(
       };
       Ok((Subst::default(), t))
     }
+    // A `0` subtracted from, like in the negation `-x`, is the same for every type of number,
+    // so it has the type of the other operand.
+    Term::Oper { opr: Op::SUB, fst, snd } if matches!(fst.as_ref(), Term::Num { val: Num::U24(0) }) => {
+      let (s1, t1) = infer(env, book, types, snd, var_gen)?;
+      let (t, s2) = unify_term(&t1, &Type::Number(Box::new(var_gen.fresh())), term)?;
+      let t = t.subst(&s2);
+      Ok((s2.compose(s1), t))
+    }
     Term::Oper { opr, fst, snd } => {
       let (s1, t1) = infer(env, book, types, fst, var_gen)?;
       let (s2, t2) = infer(&mut env.subst(&s1), book, types, snd, var_gen)?;
@@ -496,7 +509,8 @@ fn infer(
       let t = t_opr.subst(&s_opr);
       Ok((s_opr.compose(s_args), t))
     }
-    Term::Swt { bnd: _, arg, with_bnd: _, with_arg: _, pred, arms } => {
+    Term::Swt(swt) => {
+      let SwitchTerm { bnd: _, arg, with_bnd: _, with_arg: _, pred, arms } = swt.as_ref();
       let (s1, t1) = infer(env, book, types, arg, var_gen)?;
       let (_, s2) = unify_term(&t1, &Type::U24, arg)?;
       let s_arg = s2.compose(s1);
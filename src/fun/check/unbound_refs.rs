@@ -1,23 +1,28 @@
 use crate::{
-  diagnostics::Diagnostics,
+  diagnostics::{closest_name, Diagnostics},
   fun::{Book, Ctx, Name, Term},
   maybe_grow,
 };
-use std::collections::HashSet;
+use indexmap::IndexSet;
 
 impl Ctx<'_> {
+  #[tracing::instrument(level = "debug", skip_all)]
   pub fn check_unbound_refs(&mut self) -> Result<(), Diagnostics> {
     for def in self.book.defs.values() {
-      let mut unbounds = HashSet::new();
+      let mut unbounds = IndexSet::new();
       for rule in def.rules.iter() {
         rule.body.check_unbound_refs(self.book, &mut unbounds);
       }
       for unbound in unbounds {
-        self.info.add_function_error(
-          format!("Reference to undefined function '{unbound}'"),
-          def.name.clone(),
-          def.source.clone(),
-        );
+        let names =
+          self.book.defs.keys().chain(self.book.hvm_defs.keys()).filter(|name| !name.is_generated());
+        let err = match closest_name(&unbound, names) {
+          Some(suggestion) => {
+            format!("Reference to undefined function '{unbound}'. Did you mean '{suggestion}'?")
+          }
+          None => format!("Reference to undefined function '{unbound}'"),
+        };
+        self.info.add_function_error(err, def.name.clone(), def.source.clone());
       }
     }
     self.info.fatal(())
@@ -25,7 +30,7 @@ impl Ctx<'_> {
 }
 
 impl Term {
-  pub fn check_unbound_refs(&self, book: &Book, unbounds: &mut HashSet<Name>) {
+  pub fn check_unbound_refs(&self, book: &Book, unbounds: &mut IndexSet<Name>) {
     maybe_grow(|| {
       if let Term::Ref { nam } = self {
         if !(book.defs.contains_key(nam) || book.hvm_defs.contains_key(nam)) {
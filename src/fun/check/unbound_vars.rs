@@ -1,19 +1,39 @@
 use crate::{
-  diagnostics::Diagnostics,
-  fun::{transform::desugar_bend, Ctx, Name, Pattern, Term},
+  diagnostics::{closest_name, Diagnostics, TextSpan},
+  fun::{transform::desugar_bend, Book, Ctx, Definition, Name, Pattern, SourceKind, Term},
   maybe_grow,
 };
+use itertools::Itertools;
 use std::collections::HashMap;
 
 #[derive(Debug, Clone)]
 pub enum UnboundVarErr {
-  Local(Name),
-  Global { var: Name, declared: usize, used: usize },
+  Local(Name, Option<Name>, Option<TextSpan>),
+  /// `other_def` is another definition with the missing half of the pair, for unbound or unused unscoped variables.
+  Global {
+    var: Name,
+    declared: usize,
+    used: usize,
+    other_def: Option<Name>,
+  },
 }
 
 impl Ctx<'_> {
   /// Checks that there are no unbound variables in all definitions.
+  ///
+  /// Unscoped variables and lambdas must be paired in the same definition,
+  /// if the missing half of a pair is in another definition, the error points to it.
+  /// Unbound variables that are misspellings of a definition or constructor suggest it.
+  #[tracing::instrument(level = "debug", skip_all)]
   pub fn check_unbound_vars(&mut self) -> Result<(), Diagnostics> {
+    let top_level_names = (self.book.defs.keys())
+      .chain(self.book.hvm_defs.keys())
+      .chain(self.book.ctrs.keys())
+      .filter(|name| !name.is_generated())
+      .cloned()
+      .collect::<Vec<_>>();
+
+    let mut def_errs = Vec::new();
     for (def_name, def) in self.book.defs.iter_mut() {
       let mut errs = Vec::new();
       for rule in &mut def.rules {
@@ -21,9 +41,57 @@ impl Ctx<'_> {
         let mut scope = rule.pats.iter().flat_map(|pat| pat.binds()).map(|x| x.as_ref()).collect::<Vec<_>>();
         rule.body.check_unbound_vars(&mut scope, &mut errs);
       }
+      if !errs.is_empty() {
+        def_errs.push((def_name.clone(), def.source.clone(), errs));
+      }
+    }
 
+    // The definitions with unscoped lambdas without variables and with unscoped variables without lambdas.
+    let mut unused_lams = HashMap::new();
+    let mut unbound_vars = HashMap::new();
+    for (def_name, _, errs) in &def_errs {
       for err in errs {
-        self.info.add_function_error(err, def_name.clone(), def.source.clone());
+        match err {
+          UnboundVarErr::Global { var, declared: 0, .. } => {
+            _ = unbound_vars.entry(var.clone()).or_insert(def_name.clone())
+          }
+          UnboundVarErr::Global { var, used: 0, .. } => {
+            _ = unused_lams.entry(var.clone()).or_insert(def_name.clone())
+          }
+          _ => {}
+        }
+      }
+    }
+
+    for (def_name, source, errs) in def_errs {
+      let def = &self.book.defs[&def_name];
+      let locals = errs.iter().filter_map(|err| match err {
+        UnboundVarErr::Local(var, ..) => Some(var.clone()),
+        UnboundVarErr::Global { .. } => None,
+      });
+      let counts = locals.counts();
+      let mut uses = HashMap::<Name, usize>::new();
+      for mut err in errs {
+        if let UnboundVarErr::Global { var, declared, used, other_def } = &mut err {
+          let others = match (declared, used) {
+            (0, _) => Some(&unused_lams),
+            (_, 0) => Some(&unbound_vars),
+            _ => None,
+          };
+          let other = others.and_then(|others| others.get(var)).filter(|other| **other != def_name);
+          *other_def = other.map(|other| (*other).clone());
+        }
+        // Suggests a definition with a similar name, if no variable in scope was similar enough.
+        if let UnboundVarErr::Local(var, suggestion, span) = &mut err {
+          if suggestion.is_none() {
+            *suggestion = closest_name(var, &top_level_names).cloned();
+          }
+          // The uses are checked in the order they're written.
+          let nth = uses.entry(var.clone()).or_default();
+          *span = self.book.unbound_var_span(def, var, *nth, counts[var]);
+          *nth += 1;
+        }
+        self.info.add_function_error(err, def_name.clone(), source.clone());
       }
     }
 
@@ -31,6 +99,27 @@ impl Ctx<'_> {
   }
 }
 
+impl Book {
+  /// Where the `nth` of the `count` unbound uses of `var` in `def` is written,
+  /// from the names of the definition written by the user that `def` is or was generated from.
+  ///
+  /// The uses can only be told apart when `var` isn't bound in that definition,
+  /// and when it's used only once or only the uses in `def`.
+  fn unbound_var_span(&self, def: &Definition, var: &Name, nth: usize, count: usize) -> Option<TextSpan> {
+    let user_def = if def.source.kind == SourceKind::Generated { self.generated_from(def)? } else { def };
+    if user_def.names.iter().flat_map(|names| &names.binders).any(|(nam, _)| nam == var) {
+      return None;
+    }
+    let uses = user_def.names.iter().flat_map(|names| &names.vars).filter(|(nam, _)| nam == var);
+    let uses = uses.map(|(_, span)| *span).collect::<Vec<_>>();
+    match uses.as_slice() {
+      [span] => Some(*span),
+      _ if std::ptr::eq(user_def, def) && uses.len() == count => uses.get(nth).copied(),
+      _ => None,
+    }
+  }
+}
+
 impl Term {
   /// Checks that all variables are bound.
   /// Precondition: References have been resolved, implicit binds have been solved.
@@ -44,7 +133,7 @@ impl Term {
 
     // Check global vars
     for (nam, (declared, used)) in globals.into_iter().filter(|(_, (d, u))| !(*d == 1 && *u == 1)) {
-      errs.push(UnboundVarErr::Global { var: nam.clone(), declared, used });
+      errs.push(UnboundVarErr::Global { var: nam.clone(), declared, used, other_def: None });
     }
   }
 }
@@ -60,7 +149,8 @@ pub fn check_uses<'a>(
   maybe_grow(move || match term {
     Term::Var { nam } => {
       if !scope_contains(nam, scope) {
-        errs.push(UnboundVarErr::Local(nam.clone()));
+        let suggestion = closest_name(nam, scope.iter().flatten().copied()).cloned();
+        errs.push(UnboundVarErr::Local(nam.clone(), suggestion, None));
         *term = Term::Err;
       }
     }
@@ -105,26 +195,43 @@ fn scope_contains(nam: &Name, scope: &[Option<&Name>]) -> bool {
 impl std::fmt::Display for UnboundVarErr {
   fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
     match self {
-      UnboundVarErr::Local(var) => {
+      UnboundVarErr::Local(var, suggestion, span) => {
+        let at =
+          span.map(|span| format!(", at line {}, column {}", span.start.line + 1, span.start.char + 1));
+        let at = at.unwrap_or_default();
         if var == desugar_bend::RECURSIVE_KW {
           write!(
             f,
-            "Unbound variable '{}'.\n    Note: '{}' is only a keyword inside the 'when' arm of a 'bend'.",
+            "Unbound variable '{}'{at}.\n    Note: '{}' is only a keyword inside the 'when' arm of a 'bend'.",
             var,
             desugar_bend::RECURSIVE_KW
           )
         } else if let Some((pre, suf)) = var.rsplit_once('-') {
           write!(
             f,
-            "Unbound variable '{var}'. If you wanted to subtract '{pre}' from '{suf}', you must separate it with spaces ('{pre} - {suf}') since '-' is a valid name character."
+            "Unbound variable '{var}'{at}. If you wanted to subtract '{pre}' from '{suf}', you must separate it with spaces ('{pre} - {suf}') since '-' is a valid name character."
           )
+        } else if let Some(suggestion) = suggestion {
+          write!(f, "Unbound variable '{var}'{at}. Did you mean '{suggestion}'?")
         } else {
-          write!(f, "Unbound variable '{var}'.")
+          write!(f, "Unbound variable '{var}'{at}.")
         }
       }
-      UnboundVarErr::Global { var, declared, used } => match (declared, used) {
-        (0, _) => write!(f, "Unbound unscoped variable '${var}'."),
-        (_, 0) => write!(f, "Unscoped variable from lambda 'λ${var}' is never used."),
+      UnboundVarErr::Global { var, declared, used, other_def } => match (declared, used) {
+        (0, _) => {
+          write!(f, "Unbound unscoped variable '${var}'.")?;
+          if let Some(other) = other_def {
+            write!(f, " Its lambda 'λ${var}' is in the definition '{other}', but unscoped variables can't be shared between definitions.")?;
+          }
+          Ok(())
+        }
+        (_, 0) => {
+          write!(f, "Unscoped variable from lambda 'λ${var}' is never used.")?;
+          if let Some(other) = other_def {
+            write!(f, " The variable '${var}' is used in the definition '{other}', but unscoped variables can't be shared between definitions.")?;
+          }
+          Ok(())
+        }
         (1, _) => write!(f, "Unscoped variable '${var}' used more than once."),
         (_, 1) => write!(f, "Unscoped lambda 'λ${var}' declared more than once."),
         (_, _) => {
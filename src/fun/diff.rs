@@ -0,0 +1,136 @@
+use crate::{
+  diagnostics::{Diagnostics, DiagnosticsConfig},
+  fun::{Adt, Book, Ctx, Definition, Name, Rule, Term, Type},
+};
+use indexmap::IndexMap;
+use std::fmt;
+
+/// The definitions and types that differ between two versions of a program, see [`Book::diff`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BookDiff {
+  /// Only in the new program.
+  pub added: Vec<Name>,
+  /// Only in the old program.
+  pub removed: Vec<Name>,
+  /// In both programs, but with a different meaning.
+  pub changed: Vec<Name>,
+}
+
+impl BookDiff {
+  pub fn is_empty(&self) -> bool {
+    self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+  }
+}
+
+impl Book {
+  /// Compares the definitions and types of two programs, ignoring the builtins.
+  ///
+  /// Definitions are compared up to the names of their variables and their formatting,
+  /// so a definition that was only reformatted, had its variables renamed or was rewritten
+  /// from one syntax to the other is not reported as changed.
+  /// The local definitions of a function are compared as part of it.
+  pub fn diff(&self, new: &Book, diagnostics_cfg: DiagnosticsConfig) -> Result<BookDiff, Diagnostics> {
+    let old = self.canonical_items(diagnostics_cfg)?;
+    let new = new.canonical_items(diagnostics_cfg)?;
+
+    let mut diff = BookDiff::default();
+    for (name, old_item) in old.iter() {
+      match new.get(name) {
+        None => diff.removed.push(name.clone()),
+        Some(new_item) if new_item != old_item => diff.changed.push(name.clone()),
+        Some(_) => {}
+      }
+    }
+    diff.added = new.keys().filter(|name| !old.contains_key(*name)).cloned().collect();
+    Ok(diff)
+  }
+
+  /// A canonical text for each definition and type of the program,
+  /// which is equal for two items if and only if they have the same meaning.
+  fn canonical_items(
+    &self,
+    diagnostics_cfg: DiagnosticsConfig,
+  ) -> Result<IndexMap<Name, String>, Diagnostics> {
+    let mut book = self.clone();
+    let mut ctx = Ctx::new(&mut book, diagnostics_cfg);
+    ctx.desugar_open()?;
+    // Binds the fields of the matches and puts their arms in the order of the constructors.
+    ctx.fix_match_terms()?;
+    ctx.book.lift_local_defs();
+
+    let mut items = IndexMap::new();
+    for adt in book.adts.values().filter(|adt| !adt.source.is_builtin()) {
+      items.insert(adt.name.clone(), adt.canonical());
+    }
+    for def in book.defs.values().filter(|def| !def.is_builtin()) {
+      // The local definitions lifted out of a function are part of it.
+      let name = book.generated_from(def).map_or(&def.name, |parent| &parent.name).clone();
+      let text: &mut String = items.entry(name).or_default();
+      text.push_str(&def.canonical().to_string());
+      text.push('\n');
+    }
+    for def in book.hvm_defs.values().filter(|def| !def.source.is_builtin()) {
+      items.insert(def.name.clone(), format!("hvm {}", def.body.show()));
+    }
+    Ok(items)
+  }
+}
+
+impl Definition {
+  /// The definition with each rule as a single lambda term, with variables named by the order they're bound.
+  fn canonical(&self) -> Definition {
+    let rules = self
+      .rules
+      .iter()
+      .map(|rule| {
+        let mut body = rule.pats.iter().rfold(rule.body.clone(), |body, pat| Term::lam(pat.clone(), body));
+        body.make_var_names_unique();
+        Rule { pats: vec![], body }
+      })
+      .collect();
+    // Functions without a type annotation get one with a parameter of type `Any` for each argument.
+    let typ = if self.typ.is_untyped() { Type::Any } else { self.typ.clone() };
+    Definition { rules, typ, ..self.clone() }
+  }
+}
+
+impl Type {
+  fn is_untyped(&self) -> bool {
+    match self {
+      Type::Any | Type::Hole => true,
+      Type::Arr(lft, rgt) => lft.is_untyped() && rgt.is_untyped(),
+      _ => false,
+    }
+  }
+}
+
+impl Adt {
+  fn canonical(&self) -> String {
+    let mut text = format!("type {} {:?}", self.name, self.vars);
+    for ctr in self.ctrs.values() {
+      text.push_str(&format!("\n{}: {}", ctr.name, ctr.typ));
+      for field in ctr.fields.iter() {
+        text.push_str(&format!(" {}{}: {}", if field.rec { "~" } else { "" }, field.nam, field.typ));
+      }
+    }
+    text
+  }
+}
+
+impl fmt::Display for BookDiff {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    if self.is_empty() {
+      return writeln!(f, "No differences.");
+    }
+    for name in self.added.iter() {
+      writeln!(f, "+ {name}")?;
+    }
+    for name in self.removed.iter() {
+      writeln!(f, "- {name}")?;
+    }
+    for name in self.changed.iter() {
+      writeln!(f, "~ {name}")?;
+    }
+    Ok(())
+  }
+}
@@ -1,4 +1,7 @@
-use super::{Book, Definition, FanKind, Name, Num, Op, Pattern, Rule, Tag, Term, Type};
+use super::{
+  BendTerm, Book, CtrField, Definition, FanKind, MatchTerm, Name, Num, Op, Pattern, Rule, SwitchTerm, Tag,
+  Term, Type,
+};
 use crate::maybe_grow;
 use std::{fmt, ops::Deref, sync::atomic::AtomicU64};
 
@@ -74,7 +77,8 @@ impl fmt::Display for Term {
       Term::App { tag, fun, arg } => {
         write!(f, "{}({} {})", tag.display_padded(), fun.display_app(tag), arg)
       }
-      Term::Mat { arg, bnd, with_bnd, with_arg, arms } => {
+      Term::Mat(mat) => {
+        let MatchTerm { arg, bnd, with_bnd, with_arg, arms } = mat.as_ref();
         write!(f, "match ")?;
         if let Some(bnd) = bnd {
           write!(f, "{} = ", bnd)?;
@@ -96,7 +100,8 @@ impl fmt::Display for Term {
         }
         write!(f, "}}")
       }
-      Term::Swt { arg, bnd, with_bnd, with_arg, pred, arms } => {
+      Term::Swt(swt) => {
+        let SwitchTerm { arg, bnd, with_bnd, with_arg, pred, arms } = swt.as_ref();
         write!(f, "switch ")?;
         if let Some(bnd) = bnd {
           write!(f, "{bnd} = ")?;
@@ -122,7 +127,8 @@ impl fmt::Display for Term {
         }
         write!(f, "}}")
       }
-      Term::Fold { bnd, arg, with_bnd, with_arg, arms } => {
+      Term::Fold(fold) => {
+        let MatchTerm { bnd, arg, with_bnd, with_arg, arms } = fold.as_ref();
         write!(f, "fold ")?;
         if let Some(bnd) = bnd {
           write!(f, "{} = ", bnd)?;
@@ -144,7 +150,8 @@ impl fmt::Display for Term {
         }
         write!(f, "}}")
       }
-      Term::Bend { bnd: bind, arg: init, cond, step, base } => {
+      Term::Bend(bend) => {
+        let BendTerm { bnd: bind, arg: init, cond, step, base } = bend.as_ref();
         write!(f, "bend ")?;
         for (bind, init) in bind.iter().zip(init) {
           if let Some(bind) = bind {
@@ -348,6 +355,18 @@ impl Book {
   }
 }
 
+impl Book {
+  /// The type of each definition written in the main file, one per line, like `double: u24 -> u24`.
+  ///
+  /// After type checking, they're the types the checker gave to each definition,
+  /// the inferred types specialized to the annotations.
+  pub fn display_types(&self) -> impl fmt::Display + '_ {
+    let is_fun = |def: &&Definition| !def.name.is_generated() && !self.ctrs.contains_key(&def.name);
+    let defs = move || self.defs.values().filter(|def| def.source.is_local()).filter(is_fun);
+    DisplayJoin(move || defs().map(|def| display!("{}: {}", def.name, def.typ)), "\n")
+  }
+}
+
 impl Definition {
   pub fn display_pretty(&self) -> impl fmt::Display + '_ {
     namegen_reset();
@@ -443,7 +462,8 @@ impl Term {
         Term::Oper { opr, fst, snd } => {
           write!(f, "({} {} {})", opr, fst.display_pretty(tab), snd.display_pretty(tab))
         }
-        Term::Mat { bnd, arg, with_bnd, with_arg, arms } => {
+        Term::Mat(mat) => {
+          let MatchTerm { bnd, arg, with_bnd, with_arg, arms } = mat.as_ref();
           write!(f, "match ")?;
           if let Some(bnd) = bnd {
             write!(f, "{} = ", bnd)?;
@@ -465,7 +485,8 @@ impl Term {
           }
           write!(f, "\n{:tab$}}}", "")
         }
-        Term::Swt { bnd, arg, with_bnd, with_arg, pred, arms } => {
+        Term::Swt(swt) => {
+          let SwitchTerm { bnd, arg, with_bnd, with_arg, pred, arms } = swt.as_ref();
           write!(f, "switch ")?;
           if let Some(bnd) = bnd {
             write!(f, "{bnd} = ")?;
@@ -491,7 +512,8 @@ impl Term {
           }
           write!(f, "{:tab$}}}", "")
         }
-        Term::Fold { bnd, arg, with_bnd, with_arg, arms } => {
+        Term::Fold(fold) => {
+          let MatchTerm { bnd, arg, with_bnd, with_arg, arms } = fold.as_ref();
           write!(f, "fold ")?;
           if let Some(bnd) = bnd {
             write!(f, "{} = ", bnd)?;
@@ -513,7 +535,8 @@ impl Term {
           }
           write!(f, "\n{:tab$}}}", "")
         }
-        Term::Bend { bnd: bind, arg: init, cond, step, base } => {
+        Term::Bend(bend) => {
+          let BendTerm { bnd: bind, arg: init, cond, step, base } = bend.as_ref();
           write!(f, "bend ")?;
           for (bind, init) in bind.iter().zip(init) {
             if let Some(bind) = bind {
@@ -565,3 +588,84 @@ impl Term {
     })
   }
 }
+
+impl Term {
+  /// Displays a result as a JSON value, for other programs to read.
+  ///
+  /// Numbers, strings, lists and tuples become their JSON equivalents, erasers become `null`
+  /// and num-scott encoded constructors become objects with a `$ctr` key and one key for each field.
+  /// Anything else, like lambdas, becomes an object with the term in a `$term` key.
+  pub fn display_json<'a>(&'a self, book: &'a Book) -> impl fmt::Display + 'a {
+    maybe_grow(|| {
+      DisplayFn(move |f| match self {
+        Term::Num { val: Num::U24(val) } => write!(f, "{val}"),
+        Term::Num { val: Num::I24(val) } => write!(f, "{val}"),
+        Term::Num { val: Num::F24(val) } if val.is_finite() => write!(f, "{val}"),
+        Term::Str { val } => write!(f, "{}", json_str(val)),
+        Term::List { els } | Term::Fan { fan: FanKind::Tup, tag: Tag::Static, els } => {
+          write!(f, "[{}]", DisplayJoin(|| els.iter().map(|el| el.display_json(book)), ", "))
+        }
+        Term::Era => write!(f, "null"),
+        _ => match self.as_num_scott_ctr(book) {
+          Some((ctr, fields)) => {
+            write!(f, "{{\"$ctr\": {}", json_str(&ctr))?;
+            for (field, val) in fields {
+              write!(f, ", {}: {}", json_str(&field.nam), val.display_json(book))?;
+            }
+            write!(f, "}}")
+          }
+          None => write!(f, "{{\"$term\": {}}}", json_str(&self.to_string())),
+        },
+      })
+    })
+  }
+
+  /// If the term is a constructor `λx (x Ctr/tag f0 .. fn)` of the num-scott encoding,
+  /// or a reference to a constructor without fields, returns it with its fields.
+  pub(crate) fn as_num_scott_ctr<'a>(
+    &'a self,
+    book: &'a Book,
+  ) -> Option<(Name, Vec<(&'a CtrField, &'a Term)>)> {
+    let (ctr, mut vals) = match self {
+      Term::Ref { nam } => (nam.clone(), vec![]),
+      Term::Lam { tag: Tag::Static, pat, bod } => {
+        let Pattern::Var(Some(var)) = pat.as_ref() else { return None };
+        let mut vals = vec![];
+        let mut app = bod.as_ref();
+        while let Term::App { tag: Tag::Static, fun, arg } = app {
+          vals.push(arg.as_ref());
+          app = fun;
+        }
+        let (Term::Var { nam }, Some(Term::Ref { nam: tag })) = (app, vals.pop()) else { return None };
+        if nam != var {
+          return None;
+        }
+        (Name::new(tag.strip_suffix("/tag")?), vals)
+      }
+      _ => return None,
+    };
+    let fields = &book.adts.get(book.ctrs.get(&ctr)?)?.ctrs.get(&ctr)?.fields;
+    if fields.len() != vals.len() {
+      return None;
+    }
+    vals.reverse();
+    Some((ctr, fields.iter().zip(vals).collect()))
+  }
+}
+
+fn json_str(s: &str) -> String {
+  let mut out = String::from('"');
+  for c in s.chars() {
+    match c {
+      '"' => out.push_str("\\\""),
+      '\\' => out.push_str("\\\\"),
+      '\n' => out.push_str("\\n"),
+      '\t' => out.push_str("\\t"),
+      '\r' => out.push_str("\\r"),
+      c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+      c => out.push(c),
+    }
+  }
+  out.push('"');
+  out
+}
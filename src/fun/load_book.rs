@@ -4,9 +4,15 @@ use super::{
 };
 use crate::{
   diagnostics::{Diagnostics, DiagnosticsConfig, TextSpan},
-  imports::PackageLoader,
+  imports::{normalize_path, PackageLoader},
+  LoadOpts, Prelude,
+};
+use highlight_error::highlight_error;
+use std::{
+  collections::HashSet,
+  ops::Range,
+  path::{Path, PathBuf},
 };
-use std::path::Path;
 
 // TODO: Refactor so that we don't mix the two syntaxes here.
 
@@ -15,6 +21,17 @@ pub fn load_file_to_book(
   path: &Path,
   package_loader: impl PackageLoader,
   diag: DiagnosticsConfig,
+) -> Result<Book, Diagnostics> {
+  load_file_to_book_with_opts(path, package_loader, diag, LoadOpts::default())
+}
+
+/// Reads a file and parses to a definition book, with the prelude and `cfg` flags of `opts`.
+#[tracing::instrument(level = "info", skip_all, fields(path = %path.display()))]
+pub fn load_file_to_book_with_opts(
+  path: &Path,
+  package_loader: impl PackageLoader,
+  diag: DiagnosticsConfig,
+  opts: LoadOpts,
 ) -> Result<Book, Diagnostics> {
   match path.try_exists() {
     Ok(exists) => {
@@ -22,7 +39,7 @@ pub fn load_file_to_book(
         return Err(format!("The file '{}' was not found.", path.display()).into());
       }
       let code = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
-      load_to_book(path, &code, package_loader, diag)
+      load_to_book_with_opts(path, &code, package_loader, diag, opts)
     }
     Err(e) => Err(e.to_string().into()),
   }
@@ -34,19 +51,126 @@ pub fn load_to_book(
   package_loader: impl PackageLoader,
   diag: DiagnosticsConfig,
 ) -> Result<Book, Diagnostics> {
-  let builtins = ParseBook::builtins();
-  let book = do_parse_book(code, origin, builtins)?;
+  load_to_book_with_opts(origin, code, package_loader, diag, LoadOpts::default())
+}
+
+/// Parses the code of a file to a definition book, with the prelude and `cfg` flags of `opts`.
+pub fn load_to_book_with_opts(
+  origin: &Path,
+  code: &str,
+  package_loader: impl PackageLoader,
+  diag: DiagnosticsConfig,
+  opts: LoadOpts,
+) -> Result<Book, Diagnostics> {
+  let mut prelude = match opts.prelude {
+    Prelude::Builtin => ParseBook::builtins(),
+    Prelude::None => ParseBook::default(),
+    Prelude::File(path) => load_prelude(&path)?,
+  };
+  prelude.cfg = opts.cfg.into_iter().collect();
+  let book = do_parse_book(code, origin, prelude)?;
   book.load_imports(package_loader, diag)
 }
 
-pub fn do_parse_book(code: &str, origin: &Path, mut book: ParseBook) -> Result<ParseBook, Diagnostics> {
+/// Parses a file that replaces the builtin definitions, which are treated as builtins themselves.
+fn load_prelude(path: &Path) -> Result<ParseBook, Diagnostics> {
+  let code = std::fs::read_to_string(path)
+    .map_err(|err| format!("Couldn't read the prelude file '{}': {err}.", path.display()))?;
+  let file = Name::new(path.to_string_lossy());
+  FunParser::new(file, &code, true)
+    .parse_book(ParseBook::default())
+    .map_err(|err| file_error(err.to_string(), &code, path, err.span.0..err.span.1))
+}
+
+#[tracing::instrument(level = "debug", skip_all, fields(origin = %origin.display()))]
+pub fn do_parse_book(code: &str, origin: &Path, book: ParseBook) -> Result<ParseBook, Diagnostics> {
+  let (book, mut diagnostics) = do_parse_book_recovering(code, origin, book);
+  diagnostics.fatal(book)
+}
+
+/// Parses a file like [`do_parse_book`], but also returns the book of the definitions that were parsed
+/// when there are syntax errors, for tools that can work on the rest of the file.
+///
+/// The returned diagnostics have all the syntax errors of the file and of the files it includes.
+pub fn do_parse_book_recovering(code: &str, origin: &Path, mut book: ParseBook) -> (ParseBook, Diagnostics) {
   book.source = Name::new(origin.to_string_lossy());
-  FunParser::new(book.source.clone(), code, false).parse_book(book).map_err(|err| {
-    let mut diagnostics = Diagnostics::default();
-    let span = TextSpan::from_byte_span(code, err.span.0..err.span.1);
-    let source =
-      Source { file: Some(origin.to_string_lossy().into()), span: Some(span), kind: SourceKind::User };
-    diagnostics.add_parsing_error(err, source);
-    diagnostics
-  })
+  let main = origin.canonicalize().ok();
+  let mut diagnostics = Diagnostics::default();
+  let book = parse_file(
+    code,
+    origin,
+    book,
+    &mut main.clone().into_iter().collect(),
+    &mut main.into_iter().collect(),
+    &mut diagnostics,
+  );
+  (book, diagnostics)
+}
+
+/// Parses a file into the book, followed by the files it includes with `#include "path"`,
+/// which are parsed as if their definitions were written in the including file.
+///
+/// The included paths are relative to the including file, and each file is only included once.
+/// `stack` has the files that are being included, to find cyclic includes.
+/// The errors are added to `diagnostics`, skipping the items and includes that failed.
+fn parse_file(
+  code: &str,
+  origin: &Path,
+  book: ParseBook,
+  stack: &mut Vec<PathBuf>,
+  included: &mut HashSet<PathBuf>,
+  diagnostics: &mut Diagnostics,
+) -> ParseBook {
+  let file = Name::new(origin.to_string_lossy());
+  // All the syntax errors of the file are reported together.
+  let (mut book, errs) = FunParser::new(file, code, false).parse_book_recovering(book);
+  for err in errs {
+    diagnostics.add_parsing_error(err.to_string(), file_source(code, origin, err.span.0..err.span.1));
+  }
+
+  let dir = origin.parent().unwrap_or(Path::new(""));
+  for (path, span) in std::mem::take(&mut book.includes) {
+    let path = normalize_path(&dir.join(path));
+    let not_found = format!("The included file '{}' was not found.", path.display());
+    let canonical = match path.canonicalize() {
+      Ok(canonical) => canonical,
+      Err(_) => {
+        diagnostics
+          .add_parsing_error(spanned(not_found, code, span.clone()), file_source(code, origin, span));
+        continue;
+      }
+    };
+    if stack.contains(&canonical) {
+      let msg = format!("The file '{}' includes itself through a cycle of includes.", path.display());
+      diagnostics.add_parsing_error(spanned(msg, code, span.clone()), file_source(code, origin, span));
+      continue;
+    }
+    if !included.insert(canonical.clone()) {
+      continue;
+    }
+    let Ok(included_code) = std::fs::read_to_string(&path) else {
+      diagnostics.add_parsing_error(spanned(not_found, code, span.clone()), file_source(code, origin, span));
+      continue;
+    };
+    stack.push(canonical);
+    book = parse_file(&included_code, &path, book, stack, included, diagnostics);
+    stack.pop();
+  }
+
+  book
+}
+
+fn spanned(msg: impl std::fmt::Display, code: &str, span: Range<usize>) -> String {
+  format!("{msg}\nLocation:\n{}", highlight_error(span.start, span.end, code))
+}
+
+fn file_error(msg: String, code: &str, origin: &Path, span: Range<usize>) -> Diagnostics {
+  let mut diagnostics = Diagnostics::default();
+  diagnostics.add_parsing_error(msg, file_source(code, origin, span));
+  diagnostics
+}
+
+fn file_source(code: &str, origin: &Path, span: Range<usize>) -> Source {
+  let span = TextSpan::from_byte_span(code, span);
+  Source { file: Some(origin.to_string_lossy().into()), span: Some(span), kind: SourceKind::User }
 }
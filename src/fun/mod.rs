@@ -1,7 +1,7 @@
 use crate::{
   diagnostics::{Diagnostics, DiagnosticsConfig, TextSpan},
   imports::Import,
-  maybe_grow, multi_iterator, ENTRY_POINT,
+  maybe_grow, multi_iterator,
 };
 use indexmap::{IndexMap, IndexSet};
 use interner::global::{GlobalPool, GlobalString};
@@ -14,6 +14,7 @@ use std::{
 
 pub mod builtins;
 pub mod check;
+pub mod diff;
 pub mod display;
 pub mod load_book;
 pub mod net_to_term;
@@ -57,6 +58,25 @@ pub struct Book {
 
   /// Imports declared in the program.
   pub imports: Vec<Import>,
+
+  /// Definitions marked as `macro`, that are expanded where they're called.
+  pub macros: IndexSet<Name>,
+
+  /// Definitions marked with `#[deprecated]`, with the message of the attribute.
+  pub deprecated: IndexMap<Name, Option<String>>,
+
+  /// Definitions marked with `#[test]`, that are run by `bend test`.
+  pub tests: IndexSet<Name>,
+
+  /// Definitions marked with `#[inline]` (`true`) or `#[noinline]` (`false`), see [crate::hvm::inline].
+  pub inline: IndexMap<Name, bool>,
+
+  /// Definitions generated from match arms when measuring coverage, with where each arm came from.
+  pub lifted_arms: IndexMap<Name, transform::lift_match_arms::LiftedArm>,
+
+  /// The definition and location of each `assert`, in the order of the indices of their values,
+  /// see [transform::desugar_assert].
+  pub asserts: Vec<(Name, String)>,
 }
 
 pub type Definitions = IndexMap<Name, Definition>;
@@ -64,6 +84,15 @@ pub type HvmDefinitions = IndexMap<Name, HvmDefinition>;
 pub type Adts = IndexMap<Name, Adt>;
 pub type Constructors = IndexMap<Name, Name>;
 
+/// The names written in a rule, with where each one is in its file.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct RuleNames {
+  /// The names that bind variables, in the order they're written.
+  pub binders: Vec<(Name, TextSpan)>,
+  /// The variables used in the terms, in the order they're written.
+  pub vars: Vec<(Name, TextSpan)>,
+}
+
 /// A pattern matching function definition.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Definition {
@@ -72,6 +101,9 @@ pub struct Definition {
   pub check: bool,
   pub rules: Vec<Rule>,
   pub source: Source,
+  /// The names written in each rule as it was parsed.
+  /// Empty for definitions that weren't parsed from a file, like the generated ones.
+  pub names: Vec<RuleNames>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -189,36 +221,11 @@ pub enum Term {
     snd: Box<Term>,
   },
   /// Pattern matching on an ADT.
-  Mat {
-    bnd: Option<Name>,
-    arg: Box<Term>,
-    with_bnd: Vec<Option<Name>>,
-    with_arg: Vec<Term>,
-    arms: Vec<MatchRule>,
-  },
+  Mat(Box<MatchTerm>),
   /// Native pattern matching on numbers
-  Swt {
-    bnd: Option<Name>,
-    arg: Box<Term>,
-    with_bnd: Vec<Option<Name>>,
-    with_arg: Vec<Term>,
-    pred: Option<Name>,
-    arms: Vec<Term>,
-  },
-  Fold {
-    bnd: Option<Name>,
-    arg: Box<Term>,
-    with_bnd: Vec<Option<Name>>,
-    with_arg: Vec<Term>,
-    arms: Vec<MatchRule>,
-  },
-  Bend {
-    bnd: Vec<Option<Name>>,
-    arg: Vec<Term>,
-    cond: Box<Term>,
-    step: Box<Term>,
-    base: Box<Term>,
-  },
+  Swt(Box<SwitchTerm>),
+  Fold(Box<MatchTerm>),
+  Bend(Box<BendTerm>),
   Open {
     typ: Name,
     var: Name,
@@ -228,7 +235,7 @@ pub enum Term {
     nam: Name,
   },
   Def {
-    def: Definition,
+    def: Box<Definition>,
     nxt: Box<Term>,
   },
   Era,
@@ -236,8 +243,48 @@ pub enum Term {
   Err,
 }
 
+// Terms are the most allocated nodes of the compiler, so their size shouldn't grow by accident.
+// The bound is the size of a tuple or superposition, with its tag and elements, the largest unboxed variant.
+// The variants with many fields, like the matches, `bend` and the `Definition` of a `def`, are boxed to stay under it.
+#[cfg(target_pointer_width = "64")]
+const _: () = assert!(std::mem::size_of::<Term>() <= 48);
+
+/// The fields of a `match` or a `fold` term, boxed to keep [`Term`] small.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct MatchTerm {
+  pub bnd: Option<Name>,
+  pub arg: Term,
+  pub with_bnd: Vec<Option<Name>>,
+  pub with_arg: Vec<Term>,
+  pub arms: Vec<MatchRule>,
+}
+
+/// The fields of a `switch` term, boxed to keep [`Term`] small.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct SwitchTerm {
+  pub bnd: Option<Name>,
+  pub arg: Term,
+  pub with_bnd: Vec<Option<Name>>,
+  pub with_arg: Vec<Term>,
+  pub pred: Option<Name>,
+  pub arms: Vec<Term>,
+}
+
+/// The fields of a `bend` term, boxed to keep [`Term`] small.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct BendTerm {
+  pub bnd: Vec<Option<Name>>,
+  pub arg: Vec<Term>,
+  pub cond: Term,
+  pub step: Term,
+  pub base: Term,
+}
+
 pub type MatchRule = (Option<Name>, Vec<Option<Name>>, Term);
 
+/// A `match` arm as written by the user, with an optional numeric guard.
+pub type GuardedMatchRule = (Option<Name>, Option<Term>, Term);
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum FanKind {
   Tup,
@@ -404,35 +451,10 @@ impl Clone for Term {
       Self::Str { val } => Self::Str { val: val.clone() },
       Self::List { els } => Self::List { els: els.clone() },
       Self::Oper { opr, fst, snd } => Self::Oper { opr: *opr, fst: fst.clone(), snd: snd.clone() },
-      Self::Mat { arg, bnd, with_bnd, with_arg, arms } => Self::Mat {
-        arg: arg.clone(),
-        bnd: bnd.clone(),
-        with_bnd: with_bnd.clone(),
-        with_arg: with_arg.clone(),
-        arms: arms.clone(),
-      },
-      Self::Swt { arg, bnd, with_bnd, with_arg, pred, arms } => Self::Swt {
-        arg: arg.clone(),
-        bnd: bnd.clone(),
-        with_bnd: with_bnd.clone(),
-        with_arg: with_arg.clone(),
-        pred: pred.clone(),
-        arms: arms.clone(),
-      },
-      Self::Fold { bnd, arg, with_bnd, with_arg, arms } => Self::Fold {
-        bnd: bnd.clone(),
-        arg: arg.clone(),
-        with_bnd: with_bnd.clone(),
-        with_arg: with_arg.clone(),
-        arms: arms.clone(),
-      },
-      Self::Bend { bnd: bind, arg: init, cond, step, base } => Self::Bend {
-        bnd: bind.clone(),
-        arg: init.clone(),
-        cond: cond.clone(),
-        step: step.clone(),
-        base: base.clone(),
-      },
+      Self::Mat(mat) => Self::Mat(mat.clone()),
+      Self::Swt(swt) => Self::Swt(swt.clone()),
+      Self::Fold(fold) => Self::Fold(fold.clone()),
+      Self::Bend(bend) => Self::Bend(bend.clone()),
       Self::Open { typ, var, bod: nxt } => {
         Self::Open { typ: typ.clone(), var: var.clone(), bod: nxt.clone() }
       }
@@ -522,6 +544,30 @@ impl Term {
     Term::App { tag, fun: Box::new(fun), arg: Box::new(arg) }
   }
 
+  /// The short-circuiting `lhs && rhs`, that is 0 if `lhs` is 0 and `rhs` otherwise.
+  pub fn and(lhs: Term, rhs: Term) -> Self {
+    Term::Swt(Box::new(SwitchTerm {
+      arg: lhs,
+      bnd: Some(Name::new("%and")),
+      with_bnd: vec![],
+      with_arg: vec![],
+      pred: Some(Name::new("%and-1")),
+      arms: vec![Term::Num { val: Num::U24(0) }, rhs],
+    }))
+  }
+
+  /// The short-circuiting `lhs || rhs`, that is `rhs` if `lhs` is 0 and 1 otherwise.
+  pub fn or(lhs: Term, rhs: Term) -> Self {
+    Term::Swt(Box::new(SwitchTerm {
+      arg: lhs,
+      bnd: Some(Name::new("%or")),
+      with_bnd: vec![],
+      with_arg: vec![],
+      pred: Some(Name::new("%or-1")),
+      arms: vec![rhs, Term::Num { val: Num::U24(1) }],
+    }))
+  }
+
   /// Make a call term by folding args around a called function term with applications.
   pub fn call(called: Term, args: impl IntoIterator<Item = Term>) -> Self {
     args.into_iter().fold(called, Term::app)
@@ -560,6 +606,121 @@ impl Term {
     }
   }
 
+  /// Builds a `match` term whose arms may have numeric guards.
+  ///
+  /// A guarded arm only matches if its guard is not 0, otherwise the matching falls through
+  /// to the arms that come after it. Guards are compiled to numeric switches over the guard,
+  /// matching the scrutinee again for the arms that follow:
+  /// ```hvm
+  /// match x { A: a; B if g: b; B: c; C: d }
+  ///
+  /// match x {
+  ///   A: a;
+  ///   *: switch (match x { B: g; *: 0 }) {
+  ///     0: match x { B: c; C: d };
+  ///     _: match x { B: b; *: unreachable };
+  ///   }
+  /// }
+  /// ```
+  /// If no arm matches after a failed guard, the result is `unreachable`.
+  pub fn guarded_match(
+    arg: Term,
+    bnd: Option<Name>,
+    with_bnd: Vec<Option<Name>>,
+    with_arg: Vec<Term>,
+    arms: Vec<GuardedMatchRule>,
+  ) -> Term {
+    if arms.iter().all(|(_, guard, _)| guard.is_none()) {
+      let arms = arms.into_iter().map(|(nam, _, bod)| (nam, vec![], bod)).collect();
+      return Term::Mat(Box::new(MatchTerm { arg, bnd, with_bnd, with_arg, arms }));
+    }
+
+    let bnd = bnd.expect("match terms always have a bind during parsing");
+    let mut term = Self::guarded_match_arms(&bnd, arms, &mut vec![]);
+    // The scrutinee and the `with` arguments are matched more than once, so bind them to variables.
+    for (nam, arg) in with_bnd.into_iter().zip(with_arg).rev() {
+      if !matches!((&nam, &arg), (Some(nam), Term::Var { nam: var }) if nam == var) {
+        term = Term::Let { pat: Box::new(Pattern::Var(nam)), val: Box::new(arg), nxt: Box::new(term) };
+      }
+    }
+    if !matches!(&arg, Term::Var { nam } if nam == &bnd) {
+      term = Term::Let { pat: Box::new(Pattern::Var(Some(bnd))), val: Box::new(arg), nxt: Box::new(term) };
+    }
+    term
+  }
+
+  /// `guarded_ctrs` has the constructors of the guarded arms before these ones, `None` for a guarded wildcard.
+  fn guarded_match_arms(
+    bnd: &Name,
+    mut arms: Vec<GuardedMatchRule>,
+    guarded_ctrs: &mut Vec<Option<Name>>,
+  ) -> Term {
+    let Some(guarded) = arms.iter().position(|(_, guard, _)| guard.is_some()) else {
+      return match arms.first() {
+        None => Term::r#ref(builtins::UNREACHABLE),
+        // Wildcard first arm, no need to match.
+        Some((None, _, _)) => arms.swap_remove(0).2,
+        Some(_) => {
+          let mut arms = arms.into_iter().map(|(nam, _, bod)| (nam, vec![], bod)).collect::<Vec<_>>();
+          // The constructors whose last guard failed, and that don't have an arm after it, don't match anything.
+          for ctr in guarded_ctrs.iter() {
+            if !arms.iter().any(|(nam, _, _)| nam.is_none() || nam == ctr) {
+              arms.push((ctr.clone(), vec![], Term::r#ref(builtins::GUARD_UNREACHABLE)));
+            }
+          }
+          Self::match_var(bnd, arms)
+        }
+      };
+    };
+
+    let rest = arms.split_off(guarded + 1);
+    let (ctr, guard, bod) = arms.pop().unwrap();
+    let guard = guard.unwrap();
+    guarded_ctrs.push(ctr.clone());
+    let rest = Self::guarded_match_arms(bnd, rest, guarded_ctrs);
+
+    // Wildcard guards don't need to match the scrutinee again.
+    let (guard, bod) = match ctr {
+      Some(ctr) => (
+        Self::match_var(
+          bnd,
+          vec![(Some(ctr.clone()), vec![], guard), (None, vec![], Term::Num { val: Num::U24(0) })],
+        ),
+        Self::match_var(
+          bnd,
+          vec![(Some(ctr), vec![], bod), (None, vec![], Term::r#ref(builtins::GUARD_UNREACHABLE))],
+        ),
+      ),
+      None => (guard, bod),
+    };
+    let term = Term::Swt(Box::new(SwitchTerm {
+      arg: guard,
+      bnd: Some(Name::new("%guard")),
+      with_bnd: vec![],
+      with_arg: vec![],
+      pred: Some(Name::new("%guard-1")),
+      arms: vec![rest, bod],
+    }));
+
+    if arms.is_empty() {
+      term
+    } else {
+      let mut arms = arms.into_iter().map(|(nam, _, bod)| (nam, vec![], bod)).collect::<Vec<_>>();
+      arms.push((None, vec![], term));
+      Self::match_var(bnd, arms)
+    }
+  }
+
+  fn match_var(bnd: &Name, arms: Vec<MatchRule>) -> Term {
+    Term::Mat(Box::new(MatchTerm {
+      arg: Term::Var { nam: bnd.clone() },
+      bnd: Some(bnd.clone()),
+      with_bnd: vec![],
+      with_arg: vec![],
+      arms,
+    }))
+  }
+
   pub fn pattern(&self) -> Option<&Pattern> {
     match self {
       Term::Lam { pat, .. } | Term::Let { pat, .. } => Some(pat),
@@ -578,17 +739,21 @@ impl Term {
   pub fn children(&self) -> impl DoubleEndedIterator<Item = &Term> + Clone {
     multi_iterator!(ChildrenIter { Zero, One, Two, Vec, Mat, Swt, Bend, Fold });
     match self {
-      Term::Mat { arg, bnd: _, with_bnd: _, with_arg, arms } => {
-        ChildrenIter::Mat([arg.as_ref()].into_iter().chain(with_arg.iter()).chain(arms.iter().map(|r| &r.2)))
+      Term::Mat(mat) => {
+        let MatchTerm { arg, bnd: _, with_bnd: _, with_arg, arms } = mat.as_ref();
+        ChildrenIter::Mat([arg].into_iter().chain(with_arg.iter()).chain(arms.iter().map(|r| &r.2)))
       }
-      Term::Swt { arg, bnd: _, with_bnd: _, with_arg, pred: _, arms } => {
-        ChildrenIter::Swt([arg.as_ref()].into_iter().chain(with_arg.iter()).chain(arms))
+      Term::Swt(swt) => {
+        let SwitchTerm { arg, bnd: _, with_bnd: _, with_arg, pred: _, arms } = swt.as_ref();
+        ChildrenIter::Swt([arg].into_iter().chain(with_arg.iter()).chain(arms))
       }
-      Term::Bend { bnd: _, arg: init, cond, step, base } => {
-        ChildrenIter::Bend(init.iter().chain([cond.as_ref(), step.as_ref(), base.as_ref()]))
+      Term::Bend(bend) => {
+        let BendTerm { bnd: _, arg: init, cond, step, base } = bend.as_ref();
+        ChildrenIter::Bend(init.iter().chain([cond, step, base]))
       }
-      Term::Fold { bnd: _, arg, with_bnd: _, with_arg, arms } => {
-        ChildrenIter::Fold([arg.as_ref()].into_iter().chain(with_arg.iter()).chain(arms.iter().map(|r| &r.2)))
+      Term::Fold(fold) => {
+        let MatchTerm { bnd: _, arg, with_bnd: _, with_arg, arms } = fold.as_ref();
+        ChildrenIter::Fold([arg].into_iter().chain(with_arg.iter()).chain(arms.iter().map(|r| &r.2)))
       }
       Term::Fan { els, .. } | Term::List { els } => ChildrenIter::Vec(els),
       Term::Let { val: fst, nxt: snd, .. }
@@ -614,18 +779,26 @@ impl Term {
   pub fn children_mut(&mut self) -> impl DoubleEndedIterator<Item = &mut Term> {
     multi_iterator!(ChildrenIter { Zero, One, Two, Vec, Mat, Swt, Bend, Fold });
     match self {
-      Term::Mat { arg, bnd: _, with_bnd: _, with_arg, arms } => ChildrenIter::Mat(
-        [arg.as_mut()].into_iter().chain(with_arg.iter_mut()).chain(arms.iter_mut().map(|r| &mut r.2)),
-      ),
-      Term::Swt { arg, bnd: _, with_bnd: _, with_arg, pred: _, arms } => {
-        ChildrenIter::Swt([arg.as_mut()].into_iter().chain(with_arg.iter_mut()).chain(arms))
+      Term::Mat(mat) => {
+        let MatchTerm { arg, bnd: _, with_bnd: _, with_arg, arms } = mat.as_mut();
+        ChildrenIter::Mat(
+          [arg].into_iter().chain(with_arg.iter_mut()).chain(arms.iter_mut().map(|r| &mut r.2)),
+        )
       }
-      Term::Bend { bnd: _, arg: init, cond, step, base } => {
-        ChildrenIter::Bend(init.iter_mut().chain([cond.as_mut(), step.as_mut(), base.as_mut()]))
+      Term::Swt(swt) => {
+        let SwitchTerm { arg, bnd: _, with_bnd: _, with_arg, pred: _, arms } = swt.as_mut();
+        ChildrenIter::Swt([arg].into_iter().chain(with_arg.iter_mut()).chain(arms))
+      }
+      Term::Bend(bend) => {
+        let BendTerm { bnd: _, arg: init, cond, step, base } = bend.as_mut();
+        ChildrenIter::Bend(init.iter_mut().chain([cond, step, base]))
+      }
+      Term::Fold(fold) => {
+        let MatchTerm { bnd: _, arg, with_bnd: _, with_arg, arms } = fold.as_mut();
+        ChildrenIter::Fold(
+          [arg].into_iter().chain(with_arg.iter_mut()).chain(arms.iter_mut().map(|r| &mut r.2)),
+        )
       }
-      Term::Fold { bnd: _, arg, with_bnd: _, with_arg, arms } => ChildrenIter::Fold(
-        [arg.as_mut()].into_iter().chain(with_arg.iter_mut()).chain(arms.iter_mut().map(|r| &mut r.2)),
-      ),
       Term::Fan { els, .. } | Term::List { els } => ChildrenIter::Vec(els),
       Term::Let { val: fst, nxt: snd, .. }
       | Term::Ask { val: fst, nxt: snd, .. }
@@ -662,30 +835,32 @@ impl Term {
     multi_iterator!(ChildrenIter { Zero, One, Two, Vec, Mat, Swt, Bend });
     multi_iterator!(BindsIter { Zero, One, Mat, Pat, SwtNum, SwtSucc, Bend });
     match self {
-      Term::Mat { arg, bnd, with_bnd, with_arg, arms }
-      | Term::Fold { bnd, arg, with_bnd, with_arg, arms } => {
-        let arg = [(arg.as_ref(), BindsIter::Zero([]))].into_iter();
+      Term::Mat(mat) | Term::Fold(mat) => {
+        let MatchTerm { arg, bnd, with_bnd, with_arg, arms } = mat.as_ref();
+        let arg = [(arg, BindsIter::Zero([]))].into_iter();
         let with_arg = with_arg.iter().map(|a| (a, BindsIter::Zero([])));
         let arms = arms
           .iter()
           .map(move |r| (&r.2, BindsIter::Mat([bnd].into_iter().chain(r.1.iter()).chain(with_bnd.iter()))));
         ChildrenIter::Mat(arg.chain(with_arg).chain(arms))
       }
-      Term::Swt { arg, bnd, with_bnd, with_arg, pred, arms } => {
+      Term::Swt(swt) => {
+        let SwitchTerm { arg, bnd, with_bnd, with_arg, pred, arms } = swt.as_ref();
         let (succ, nums) = arms.split_last().unwrap();
         ChildrenIter::Swt(
-          [(arg.as_ref(), BindsIter::Zero([]))]
+          [(arg, BindsIter::Zero([]))]
             .into_iter()
             .chain(with_arg.iter().map(|a| (a, BindsIter::Zero([]))))
             .chain(nums.iter().map(move |x| (x, BindsIter::SwtNum([bnd].into_iter().chain(with_bnd.iter())))))
             .chain([(succ, BindsIter::SwtSucc([bnd, pred].into_iter().chain(with_bnd.iter())))]),
         )
       }
-      Term::Bend { bnd: bind, arg: init, cond, step, base } => {
+      Term::Bend(bend) => {
+        let BendTerm { bnd: bind, arg: init, cond, step, base } = bend.as_ref();
         ChildrenIter::Bend(init.iter().map(|x| (x, BindsIter::Zero([]))).chain([
-          (cond.as_ref(), BindsIter::Bend(bind.iter())),
-          (step.as_ref(), BindsIter::Bend(bind.iter())),
-          (base.as_ref(), BindsIter::Bend(bind.iter())),
+          (cond, BindsIter::Bend(bind.iter())),
+          (step, BindsIter::Bend(bind.iter())),
+          (base, BindsIter::Bend(bind.iter())),
         ]))
       }
 
@@ -724,19 +899,20 @@ impl Term {
     multi_iterator!(ChildrenIter { Zero, One, Two, Vec, Mat, Swt, Bend });
     multi_iterator!(BindsIter { Zero, One, Mat, SwtNum, SwtSucc, Pat, Bend });
     match self {
-      Term::Mat { arg, bnd, with_bnd, with_arg, arms }
-      | Term::Fold { bnd, arg, with_bnd, with_arg, arms } => {
-        let arg = [(arg.as_mut(), BindsIter::Zero([]))].into_iter();
+      Term::Mat(mat) | Term::Fold(mat) => {
+        let MatchTerm { arg, bnd, with_bnd, with_arg, arms } = mat.as_mut();
+        let arg = [(arg, BindsIter::Zero([]))].into_iter();
         let with_arg = with_arg.iter_mut().map(|a| (a, BindsIter::Zero([])));
         let arms = arms
           .iter_mut()
           .map(|r| (&mut r.2, BindsIter::Mat([&*bnd].into_iter().chain(r.1.iter()).chain(with_bnd.iter()))));
         ChildrenIter::Mat(arg.chain(with_arg).chain(arms))
       }
-      Term::Swt { arg, bnd, with_bnd, with_arg, pred, arms } => {
+      Term::Swt(swt) => {
+        let SwitchTerm { arg, bnd, with_bnd, with_arg, pred, arms } = swt.as_mut();
         let (succ, nums) = arms.split_last_mut().unwrap();
         ChildrenIter::Swt(
-          [(arg.as_mut(), BindsIter::Zero([]))]
+          [(arg, BindsIter::Zero([]))]
             .into_iter()
             .chain(with_arg.iter_mut().map(|a| (a, BindsIter::Zero([]))))
             .chain(
@@ -745,11 +921,12 @@ impl Term {
             .chain([(succ, BindsIter::SwtSucc([&*bnd, &*pred].into_iter().chain(with_bnd.iter())))]),
         )
       }
-      Term::Bend { bnd, arg, cond, step, base } => {
+      Term::Bend(bend) => {
+        let BendTerm { bnd, arg, cond, step, base } = bend.as_mut();
         ChildrenIter::Bend(arg.iter_mut().map(|x| (x, BindsIter::Zero([]))).chain([
-          (cond.as_mut(), BindsIter::Bend(bnd.iter())),
-          (step.as_mut(), BindsIter::Bend(bnd.iter())),
-          (base.as_mut(), BindsIter::Bend(bnd.iter())),
+          (cond, BindsIter::Bend(bnd.iter())),
+          (step, BindsIter::Bend(bnd.iter())),
+          (base, BindsIter::Bend(bnd.iter())),
         ]))
       }
 
@@ -816,8 +993,8 @@ impl Term {
     });
 
     match self {
-      Term::Fold { arms, .. } | Term::Mat { arms, .. } => {
-        for (arm, _, _) in arms {
+      Term::Fold(mat) | Term::Mat(mat) => {
+        for (arm, _, _) in &mut mat.arms {
           if let Some(nam) = arm {
             if nam == from {
               *nam = to.clone();
@@ -1085,11 +1262,24 @@ impl Rule {
   }
 }
 
+impl Book {
+  /// The definition written by the user that a generated definition was generated from,
+  /// like the function of a lifted local definition. See [Source::is_generated_from].
+  pub fn generated_from(&self, def: &Definition) -> Option<&Definition> {
+    if def.source.kind != SourceKind::Generated {
+      return None;
+    }
+    self.defs.values().find(|parent| {
+      parent.source.kind != SourceKind::Generated && def.source.is_generated_from(&parent.source)
+    })
+  }
+}
+
 impl Definition {
   pub fn new_gen(name: Name, rules: Vec<Rule>, source: Source, check: bool) -> Self {
     let kind = if source.is_builtin() { SourceKind::Builtin } else { SourceKind::Generated };
     let source = Source { kind, ..source };
-    Self { name, typ: Type::Hole, check, rules, source }
+    Self { name, typ: Type::Hole, check, rules, source, names: vec![] }
   }
 
   pub fn is_builtin(&self) -> bool {
@@ -1222,15 +1412,6 @@ impl AsRef<str> for Name {
   }
 }
 
-impl Book {
-  pub fn hvm_entrypoint(&self) -> &str {
-    match self.entrypoint.as_ref().map(|e| e.as_ref()) {
-      Some("main" | "Main") | None => ENTRY_POINT,
-      Some(nam) => nam,
-    }
-  }
-}
-
 impl Source {
   pub fn is_builtin(&self) -> bool {
     matches!(self.kind, SourceKind::Builtin)
@@ -1240,6 +1421,21 @@ impl Source {
     matches!(self.kind, SourceKind::User)
   }
 
+  /// Whether this is the source of a definition that the compiler generated from the code of `parent`,
+  /// like the lifted match arms and local definitions of a function.
+  /// The generated definitions of a builtin are also builtins.
+  pub fn is_generated_from(&self, parent: &Source) -> bool {
+    let generated = match parent.kind {
+      SourceKind::Builtin => self.kind == SourceKind::Builtin,
+      _ => self.kind == SourceKind::Generated,
+    };
+    let inside = match (&self.span, &parent.span) {
+      (Some(span), Some(parent)) => parent.start <= span.start && span.end <= parent.end,
+      _ => false,
+    };
+    generated && inside && self.file == parent.file
+  }
+
   pub fn from_file_span(file: &Name, txt: &str, span: Range<usize>, builtin: bool) -> Self {
     let span = Some(TextSpan::from_byte_span(txt, span));
     let kind = if builtin { SourceKind::Builtin } else { SourceKind::User };
@@ -1,6 +1,6 @@
 use crate::{
   diagnostics::{DiagnosticOrigin, Diagnostics, Severity},
-  fun::{term_to_net::Labels, Book, FanKind, Name, Num, Op, Pattern, Tag, Term},
+  fun::{term_to_net::Labels, Book, FanKind, Name, Num, Op, Pattern, SwitchTerm, Tag, Term},
   maybe_grow,
   net::{BendLab, CtrKind, INet, NodeId, NodeKind, Port, SlotId, ROOT},
 };
@@ -465,14 +465,14 @@ impl Reader<'_> {
             succ
           }
         };
-        Term::Swt {
-          arg: Box::new(arg),
+        Term::Swt(Box::new(SwitchTerm {
+          arg,
           bnd: Some(bnd),
           with_arg: vec![],
           with_bnd: vec![],
           pred: None,
           arms: vec![zero, succ],
-        }
+        }))
       }
       _ => {
         self.error(ReadbackError::InvalidNumericMatch);
@@ -748,7 +748,8 @@ impl Term {
   pub fn collect_unscoped(&self, unscoped: &mut HashSet<Name>, scope: &mut Vec<Name>) {
     maybe_grow(|| match self {
       Term::Var { nam } if !scope.contains(nam) => _ = unscoped.insert(nam.clone()),
-      Term::Swt { arg, bnd, with_bnd: _, with_arg, pred: _, arms } => {
+      Term::Swt(swt) => {
+        let SwitchTerm { arg, bnd, with_bnd: _, with_arg, pred: _, arms } = swt.as_ref();
         arg.collect_unscoped(unscoped, scope);
         for arg in with_arg {
           arg.collect_unscoped(unscoped, scope);
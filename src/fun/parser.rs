@@ -1,21 +1,35 @@
 use crate::{
+  diagnostics::{TextLocation, TextSpan},
+  fun::transform::desugar_assert::ASSERT_FAILED,
   fun::{
-    display::DisplayFn, Adt, AdtCtr, Adts, Constructors, CtrField, FanKind, HvmDefinition, HvmDefinitions,
-    MatchRule, Name, Num, Op, Pattern, Rule, Source, SourceKind, Tag, Term, Type, STRINGS,
+    builtins, display::DisplayFn, Adt, AdtCtr, Adts, BendTerm, Constructors, CtrField, FanKind,
+    GuardedMatchRule, HvmDefinition, HvmDefinitions, MatchRule, MatchTerm, Name, Num, Op, Pattern, Rule,
+    RuleNames, Source, SourceKind, SwitchTerm, Tag, Term, Type, STRINGS,
   },
   imp::parser::ImpParser,
   imports::{Import, ImportCtx, ImportType},
   maybe_grow,
 };
 use highlight_error::highlight_error;
-use indexmap::IndexMap;
+use indexmap::{IndexMap, IndexSet};
 use itertools::Itertools;
-use std::ops::Range;
+use std::{borrow::Cow, ops::Range};
 use TSPL::{ParseError, Parser};
 
 type FunDefinition = super::Definition;
 type ImpDefinition = crate::imp::Definition;
 
+/// Rules of a function that was already defined, found after other definitions.
+/// Depending on the diagnostics, they're gathered into the function or replace it as a redefinition.
+#[derive(Debug, Clone)]
+pub struct OutOfOrderRules {
+  pub def: FunDefinition,
+  /// Describes where both groups of rules are, for when they're gathered.
+  pub out_of_order_msg: String,
+  /// Describes where both definitions are, for when the rules replace the function.
+  pub redefinition_msg: String,
+}
+
 /// Intermediate representation of a program.
 #[derive(Debug, Clone, Default)]
 pub struct ParseBook {
@@ -39,6 +53,66 @@ pub struct ParseBook {
 
   /// File path that the book was loaded from.
   pub source: Name,
+
+  /// Rules of functions found separated from their first rules by other definitions.
+  pub rules_out_of_order: Vec<OutOfOrderRules>,
+
+  /// Functions defined more than once, with the message describing where they are
+  /// and the source of the last definition, which replaces the previous ones.
+  pub redefinitions: Vec<(Name, String, Source)>,
+
+  /// Definitions marked as `private`, that can't be imported by other files.
+  pub private_defs: IndexSet<Name>,
+
+  /// Definitions marked as `macro`, that are expanded where they're called.
+  pub macros: IndexSet<Name>,
+
+  /// The flags enabled with `--cfg`, that select which definitions marked with `#[cfg(...)]` are loaded.
+  pub cfg: IndexSet<Name>,
+
+  /// Definitions marked with `#[deprecated]`, with the message of the attribute.
+  pub deprecated: IndexMap<Name, Option<String>>,
+
+  /// Definitions marked with `#[test]`, that are run by `bend test`.
+  pub tests: IndexSet<Name>,
+
+  /// Definitions marked with `#[inline]` (`true`) or `#[noinline]` (`false`).
+  pub inline: IndexMap<Name, bool>,
+
+  /// The definition marked with `#[main]`, which is the entry point of the program instead of `main`.
+  pub entrypoint: Option<Name>,
+
+  /// The paths of the `#include` directives of the last parsed file, with the span of each directive.
+  pub includes: Vec<(String, Range<usize>)>,
+
+  /// Names that are reserved keywords, with the message describing where each one is.
+  pub keyword_names: Vec<(String, Source)>,
+}
+
+/// An edition of the language, that a file can declare with a `#lang bend/<edition>` line at its start,
+/// so that a file written for an edition the compiler doesn't support fails to parse instead of meaning something else.
+///
+/// The line is only checked against the supported editions, the grammar is the same for all of them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Edition {
+  #[default]
+  V0_2,
+}
+
+impl Edition {
+  pub const ALL: [Edition; 1] = [Edition::V0_2];
+
+  pub fn name(&self) -> &'static str {
+    match self {
+      Edition::V0_2 => "bend/0.2",
+    }
+  }
+}
+
+impl std::fmt::Display for Edition {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{}", self.name())
+  }
 }
 
 impl ParseBook {
@@ -63,130 +137,350 @@ pub struct FunParser<'i> {
   input: &'i str,
   index: usize,
   builtin: bool,
+  /// The spans of the names that are reserved keywords, not written as raw identifiers.
+  keyword_names: Vec<Range<usize>>,
+  /// The binders parsed that weren't given to a definition yet.
+  binders: Vec<(Name, Range<usize>)>,
+  /// The variables parsed that weren't given to a definition yet.
+  vars: Vec<(Name, Range<usize>)>,
+  /// Where each line of the input starts, to find the locations of the names.
+  line_starts: Vec<usize>,
+}
+
+/// The attributes of a top-level item, with where each one starts.
+#[derive(Default)]
+struct Attributes {
+  /// Whether the `cfg` attributes enable the item, if it has any.
+  cfg: Option<bool>,
+  deprecated: Option<(usize, Option<String>)>,
+  test: Option<usize>,
+  /// `true` for `#[inline]` and `false` for `#[noinline]`.
+  inline: Option<(usize, bool)>,
+  main: Option<usize>,
 }
 
 impl<'a> FunParser<'a> {
   pub fn new(file: Name, input: &'a str, builtin: bool) -> Self {
-    Self { file, input, index: 0, builtin }
+    let line_starts = std::iter::once(0).chain(input.match_indices('\n').map(|(idx, _)| idx + 1)).collect();
+    Self { file, input, index: 0, builtin, keyword_names: vec![], binders: vec![], vars: vec![], line_starts }
   }
 
   /* AST parsing functions */
 
   pub fn parse_book(&mut self, default_book: ParseBook) -> ParseResult<ParseBook> {
     let mut book = default_book;
+    self.check_edition_pragma()?;
     let mut indent = self.advance_newlines()?;
+    // The definitions disabled by their `cfg` are parsed, but not added to the book.
+    let mut disabled = ParseBook::default();
     while !self.is_eof() {
-      // Record type definition
-      if self.starts_with_keyword("object") {
-        let ini_idx = *self.index();
+      self.parse_top_level(&mut book, &mut disabled, &mut indent)?;
+    }
+    self.report_keyword_names(&mut book);
+    Ok(book)
+  }
+
+  /// Parses a book like [`Self::parse_book`], but continues after a syntax error in a top-level item,
+  /// skipping to the next line that starts a new item.
+  ///
+  /// Returns every error found, in order, with the book of the items that were parsed successfully.
+  /// The errors after the first one may be caused by a bad guess of where the next item starts.
+  pub fn parse_book_recovering(&mut self, default_book: ParseBook) -> (ParseBook, Vec<ParseError>) {
+    let mut book = default_book;
+    let mut errs = vec![];
+    if let Err(err) = self.check_edition_pragma() {
+      errs.push(err);
+    }
+    let mut disabled = ParseBook::default();
+    let mut indent = self.advance_newlines();
+    loop {
+      match indent {
+        Ok(_) if self.is_eof() => break,
+        Ok(ref mut ok_indent) => {
+          let ini_idx = *self.index();
+          if let Err(err) = self.parse_top_level(&mut book, &mut disabled, ok_indent) {
+            self.index = ini_idx.max(err.span.0);
+            errs.push(err);
+            self.skip_to_next_top_level(ini_idx);
+            indent = self.advance_newlines();
+          }
+        }
+        Err(err) => {
+          // Trivia that can't be skipped, like an unclosed comment, is the rest of the file.
+          errs.push(err);
+          break;
+        }
+      }
+    }
+    self.report_keyword_names(&mut book);
+    (book, errs)
+  }
+
+  /// Moves back to `idx`, forgetting the names found after it, which will be parsed again.
+  fn backtrack(&mut self, idx: usize) {
+    self.index = idx;
+    self.keyword_names.retain(|span| span.start < idx);
+    self.binders.retain(|(_, span)| span.start < idx);
+    self.vars.retain(|(_, span)| span.start < idx);
+  }
+
+  /// Takes the binders and variables found after `idx`, with their byte spans turned into [`TextSpan`]s.
+  fn take_names(&mut self, idx: usize) -> RuleNames {
+    let line_starts = &self.line_starts;
+    let take = |names: &mut Vec<(Name, Range<usize>)>| {
+      let loc = |idx: usize| {
+        let line = line_starts.partition_point(|&start| start <= idx) - 1;
+        TextLocation::new(line, idx - line_starts[line])
+      };
+      let first = names.partition_point(|(_, span)| span.start < idx);
+      let names = names.split_off(first);
+      names.into_iter().map(|(name, span)| (name, TextSpan::new(loc(span.start), loc(span.end)))).collect()
+    };
+    RuleNames { binders: take(&mut self.binders), vars: take(&mut self.vars) }
+  }
+
+  /// Adds the warnings about the names that are reserved keywords to the book.
+  fn report_keyword_names(&mut self, book: &mut ParseBook) {
+    for span in std::mem::take(&mut self.keyword_names) {
+      let name = &self.input[span.clone()];
+      let msg = format!(
+        "'{name}' is a keyword, so this name can only be referenced in a term as 'r#{name}'.\nLocation:\n{}",
+        highlight_error(span.start, span.end, self.input)
+      );
+      let source = Source::from_file_span(&self.file, self.input, span, self.builtin);
+      book.keyword_names.push((msg, source));
+    }
+  }
+
+  /// Moves to the next line after `ini_idx` that looks like the start of a top-level item,
+  /// a line starting with a name, a keyword, a directive or the `(` of a rule without indentation.
+  /// That can be the current line, when an item was only found to be incomplete at the start of the next one.
+  fn skip_to_next_top_level(&mut self, ini_idx: usize) {
+    let starts_item = |idx: usize| {
+      (idx == 0 || self.input[..idx].ends_with('\n'))
+        && self.input[idx..].starts_with(|c: char| c.is_alphabetic() || c == '_' || c == '(')
+        || is_directive(self.input, idx)
+    };
+    let mut idx = self.index;
+    if idx > ini_idx && starts_item(idx) {
+      return;
+    }
+    while let Some(newline) = self.input[idx..].find('\n') {
+      idx += newline + 1;
+      if starts_item(idx) {
+        self.index = idx;
+        return;
+      }
+    }
+    self.index = self.input.len();
+  }
+
+  /// Parses one top-level item into the book, or into `disabled` if it's disabled by a `cfg` attribute.
+  /// Updates `indent` to the indentation of the line after it.
+  fn parse_top_level(
+    &mut self,
+    book: &mut ParseBook,
+    disabled: &mut ParseBook,
+    indent: &mut Indent,
+  ) -> ParseResult<()> {
+    // Include directive
+    if self.starts_with("#include") {
+      let include = self.parse_include()?;
+      book.includes.push(include);
+      *indent = self.advance_newlines()?;
+      return Ok(());
+    }
+
+    let attrs = self.parse_attributes(&book.cfg)?;
+    let target = if attrs.cfg.unwrap_or(true) { book } else { disabled };
+    let private = self.parse_private_modifier()?;
+    let is_macro = self.parse_macro_modifier()?;
+    self.check_attributes(&attrs)?;
+    let deprecated = attrs.deprecated.map(|(_, msg)| msg);
+    let is_test = attrs.test.is_some();
+    let inline = attrs.inline.map(|(_, inline)| inline);
+    let main_attr = attrs.main;
+
+    // Record type definition
+    if self.starts_with_keyword("object") {
+      let ini_idx = *self.index();
+      let mut prs = ImpParser {
+        file: self.file.clone(),
+        input: self.input,
+        index: *self.index(),
+        builtin: self.builtin,
+        binders: vec![],
+        vars: vec![],
+      };
+      let (adt, nxt_indent) = prs.parse_object(*indent)?;
+      self.index = prs.index;
+      let end_idx = *self.index();
+      self.add_type_def(adt, target, ini_idx..end_idx)?;
+      *indent = nxt_indent;
+      return Ok(());
+    }
+
+    // Imp function definition
+    if self.starts_with_keyword("def") {
+      let ini_idx = *self.index();
+      let mut prs = ImpParser {
+        file: self.file.clone(),
+        input: self.input,
+        index: ini_idx,
+        builtin: self.builtin,
+        binders: vec![],
+        vars: vec![],
+      };
+      let (mut def, nxt_indent) = prs.parse_function_def(*indent)?;
+      self.index = prs.index;
+      self.binders.append(&mut prs.binders);
+      self.vars.append(&mut prs.vars);
+      def.names = self.take_names(ini_idx);
+      let end_idx = *self.index();
+      if private {
+        target.private_defs.insert(def.name.clone());
+      }
+      if is_macro {
+        target.macros.insert(def.name.clone());
+      }
+      if let Some(msg) = deprecated {
+        target.deprecated.insert(def.name.clone(), msg);
+      }
+      if is_test {
+        target.tests.insert(def.name.clone());
+      }
+      if let Some(inline) = inline {
+        target.inline.insert(def.name.clone(), inline);
+      }
+      self.set_main_attribute(&def.name, main_attr, target)?;
+      self.add_imp_def(def, target, ini_idx..end_idx)?;
+      *indent = nxt_indent;
+      return Ok(());
+    }
+
+    // Fun/Imp type definition
+    if self.starts_with_keyword("type") {
+      fn starts_with_imp_type(p: &mut FunParser) -> ParseResult<()> {
+        p.parse_keyword("type")?;
+        p.skip_trivia_inline()?;
+        p.parse_top_level_name()?;
+        p.skip_trivia_inline()?;
+        if p.starts_with(":") || p.starts_with("(") {
+          Ok(())
+        } else {
+          Err(ParseError::new((0, 0), ""))
+        }
+      }
+
+      let ini_idx = *self.index();
+      let is_imp = starts_with_imp_type(self).is_ok();
+      self.backtrack(ini_idx);
+      if is_imp {
+        // Imp type definition
         let mut prs = ImpParser {
           file: self.file.clone(),
           input: self.input,
           index: *self.index(),
           builtin: self.builtin,
+          binders: vec![],
+          vars: vec![],
         };
-        let (adt, nxt_indent) = prs.parse_object(indent)?;
+        let (adt, nxt_indent) = prs.parse_type_def(*indent)?;
         self.index = prs.index;
         let end_idx = *self.index();
-        self.add_type_def(adt, &mut book, ini_idx..end_idx)?;
-        indent = nxt_indent;
-        continue;
-      }
-
-      // Imp function definition
-      if self.starts_with_keyword("def") {
-        let ini_idx = *self.index();
-        let mut prs =
-          ImpParser { file: self.file.clone(), input: self.input, index: ini_idx, builtin: self.builtin };
-        let (def, nxt_indent) = prs.parse_function_def(indent)?;
-        self.index = prs.index;
+        self.add_type_def(adt, target, ini_idx..end_idx)?;
+        *indent = nxt_indent;
+        return Ok(());
+      } else {
+        // Fun type definition
+        let adt = self.parse_type_def()?;
         let end_idx = *self.index();
-        self.add_imp_def(def, &mut book, ini_idx..end_idx)?;
-        indent = nxt_indent;
-        continue;
+        self.add_type_def(adt, target, ini_idx..end_idx)?;
+        *indent = self.advance_newlines()?;
+        return Ok(());
       }
+    }
 
-      // Fun/Imp type definition
-      if self.starts_with_keyword("type") {
-        fn starts_with_imp_type(p: &mut FunParser) -> ParseResult<()> {
-          p.parse_keyword("type")?;
-          p.skip_trivia_inline()?;
-          p.parse_top_level_name()?;
-          p.skip_trivia_inline()?;
-          if p.starts_with(":") || p.starts_with("(") {
-            Ok(())
-          } else {
-            Err(ParseError::new((0, 0), ""))
-          }
-        }
-
-        let ini_idx = *self.index();
-        let is_imp = starts_with_imp_type(self).is_ok();
-        self.index = ini_idx;
-        if is_imp {
-          // Imp type definition
-          let mut prs = ImpParser {
-            file: self.file.clone(),
-            input: self.input,
-            index: *self.index(),
-            builtin: self.builtin,
-          };
-          let (adt, nxt_indent) = prs.parse_type_def(indent)?;
-          self.index = prs.index;
-          let end_idx = *self.index();
-          self.add_type_def(adt, &mut book, ini_idx..end_idx)?;
-          indent = nxt_indent;
-          continue;
-        } else {
-          // Fun type definition
-          let adt = self.parse_type_def()?;
-          let end_idx = *self.index();
-          self.add_type_def(adt, &mut book, ini_idx..end_idx)?;
-          indent = self.advance_newlines()?;
-          continue;
-        }
+    // HVM native function definition
+    if self.starts_with_keyword("hvm") {
+      let ini_idx = self.index;
+      let mut prs = ImpParser {
+        file: self.file.clone(),
+        input: self.input,
+        index: self.index,
+        builtin: self.builtin,
+        binders: vec![],
+        vars: vec![],
+      };
+      let (def, nxt_indent) = prs.parse_hvm()?;
+      *self.index() = prs.index;
+      let end_idx = *self.index();
+      if private {
+        target.private_defs.insert(def.name.clone());
       }
-
-      // HVM native function definition
-      if self.starts_with_keyword("hvm") {
-        let ini_idx = self.index;
-        let mut prs =
-          ImpParser { file: self.file.clone(), input: self.input, index: self.index, builtin: self.builtin };
-        let (def, nxt_indent) = prs.parse_hvm()?;
-        *self.index() = prs.index;
-        let end_idx = *self.index();
-        self.add_hvm(def, &mut book, ini_idx..end_idx)?;
-        indent = nxt_indent;
-        continue;
+      if let Some(msg) = deprecated {
+        target.deprecated.insert(def.name.clone(), msg);
       }
-
-      // Import declaration
-      if self.starts_with_keyword("from") {
-        let import = self.parse_from_import()?;
-        book.import_ctx.add_import(import);
-        indent = self.advance_newlines()?;
-        continue;
+      if let Some(inline) = inline {
+        target.inline.insert(def.name.clone(), inline);
       }
+      self.add_hvm(def, target, ini_idx..end_idx)?;
+      *indent = nxt_indent;
+      return Ok(());
+    }
 
-      if self.starts_with_keyword("import") {
-        let imports = self.parse_import()?;
-        for imp in imports {
-          book.import_ctx.add_import(imp);
-        }
-        indent = self.advance_newlines()?;
-        continue;
+    // Import declaration
+    if self.starts_with_keyword("from") {
+      let import = self.parse_from_import()?;
+      target.import_ctx.add_import(import);
+      *indent = self.advance_newlines()?;
+      return Ok(());
+    }
+
+    if self.starts_with_keyword("import") {
+      let imports = self.parse_import()?;
+      for imp in imports {
+        target.import_ctx.add_import(imp);
       }
+      *indent = self.advance_newlines()?;
+      return Ok(());
+    }
 
-      // Fun function definition
-      let ini_idx = *self.index();
-      let def = self.parse_fun_def()?;
-      let end_idx = *self.index();
+    let ini_idx = *self.index();
 
-      self.add_fun_def(def, &mut book, ini_idx..end_idx)?;
-      indent = self.advance_newlines()?;
+    // Script mode, a term at the end of a file without a main function
+    if let Some(def) = self
+      .try_parse_script_main(target)
+      .filter(|_| !private && !is_macro && deprecated.is_none() && !is_test && inline.is_none())
+    {
+      let end_idx = *self.index();
+      self.add_fun_def(def, target, ini_idx..end_idx)?;
+      return Ok(());
     }
 
-    Ok(book)
+    // Fun function definition
+    let def = self.parse_fun_def()?;
+    let end_idx = *self.index();
+
+    if private {
+      target.private_defs.insert(def.name.clone());
+    }
+    if is_macro {
+      target.macros.insert(def.name.clone());
+    }
+    if let Some(msg) = deprecated {
+      target.deprecated.insert(def.name.clone(), msg);
+    }
+    if is_test {
+      target.tests.insert(def.name.clone());
+    }
+    if let Some(inline) = inline {
+      target.inline.insert(def.name.clone(), inline);
+    }
+    self.set_main_attribute(&def.name, main_attr, target)?;
+    self.add_fun_def(def, target, ini_idx..end_idx)?;
+    *indent = self.advance_newlines()?;
+    Ok(())
   }
 
   fn parse_type_def(&mut self) -> ParseResult<Adt> {
@@ -283,43 +577,265 @@ impl<'a> FunParser<'a> {
         let body = self.parse_term()?;
         let pats = args.into_iter().map(|nam| Pattern::Var(Some(nam))).collect();
         let rules = vec![Rule { pats, body }];
+        let names = vec![self.take_names(ini_idx)];
         let end_idx = *self.index();
         let source = Source::from_file_span(&self.file, self.input, ini_idx..end_idx, self.builtin);
-        let def = FunDefinition { name, typ, check, rules, source };
+        let def = FunDefinition { name, typ, check, rules, source, names };
         Ok(def)
       } else {
         // Multiple rules with signature
+        // The names of the arguments in the signature don't bind anything in the rules.
+        self.take_names(ini_idx);
         let mut rules = vec![];
-        let (_, rule) = self.parse_rule()?;
+        let mut names = vec![];
+        let (_, rule, rule_names) = self.parse_rule()?;
         rules.push(rule);
+        names.push(rule_names);
         while self.starts_with_rule(&name) {
-          let (_, rule) = self.parse_rule()?;
+          let (_, rule, rule_names) = self.parse_rule()?;
           rules.push(rule);
+          names.push(rule_names);
         }
         let end_idx = *self.index();
         let source = Source::from_file_span(&self.file, self.input, ini_idx..end_idx, self.builtin);
-        let def = FunDefinition { name, typ, check, rules, source };
+        let def = FunDefinition { name, typ, check, rules, source, names };
         Ok(def)
       }
     } else {
       // Was not a signature, backtrack and read the name from the first rule
-      self.index = ini_idx;
+      self.backtrack(ini_idx);
       // No signature, don't check by default
       let check = self.parse_checked(false);
       let mut rules = vec![];
-      let (name, rule) = self.parse_rule()?;
+      let mut names = vec![];
+      let (name, rule, rule_names) = self.parse_rule()?;
       rules.push(rule);
+      names.push(rule_names);
       while self.starts_with_rule(&name) {
-        let (_, rule) = self.parse_rule()?;
+        let (_, rule, rule_names) = self.parse_rule()?;
         rules.push(rule);
+        names.push(rule_names);
       }
       let end_idx = *self.index();
       let source = Source::from_file_span(&self.file, self.input, ini_idx..end_idx, self.builtin);
-      let def = FunDefinition { name, typ: Type::Any, check, rules, source };
+      let def = FunDefinition { name, typ: Type::Any, check, rules, source, names };
       Ok(def)
     }
   }
 
+  /// Parses the `private` modifier of a top-level definition, if there's one.
+  fn parse_private_modifier(&mut self) -> ParseResult<bool> {
+    let ini_idx = *self.index();
+    if !self.starts_with_modifier("private") {
+      return Ok(false);
+    }
+    self.parse_keyword("private")?;
+    self.skip_trivia();
+    let is_def = !["object", "type", "from", "import"].iter().any(|kw| self.starts_with_keyword(kw));
+    if is_def {
+      Ok(true)
+    } else {
+      let end_idx = *self.index();
+      self.err_msg_spanned("Only function definitions can be private.", ini_idx..end_idx)
+    }
+  }
+
+  /// Whether the input starts with the `private` or `macro` modifier.
+  /// It's only a modifier when the name or the `(` of a definition follows it,
+  /// otherwise it's the name of a definition, like in `private = 1` or `macro 0 = 1`.
+  fn starts_with_modifier(&mut self, modifier: &str) -> bool {
+    let ini_idx = *self.index();
+    let is_modifier = self.try_parse_keyword(modifier) && {
+      self.skip_trivia();
+      self.peek_one().is_some_and(|c| c == '(' || c.is_alphabetic() || c == '_')
+    };
+    self.backtrack(ini_idx);
+    is_modifier
+  }
+
+  /// Parses the `macro` modifier of a top-level definition, if there's one.
+  fn parse_macro_modifier(&mut self) -> ParseResult<bool> {
+    let ini_idx = *self.index();
+    if !self.starts_with_modifier("macro") {
+      return Ok(false);
+    }
+    self.parse_keyword("macro")?;
+    self.skip_trivia();
+    let is_def = !["object", "type", "hvm", "from", "import"].iter().any(|kw| self.starts_with_keyword(kw));
+    if is_def {
+      Ok(true)
+    } else {
+      let end_idx = *self.index();
+      self.err_msg_spanned("Only function definitions can be macros.", ini_idx..end_idx)
+    }
+  }
+
+  /// Checks that the `#lang bend/<edition>` line at the start of the file, if it has one, is of a supported edition.
+  /// The line is a comment for the rest of the parser, nothing else depends on the edition it declares.
+  fn check_edition_pragma(&mut self) -> ParseResult<()> {
+    let line = self.input.lines().next().unwrap_or_default();
+    let Some(lang) = line.strip_prefix("#lang").filter(|lang| lang.starts_with([' ', '\t'])) else {
+      return Ok(());
+    };
+    let lang = lang.trim();
+    if Edition::ALL.iter().any(|edition| edition.name() == lang) {
+      return Ok(());
+    }
+    let editions = Edition::ALL.iter().map(|edition| format!("'{edition}'")).join(", ");
+    let msg = if lang.starts_with("bend/") {
+      format!("Unknown edition '{lang}'. The supported editions are {editions}.")
+    } else {
+      format!("Unknown language '{lang}', expected an edition of Bend, like '#lang {}'.", Edition::default())
+    };
+    self.err_msg_spanned(&msg, 0..line.len())
+  }
+
+  /// Parses an `#include "path"` directive, returning the path with the span of the directive.
+  fn parse_include(&mut self) -> ParseResult<(String, Range<usize>)> {
+    let ini_idx = *self.index();
+    self.consume_exactly("#include")?;
+    self.skip_trivia_inline()?;
+    if !self.starts_with("\"") {
+      let end_idx = ini_idx + self.input[ini_idx..].find('\n').unwrap_or(self.input.len() - ini_idx);
+      let end_idx = ini_idx + self.input[ini_idx..end_idx].trim_end().len();
+      return self.err_msg_spanned("Expected a quoted file path after '#include'.", ini_idx..end_idx);
+    }
+    let path = self.parse_quoted_string()?;
+    Ok((path, ini_idx..*self.index()))
+  }
+
+  /// Parses the attributes of a top-level item, each one in its own line right before the item, like `#[test]`.
+  /// A `cfg` attribute enables the item if its condition holds for the given flags.
+  fn parse_attributes(&mut self, flags: &IndexSet<Name>) -> ParseResult<Attributes> {
+    let mut attrs = Attributes::default();
+    while self.starts_with_directive() {
+      let ini_idx = *self.index();
+      self.consume_exactly("#[")?;
+      let name = self.take_while(is_name_char);
+      match name {
+        "cfg" => {
+          self.consume_exactly("(")?;
+          let enabled = self.parse_cfg_predicate(flags)?;
+          self.consume(")")?;
+          attrs.cfg = Some(attrs.cfg.unwrap_or(true) && enabled);
+        }
+        "deprecated" => {
+          let msg = if self.try_consume_exactly("(") { Some(self.parse_string()?) } else { None };
+          if msg.is_some() {
+            self.consume(")")?;
+          }
+          attrs.deprecated = Some((ini_idx, msg));
+        }
+        "test" => attrs.test = Some(ini_idx),
+        "inline" | "noinline" => {
+          let inline = name == "inline";
+          if let Some((other, _)) = attrs.inline.filter(|(_, other)| *other != inline) {
+            let end_idx = ini_idx.max(other) + "#[noinline]".len();
+            let msg = "A definition can't be both '#[inline]' and '#[noinline]'.";
+            return self.err_msg_spanned(msg, other.min(ini_idx)..end_idx);
+          }
+          attrs.inline = Some((ini_idx, inline));
+        }
+        "main" => attrs.main = Some(ini_idx),
+        _ => unreachable!(),
+      }
+      self.consume_exactly("]")?;
+      self.consume_new_line()?;
+      self.advance_newlines()?;
+    }
+    Ok(attrs)
+  }
+
+  /// Checks that the item after the attributes is a function definition, if it has any attributes that only functions can have.
+  fn check_attributes(&mut self, attrs: &Attributes) -> ParseResult<()> {
+    let ini_idx = *self.index();
+    self.skip_trivia();
+    let starts_with_any = |p: &mut Self, kws: &[&str]| kws.iter().any(|kw| p.starts_with_keyword(kw));
+    let not_def = starts_with_any(self, &["object", "type", "from", "import"]);
+    let not_ref_def = not_def || starts_with_any(self, &["hvm"]);
+    self.backtrack(ini_idx);
+    if let (Some((start, _)), true) = (&attrs.deprecated, not_def) {
+      let end_idx = start + "#[deprecated".len();
+      return self.err_msg_spanned("Only function definitions can be deprecated.", *start..end_idx);
+    }
+    if let (Some(start), true) = (attrs.test, not_ref_def) {
+      return self.err_msg_spanned("Only function definitions can be tests.", start..start + "#[test]".len());
+    }
+    if let (Some((start, inline)), true) = (attrs.inline, not_def) {
+      let attr = if inline { "#[inline]" } else { "#[noinline]" };
+      let msg = format!("Only function definitions can have the '{attr}' attribute.");
+      return self.err_msg_spanned(&msg, start..start + attr.len());
+    }
+    if let (Some(start), true) = (attrs.main, not_ref_def) {
+      let msg = "Only function definitions can be the entry point.";
+      return self.err_msg_spanned(msg, start..start + "#[main]".len());
+    }
+    Ok(())
+  }
+
+  /// Makes the definition the entry point of the program if it's marked with `#[main]`,
+  /// failing if another definition already is.
+  fn set_main_attribute(
+    &mut self,
+    name: &Name,
+    start: Option<usize>,
+    book: &mut ParseBook,
+  ) -> ParseResult<()> {
+    let Some(start) = start else { return Ok(()) };
+    if let Some(other) = &book.entrypoint {
+      let msg = format!("Only one definition can be marked with '#[main]', but '{other}' already is.");
+      return self.err_msg_spanned(&msg, start..start + "#[main]".len());
+    }
+    book.entrypoint = Some(name.clone());
+    Ok(())
+  }
+
+  /// Parses the condition of a `cfg` attribute, like `flag`, `not(flag)`, `all(flag1, flag2)` or `any(flag1, flag2)`.
+  fn parse_cfg_predicate(&mut self, flags: &IndexSet<Name>) -> ParseResult<bool> {
+    self.skip_trivia();
+    let ini_idx = *self.index();
+    let name = self.parse_top_level_name()?;
+    self.skip_trivia();
+    if !self.starts_with("(") {
+      return Ok(flags.contains(&name));
+    }
+    let args = self.list_like(|p| p.parse_cfg_predicate(flags), "(", ")", ",", true, 0)?;
+    match (name.as_ref(), args.as_slice()) {
+      ("not", [arg]) => Ok(!arg),
+      ("all", args) => Ok(args.iter().all(|arg| *arg)),
+      ("any", args) => Ok(args.iter().any(|arg| *arg)),
+      _ => {
+        let end_idx = *self.index();
+        self.err_msg_spanned(
+          "Expected a flag name, 'not(...)' with one condition, 'all(...)' or 'any(...)'.",
+          ini_idx..end_idx,
+        )
+      }
+    }
+  }
+
+  /// Parses a term that ends the file, which becomes the `main` definition of the program.
+  /// If that's not the case, backtracks and returns `None`.
+  fn try_parse_script_main(&mut self, book: &ParseBook) -> Option<FunDefinition> {
+    let name = Name::new("main");
+    if book.fun_defs.contains_key(&name) || book.imp_defs.contains_key(&name) {
+      return None;
+    }
+    let ini_idx = *self.index();
+    if let Ok(body) = self.parse_term() {
+      let end_idx = *self.index();
+      self.skip_trivia();
+      if self.is_eof() {
+        let rules = vec![Rule { pats: vec![], body }];
+        let names = vec![self.take_names(ini_idx)];
+        let source = Source::from_file_span(&self.file, self.input, ini_idx..end_idx, self.builtin);
+        return Some(FunDefinition { name, typ: Type::Any, check: false, rules, source, names });
+      }
+    }
+    self.backtrack(ini_idx);
+    None
+  }
+
   /// Parses a function definition signature.
   /// Returns the name, name of the arguments and the type of the function.
   fn parse_def_sig(&mut self) -> ParseResult<(Name, Vec<Name>, bool, Type)> {
@@ -349,12 +865,12 @@ impl<'a> FunParser<'a> {
     // '(' name ')'
     // '(' name ':' type ')'
     if self.try_consume("(") {
-      let name = self.parse_var_name()?;
+      let name = self.parse_binder_name()?;
       let typ = if self.try_consume(":") { self.parse_type_term()? } else { Type::Any };
       self.consume(")")?;
       Ok((name, typ))
     } else {
-      let name = self.parse_var_name()?;
+      let name = self.parse_binder_name()?;
       Ok((name, Type::Any))
     }
   }
@@ -444,8 +960,10 @@ impl<'a> FunParser<'a> {
     }
   }
 
-  fn parse_rule(&mut self) -> ParseResult<(Name, Rule)> {
+  /// Parses a rule, returning it with the name of its function and the names written in it.
+  fn parse_rule(&mut self) -> ParseResult<(Name, Rule, RuleNames)> {
     self.skip_trivia();
+    let ini_idx = *self.index();
     let (name, pats) = self.parse_rule_lhs()?;
 
     self.consume("=")?;
@@ -453,18 +971,22 @@ impl<'a> FunParser<'a> {
     let body = self.parse_term()?;
 
     let rule = Rule { pats, body };
-    Ok((name, rule))
+    Ok((name, rule, self.take_names(ini_idx)))
   }
 
   fn starts_with_rule(&mut self, expected_name: &Name) -> bool {
     let ini_idx = *self.index();
     self.skip_trivia();
+    if self.starts_with_modifier("private") || self.starts_with_modifier("macro") {
+      self.backtrack(ini_idx);
+      return false;
+    }
     let res = self.parse_rule_lhs();
     if !self.try_consume("=") {
-      self.index = ini_idx;
+      self.backtrack(ini_idx);
       return false;
     }
-    self.index = ini_idx;
+    self.backtrack(ini_idx);
     if let Ok((name, _)) = res {
       if &name == expected_name {
         // Found rule with the expected name
@@ -493,7 +1015,7 @@ impl<'a> FunParser<'a> {
 
         // Tup
         self.skip_trivia();
-        if self.starts_with(",") || simple {
+        if self.starts_with(",") || (simple && !matches!(head, Pattern::Var(Some(_)))) {
           self.consume(",")?;
           let mut els = self.list_like(|p| p.parse_pattern(simple), "", ")", ",", true, 1)?;
           els.insert(0, head);
@@ -505,6 +1027,8 @@ impl<'a> FunParser<'a> {
         let Pattern::Var(Some(name)) = head else {
           return self.expected_spanned("constructor name", head_ini_idx..head_end_idx);
         };
+        // The name was parsed as a variable, but it's the constructor.
+        self.binders.retain(|(_, span)| span.start < head_ini_idx);
         let els = self.list_like(|p| p.parse_pattern(simple), "", ")", "", false, 0)?;
         return Ok(Pattern::Ctr(name, els));
       }
@@ -523,14 +1047,14 @@ impl<'a> FunParser<'a> {
       }
 
       // String
-      if self.starts_with("\"") && !simple {
+      if self.starts_with_string() && !simple {
         unexpected_tag(self)?;
-        let str = self.parse_quoted_string()?;
+        let str = self.parse_string()?;
         return Ok(Pattern::Str(STRINGS.get(str)));
       }
 
       // Char
-      if self.starts_with("'") {
+      if self.starts_with("'") && !simple {
         unexpected_tag(self)?;
         let char = self.parse_quoted_char()?;
         return Ok(Pattern::Num(char as u32));
@@ -548,7 +1072,7 @@ impl<'a> FunParser<'a> {
         unexpected_tag(self)?;
         self.advance_one();
         self.skip_trivia();
-        let name = self.parse_var_name()?;
+        let name = self.parse_binder_name()?;
         return Ok(Pattern::Chn(name));
       }
 
@@ -559,7 +1083,7 @@ impl<'a> FunParser<'a> {
           .is_some_and(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '-' || c == '/')
       {
         unexpected_tag(self)?;
-        let nam = self.parse_name_or_era()?;
+        let nam = self.parse_binder_or_era()?;
         return Ok(Pattern::Var(nam));
       }
 
@@ -579,7 +1103,7 @@ impl<'a> FunParser<'a> {
       self.skip_trivia();
 
       // Lambda, unscoped lambda
-      if self.starts_with("λ") || self.starts_with("@") {
+      if self.starts_with("λ") || self.starts_with("@") || self.starts_with("\\") {
         self.advance_one();
         let tag = tag.unwrap_or(Tag::Static);
         let pat = self.parse_pattern(true)?;
@@ -599,6 +1123,19 @@ impl<'a> FunParser<'a> {
         // ( * ,       -> Tup with Era
         // ( * )       -> Era
         // ( opr       -> Num Op
+        // Short-circuiting logical operators
+        // ( && a b )
+        // ( || a b )
+        if self.starts_with("&&") || self.starts_with("||") {
+          unexpected_tag(self)?;
+          let and = self.starts_with("&&");
+          self.advance_many(2);
+          let lhs = self.parse_term()?;
+          let rhs = self.parse_term()?;
+          self.consume(")")?;
+          return Ok(if and { Term::and(lhs, rhs) } else { Term::or(lhs, rhs) });
+        }
+
         if let Some(opr) = self.try_parse_oper() {
           if (opr == Op::ADD || opr == Op::SUB) && self.peek_one().is_some_and(|c| "0123456789".contains(c)) {
             unexpected_tag(self)?;
@@ -732,9 +1269,9 @@ impl<'a> FunParser<'a> {
       }
 
       // String
-      if self.starts_with("\"") {
+      if self.starts_with_string() {
         unexpected_tag(self)?;
-        let str = self.parse_quoted_string()?;
+        let str = self.parse_string()?;
         return Ok(Term::Str { val: STRINGS.get(str) });
       }
 
@@ -763,7 +1300,7 @@ impl<'a> FunParser<'a> {
       if self.try_parse_keyword("use") {
         unexpected_tag(self)?;
         self.skip_trivia();
-        let nam = self.parse_var_name()?;
+        let nam = self.parse_binder_name()?;
         self.consume("=")?;
         let val = self.parse_term()?;
         self.try_consume(";");
@@ -777,7 +1314,21 @@ impl<'a> FunParser<'a> {
         let pat = self.parse_pattern(true)?;
         self.consume("=")?;
         let val = self.parse_term()?;
-        self.try_consume(";");
+        self.skip_trivia();
+        let in_idx = *self.index();
+        if self.try_parse_keyword("in") {
+          // Without a term after it, `in` was likely meant as a variable, the next term of the `let`.
+          self.skip_trivia();
+          if self.is_eof() || [")", "}", "]", ";", ","].iter().any(|end| self.starts_with(end)) {
+            let msg = concat!(
+              "Expected the next term of the 'let' after 'in'.\n",
+              "To use a variable named 'in' as the next term, separate it with ';', like 'let x = val; in'."
+            );
+            return self.err_msg_spanned(msg, in_idx..in_idx + "in".len());
+          }
+        } else {
+          self.try_consume(";");
+        }
         let nxt = self.parse_term()?;
         return Ok(Term::Let { pat: Box::new(pat), val: Box::new(val), nxt: Box::new(nxt) });
       }
@@ -793,13 +1344,33 @@ impl<'a> FunParser<'a> {
         return Ok(Term::Ask { pat: Box::new(pat), val: Box::new(val), nxt: Box::new(nxt) });
       }
 
+      // Assert, unless it's a variable named `assert`, which isn't followed by a condition and a `;`.
+      if self.starts_with_keyword("assert") {
+        let ini_idx = *self.index();
+        let loc = assert_location(&self.file, self.input, self.index);
+        self.parse_keyword("assert")?;
+        if let Some(cond) = self.parse_term().ok().filter(|_| self.try_consume(";")) {
+          unexpected_tag(self)?;
+          let nxt = self.parse_term()?;
+          return Ok(Term::Swt(Box::new(SwitchTerm {
+            bnd: Some(Name::new("%assert")),
+            arg: cond,
+            with_bnd: Vec::new(),
+            with_arg: Vec::new(),
+            pred: Some(Name::new("%assert-1")),
+            arms: vec![Term::call(Term::Var { nam: Name::new(ASSERT_FAILED) }, [Term::str(&loc)]), nxt],
+          })));
+        }
+        self.backtrack(ini_idx);
+      }
+
       // Def
       if self.try_parse_keyword("def") {
         self.skip_trivia();
         let mut def = self.parse_fun_def()?;
         def.source.kind = SourceKind::Generated;
         let nxt = self.parse_term()?;
-        return Ok(Term::Def { def, nxt: Box::new(nxt) });
+        return Ok(Term::Def { def: Box::new(def), nxt: Box::new(nxt) });
       }
 
       // If
@@ -826,13 +1397,15 @@ impl<'a> FunParser<'a> {
         self.consume("{")?;
         let els = self.parse_term()?;
         self.consume("}")?;
-        let els = chain.into_iter().rfold(els, |acc, (cnd, thn)| Term::Swt {
-          bnd: Some(Name::new("%cond")),
-          arg: Box::new(cnd),
-          with_bnd: Vec::new(),
-          with_arg: Vec::new(),
-          pred: Some(Name::new("%cond-1")),
-          arms: vec![acc, thn],
+        let els = chain.into_iter().rfold(els, |acc, (cnd, thn)| {
+          Term::Swt(Box::new(SwitchTerm {
+            bnd: Some(Name::new("%cond")),
+            arg: cnd,
+            with_bnd: Vec::new(),
+            with_arg: Vec::new(),
+            pred: Some(Name::new("%cond-1")),
+            arms: vec![acc, thn],
+          }))
         });
         return Ok(els);
       }
@@ -840,10 +1413,15 @@ impl<'a> FunParser<'a> {
       // Match
       if self.try_parse_keyword("match") {
         unexpected_tag(self)?;
+        let ini_idx = *self.index();
         let (bnd, arg) = self.parse_match_arg()?;
+        self.skip_trivia();
+        if self.starts_with(",") {
+          return self.parse_multi_match(ini_idx, arg);
+        }
         let (with_bnd, with_arg) = self.parse_with_clause()?;
-        let arms = self.list_like(|p| p.parse_match_arm(), "", "}", ";", false, 1)?;
-        return Ok(Term::Mat { arg: Box::new(arg), bnd, with_bnd, with_arg, arms });
+        let arms = self.list_like(|p| p.parse_guarded_match_arm(), "", "}", ";", false, 1)?;
+        return Ok(Term::guarded_match(arg, bnd, with_bnd, with_arg, arms));
       }
 
       // Switch
@@ -881,7 +1459,7 @@ impl<'a> FunParser<'a> {
           self.try_consume(";");
         }
         let pred = Some(Name::new(format!("{}-{}", bnd.as_ref().unwrap(), arms.len() - 1)));
-        return Ok(Term::Swt { arg: Box::new(arg), bnd, with_bnd, with_arg, pred, arms });
+        return Ok(Term::Swt(Box::new(SwitchTerm { arg, bnd, with_bnd, with_arg, pred, arms })));
       }
 
       // With (monadic block)
@@ -900,7 +1478,7 @@ impl<'a> FunParser<'a> {
         let (bnd, arg) = self.parse_match_arg()?;
         let (with_bnd, with_arg) = self.parse_with_clause()?;
         let arms = self.list_like(|p| p.parse_match_arm(), "", "}", ";", false, 1)?;
-        return Ok(Term::Fold { arg: Box::new(arg), bnd, with_bnd, with_arg, arms });
+        return Ok(Term::Fold(Box::new(MatchTerm { arg, bnd, with_bnd, with_arg, arms })));
       }
 
       // Bend
@@ -908,7 +1486,7 @@ impl<'a> FunParser<'a> {
         unexpected_tag(self)?;
         let args = self.list_like(
           |p| {
-            let bind = p.parse_var_name()?;
+            let bind = p.parse_binder_name()?;
             let init = if p.try_consume("=") { p.parse_term()? } else { Term::Var { nam: bind.clone() } };
             Ok((bind, init))
           },
@@ -930,13 +1508,7 @@ impl<'a> FunParser<'a> {
         self.consume(":")?;
         let base = self.parse_term()?;
         self.consume("}")?;
-        return Ok(Term::Bend {
-          bnd: bind,
-          arg: init,
-          cond: Box::new(cond),
-          step: Box::new(step),
-          base: Box::new(base),
-        });
+        return Ok(Term::Bend(Box::new(BendTerm { bnd: bind, arg: init, cond, step, base })));
       }
 
       // Open
@@ -953,7 +1525,10 @@ impl<'a> FunParser<'a> {
 
       // Var
       unexpected_tag(self)?;
+      let ini_idx = *self.index();
       let nam = self.labelled(|p| p.parse_var_name(), "term")?;
+      let end_idx = *self.index();
+      self.add_var(&nam, ini_idx..end_idx);
       Ok(Term::Var { nam })
     })
   }
@@ -972,6 +1547,21 @@ impl<'a> FunParser<'a> {
     )
   }
 
+  /// Like [`Self::parse_name_or_era`], for a name that binds a variable.
+  fn parse_binder_or_era(&mut self) -> ParseResult<Option<Name>> {
+    self.labelled(
+      |p| {
+        if p.try_consume_exactly("*") {
+          Ok(None)
+        } else {
+          let nam = p.parse_binder_name()?;
+          Ok(Some(nam))
+        }
+      },
+      "name or '*'",
+    )
+  }
+
   /// Parses a tag where it may or may not be valid.
   ///
   /// If it is not valid, the returned callback can be used to issue an error.
@@ -1007,6 +1597,8 @@ impl<'a> FunParser<'a> {
     match (&mut arg, self.starts_with("=")) {
       (Term::Var { nam }, true) => {
         self.consume("=")?;
+        // The name binds the value instead of using a variable.
+        self.binders.extend(self.vars.pop());
         Ok((Some(std::mem::take(nam)), self.parse_term()?))
       }
       (Term::Var { nam }, false) => Ok((Some(nam.clone()), Term::Var { nam: std::mem::take(nam) })),
@@ -1049,6 +1641,52 @@ impl<'a> FunParser<'a> {
     Ok((nam, vec![], bod))
   }
 
+  /// Parses the rest of a match on multiple scrutinees: `match <term> ("," <term>)+ { (<pat> ("," <pat>)* ":" <term>)* }`.
+  ///
+  /// It's compiled as a local pattern matching function applied to the scrutinees,
+  /// so that all the patterns are desugared together into a single decision tree.
+  fn parse_multi_match(&mut self, ini_idx: usize, fst: Term) -> ParseResult<Term> {
+    let mut args = vec![fst];
+    while self.try_consume(",") {
+      args.push(self.parse_term()?);
+    }
+    self.consume("{")?;
+    let rules = self.list_like(|p| p.parse_multi_match_arm(args.len()), "", "}", ";", false, 1)?;
+    let end_idx = *self.index();
+
+    let source = Source::from_file_span(&self.file, self.input, ini_idx..end_idx, self.builtin);
+    let name = Name::new("match");
+    let def = FunDefinition::new_gen(name.clone(), rules, source, false);
+    let nxt = Term::call(Term::Var { nam: name }, args);
+    Ok(Term::Def { def: Box::new(def), nxt: Box::new(nxt) })
+  }
+
+  fn parse_multi_match_arm(&mut self, arity: usize) -> ParseResult<Rule> {
+    self.try_consume("|");
+    self.skip_trivia();
+    let ini_idx = *self.index();
+    let pats = self.list_like(|p| p.parse_pattern(false), "", ":", ",", false, 1)?;
+    let end_idx = *self.index();
+    if pats.len() != arity {
+      let msg = format!("Expected {arity} patterns in 'match' arm, found {}.", pats.len());
+      return self.err_msg_spanned(&msg, ini_idx..end_idx);
+    }
+    let body = self.parse_term()?;
+    Ok(Rule { pats, body })
+  }
+
+  /// A match arm with an optional numeric guard: `<ctr> ("if" <term>)? ":" <term>`.
+  fn parse_guarded_match_arm(&mut self) -> ParseResult<GuardedMatchRule> {
+    self.try_consume("|");
+    self.skip_trivia();
+    let nam = self.parse_name_or_era()?;
+    self.skip_trivia();
+    let guard = if self.try_parse_keyword("if") { Some(self.parse_term()?) } else { None };
+    self.consume(":")?;
+    let bod = self.parse_term()?;
+    Ok((nam, guard, bod))
+  }
+
   fn parse_type_term(&mut self) -> ParseResult<Type> {
     let mut left = self.parse_type_atom()?;
     self.skip_trivia();
@@ -1119,6 +1757,23 @@ impl<'a> FunParser<'a> {
   }
 
   fn add_fun_def(&mut self, def: FunDefinition, book: &mut ParseBook, span: Range<usize>) -> ParseResult<()> {
+    // Rules of a function that was already defined with rules, but with other definitions in between.
+    if let Some(prev) = book.fun_defs.get(&def.name) {
+      let is_rules = def.typ == Type::Any && !def.check && def.arity() == prev.arity();
+      if !prev.is_builtin() && is_rules {
+        let prev_span = prev.source.span.map(|span| span.to_byte_span(self.input)).unwrap_or(span.clone());
+        let out_of_order_msg = format!(
+          "Rules of function '{}' are out of order, there are other definitions between them.\nFirst rules:\n{}\nOut of order rules:\n{}",
+          def.name,
+          highlight_error(prev_span.start, prev_span.end, self.input),
+          highlight_error(span.start, span.end, self.input),
+        );
+        let redefinition_msg = self.redefinition_msg(&def.name, prev_span, span);
+        // Whether the rules are gathered or replace the function is decided when the diagnostics are reported.
+        book.rules_out_of_order.push(OutOfOrderRules { def, out_of_order_msg, redefinition_msg });
+        return Ok(());
+      }
+    }
     self.check_top_level_redefinition(&def.name, book, span)?;
     book.fun_defs.insert(def.name.clone(), def);
     Ok(())
@@ -1142,6 +1797,7 @@ impl<'a> FunParser<'a> {
   }
 
   fn add_type_def(&mut self, adt: Adt, book: &mut ParseBook, span: Range<usize>) -> ParseResult<()> {
+    Self::shadow_builtin_type(&adt.name, book);
     self.check_type_redefinition(&adt.name, book, span.clone())?;
     for ctr in adt.ctrs.keys() {
       if let Some(builtin) = book.contains_builtin_def(ctr) {
@@ -1167,8 +1823,15 @@ impl<'a> FunParser<'a> {
     span: Range<usize>,
   ) -> ParseResult<()> {
     if let Some(builtin) = book.contains_builtin_def(name) {
-      let msg = Self::redefinition_of_function_msg(builtin, name);
-      return self.err_msg_spanned(&msg, span);
+      if !builtin {
+        self.replace_redefined(name, book, span.clone());
+      } else if builtins::SHADOWABLE_DEFS.contains(&name.as_ref()) || builtins::is_in_shadowable_type(name) {
+        book.fun_defs.shift_remove(name);
+        book.imp_defs.shift_remove(name);
+      } else {
+        let msg = Self::redefinition_of_function_msg(builtin, name);
+        return self.err_msg_spanned(&msg, span);
+      }
     }
     if book.ctrs.contains_key(name) {
       let msg = Self::redefinition_of_constructor_msg(name);
@@ -1181,6 +1844,56 @@ impl<'a> FunParser<'a> {
     Ok(())
   }
 
+  /// Removes the previous definition of a function that is being defined again, so the last one is used.
+  /// The diagnostic decides if that's allowed.
+  fn replace_redefined(&mut self, name: &Name, book: &mut ParseBook, span: Range<usize>) {
+    let prev_source = if let Some(def) = book.fun_defs.shift_remove(name) {
+      def.source
+    } else if let Some(def) = book.imp_defs.shift_remove(name) {
+      def.source
+    } else if let Some(def) = book.hvm_defs.shift_remove(name) {
+      def.source
+    } else {
+      return;
+    };
+    // The rules found after the replaced definition are replaced with it.
+    book.rules_out_of_order.retain(|rules| &rules.def.name != name);
+    let prev_span = prev_source.span.map(|span| span.to_byte_span(self.input)).unwrap_or(span.clone());
+    let msg = self.redefinition_msg(name, prev_span, span.clone());
+    let source = Source::from_file_span(&self.file, self.input, span, self.builtin);
+    book.redefinitions.push((name.clone(), msg, source));
+  }
+
+  fn redefinition_msg(&self, name: &Name, prev_span: Range<usize>, span: Range<usize>) -> String {
+    // The span of an imp definition goes until the next definition.
+    let trim = |span: Range<usize>| match self.input.get(span.clone()) {
+      Some(txt) => span.start..span.start + txt.trim_end().len(),
+      None => span,
+    };
+    let (prev_span, span) = (trim(prev_span), trim(span));
+    format!(
+      "Function '{name}' is defined more than once.\nFirst definition:\n{}\nRedefinition:\n{}",
+      highlight_error(prev_span.start, prev_span.end, self.input),
+      highlight_error(span.start, span.end, self.input),
+    )
+  }
+
+  /// Removes the builtin type that a type of the program replaces, if it's one of the shadowable ones,
+  /// with its constructors and the builtin functions in its namespace.
+  fn shadow_builtin_type(name: &Name, book: &mut ParseBook) {
+    if !builtins::SHADOWABLE_TYPES.contains(&name.as_ref()) {
+      return;
+    }
+    let Some(adt) = book.adts.get(name).filter(|adt| adt.source.is_builtin()) else { return };
+    for ctr in adt.ctrs.keys() {
+      book.ctrs.shift_remove(ctr);
+    }
+    book.adts.shift_remove(name);
+    let prefix = format!("{name}/");
+    book.fun_defs.retain(|nam, def| !(def.is_builtin() && nam.starts_with(&prefix)));
+    book.imp_defs.retain(|nam, def| !(def.source.is_builtin() && nam.starts_with(&prefix)));
+  }
+
   fn check_type_redefinition(
     &mut self,
     name: &Name,
@@ -1200,6 +1913,10 @@ impl<'a> Parser<'a> for FunParser<'a> {
     self.input
   }
 
+  fn parse_char(&mut self) -> ParseResult<char> {
+    self.parse_text_char()
+  }
+
   fn index(&mut self) -> &mut usize {
     &mut self.index
   }
@@ -1242,21 +1959,14 @@ impl<'a> Parser<'a> for FunParser<'a> {
         continue;
       }
       if c == '#' {
+        if self.starts_with_directive() {
+          break;
+        }
         self.advance_one();
         if let Some(c) = self.peek_one() {
           if c == '{' {
             self.advance_one();
-            while let Some(c) = self.peek_one() {
-              self.advance_one();
-              if c == '#' {
-                if let Some('}') = self.peek_one() {
-                  self.advance_one();
-                  break;
-                } else {
-                  self.advance_one();
-                }
-              }
-            }
+            self.skip_block_comment();
           } else {
             while let Some(c) = self.peek_one() {
               if c != '\n' {
@@ -1274,14 +1984,47 @@ impl<'a> Parser<'a> for FunParser<'a> {
   }
 }
 
+/// The attributes that can be written before a top-level item.
+const ATTRIBUTES: &[&str] = &["cfg", "deprecated", "test", "inline", "noinline", "main"];
+
+/// Whether the byte `idx` of `input` starts a directive, an `#include "path"` or one of the [`ATTRIBUTES`].
+/// The directives must be at the start of a line, the other lines starting with `#` are comments.
+fn is_directive(input: &str, idx: usize) -> bool {
+  if idx != 0 && !input[..idx].ends_with('\n') {
+    return false;
+  }
+  let input = &input[idx..];
+  if let Some(rest) = input.strip_prefix("#include") {
+    return rest.starts_with([' ', '"']);
+  }
+  let Some(attr) = input.strip_prefix("#[") else { return false };
+  let name = &attr[..attr.find(|c| !is_name_char(c)).unwrap_or(attr.len())];
+  ATTRIBUTES.contains(&name) && attr[name.len()..].starts_with([']', '('])
+}
+
 pub fn is_name_char(c: char) -> bool {
   c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '-' || c == '/'
 }
 
+/// The location of an `assert` that starts at the byte `idx` of the file, as `file:line:column`.
+pub fn assert_location(file: &Name, input: &str, idx: usize) -> String {
+  let loc = TextLocation::from_byte_loc(input, idx);
+  format!("{}:{}:{}", file, loc.line + 1, loc.char + 1)
+}
+
 pub fn is_num_char(c: char) -> bool {
   "0123456789+-".contains(c)
 }
 
+/// Removes the `_` separators of the digits of a number, only copying it when there are any.
+fn without_digit_separators(digits: &str) -> Cow<'_, str> {
+  if digits.contains('_') {
+    Cow::Owned(digits.chars().filter(|c| *c != '_').collect())
+  } else {
+    Cow::Borrowed(digits)
+  }
+}
+
 pub fn make_fn_type(args: Vec<Type>, ret: Type) -> Type {
   args.into_iter().rfold(ret, |acc, typ| Type::Arr(Box::new(typ), Box::new(acc)))
 }
@@ -1316,9 +2059,47 @@ impl Indent {
   }
 }
 
-impl<'a> ParserCommons<'a> for FunParser<'a> {}
+impl<'a> ParserCommons<'a> for FunParser<'a> {
+  fn reserved_keywords(&self) -> &'static [&'static str] {
+    &["use", "let", "ask", "def", "if", "match", "switch", "with", "fold", "bend", "open"]
+  }
+
+  fn add_keyword_name(&mut self, span: Range<usize>) {
+    // The same name can be checked again when the parser backtracks.
+    if !self.keyword_names.contains(&span) {
+      self.keyword_names.push(span);
+    }
+  }
+
+  fn add_binder(&mut self, name: &Name, span: Range<usize>) {
+    // The builtin definitions are never reported, so their binders aren't kept.
+    if !self.builtin {
+      self.binders.push((name.clone(), span));
+    }
+  }
+
+  fn add_var(&mut self, name: &Name, span: Range<usize>) {
+    if !self.builtin {
+      self.vars.push((name.clone(), span));
+    }
+  }
+}
 
 pub trait ParserCommons<'a>: Parser<'a> {
+  /// Keywords that can only be used as names when written as raw identifiers.
+  fn reserved_keywords(&self) -> &'static [&'static str] {
+    &[]
+  }
+
+  /// Records the span of a name that is a reserved keyword, to warn that it can't be referenced in a term.
+  fn add_keyword_name(&mut self, _span: Range<usize>) {}
+
+  /// Records the span of a name that binds a variable, which the definition being parsed keeps.
+  fn add_binder(&mut self, _name: &Name, _span: Range<usize>) {}
+
+  /// Records the span of a variable used in a term, like [`Self::add_binder`].
+  fn add_var(&mut self, _name: &Name, _span: Range<usize>) {}
+
   fn labelled<T>(&mut self, parser: impl Fn(&mut Self) -> ParseResult<T>, label: &str) -> ParseResult<T> {
     match parser(self) {
       Ok(val) => Ok(val),
@@ -1328,11 +2109,15 @@ pub trait ParserCommons<'a>: Parser<'a> {
 
   fn parse_restricted_name(&mut self, kind: &str) -> ParseResult<Name> {
     let ini_idx = *self.index();
+    // Raw identifier, to allow using keywords as names, like `r#let`.
+    if !self.try_consume_exactly("r#") {
+      self.check_reserved_keyword();
+    }
     let name = self.take_while(is_name_char);
     if name.is_empty() {
       self.expected(&format!("{kind} name"))?
     }
-    let name = Name::new(name.to_owned());
+    let name = Name::new(name);
     let end_idx = *self.index();
     if name.contains("__") {
       let msg = format!("{kind} names are not allowed to contain \"__\".");
@@ -1345,6 +2130,16 @@ pub trait ParserCommons<'a>: Parser<'a> {
     }
   }
 
+  /// Records the next name if it's a reserved keyword that isn't written as a raw identifier.
+  fn check_reserved_keyword(&mut self) {
+    let ini_idx = *self.index();
+    let input = &self.input()[ini_idx..];
+    let name = &input[..input.find(|c| !is_name_char(c)).unwrap_or(input.len())];
+    if self.reserved_keywords().contains(&name) {
+      self.add_keyword_name(ini_idx..ini_idx + name.len());
+    }
+  }
+
   fn parse_top_level_name(&mut self) -> ParseResult<Name> {
     self.parse_restricted_name("Top-level")
   }
@@ -1353,6 +2148,15 @@ pub trait ParserCommons<'a>: Parser<'a> {
     self.parse_restricted_name("Variable")
   }
 
+  /// Parses the name of a variable that is bound here, recording where it's written.
+  fn parse_binder_name(&mut self) -> ParseResult<Name> {
+    let ini_idx = *self.index();
+    let name = self.parse_var_name()?;
+    let end_idx = *self.index();
+    self.add_binder(&name, ini_idx..end_idx);
+    Ok(name)
+  }
+
   fn parse_name_maybe_alias(&mut self, label: &str) -> ParseResult<(Name, Option<Name>)> {
     let name = self.parse_restricted_name(label)?;
 
@@ -1404,6 +2208,37 @@ pub trait ParserCommons<'a>: Parser<'a> {
     }
   }
 
+  /// Whether the parser is at a directive, an `#include` or an attribute like `#[test]`.
+  /// A directive is part of the program, so skipping trivia stops at it instead of skipping it as a comment.
+  fn starts_with_directive(&mut self) -> bool {
+    let idx = *self.index();
+    is_directive(self.input(), idx)
+  }
+
+  /// Skips the rest of a block comment after its opening `#{`, returning how many characters were skipped.
+  /// Block comments nest, so each `#{` inside of one must be closed by its own `#}`.
+  fn skip_block_comment(&mut self) -> usize {
+    let mut depth = 1;
+    let mut char_count = 0;
+    while let Some(c) = self.peek_one() {
+      self.advance_one();
+      char_count += 1;
+      if c == '#' {
+        match self.peek_one() {
+          Some('{') => depth += 1,
+          Some('}') => depth -= 1,
+          _ => continue,
+        }
+        self.advance_one();
+        char_count += 1;
+        if depth == 0 {
+          break;
+        }
+      }
+    }
+    char_count
+  }
+
   /// Advances the parser to the next non-trivia character in the same line.
   /// Returns how many characters were advanced.
   fn advance_trivia_inline(&mut self) -> ParseResult<isize> {
@@ -1419,26 +2254,16 @@ pub trait ParserCommons<'a>: Parser<'a> {
         continue;
       }
       if c == '#' {
+        if self.starts_with_directive() {
+          break;
+        }
         self.advance_one();
         char_count += 1;
         if let Some(c) = self.peek_one() {
           if c == '{' {
             self.advance_one();
             char_count += 1;
-            while let Some(c) = self.peek_one() {
-              self.advance_one();
-              char_count += 1;
-              if c == '#' {
-                if let Some('}') = self.peek_one() {
-                  self.advance_one();
-                  char_count += 1;
-                  break;
-                } else {
-                  self.advance_one();
-                  char_count += 1;
-                }
-              }
-            }
+            char_count += self.skip_block_comment() as isize;
           } else {
             while let Some(c) = self.peek_one() {
               if c != '\n' {
@@ -1692,7 +2517,7 @@ pub trait ParserCommons<'a>: Parser<'a> {
       _ => Radix::Dec,
     };
     let num_str = self.take_while(move |c| c.is_digit(radix as u32) || c == '_');
-    let num_str = num_str.chars().filter(|c| *c != '_').collect::<String>();
+    let num_str = without_digit_separators(num_str);
 
     let next_is_hex = self.peek_one().is_some_and(|c| "0123456789abcdefABCDEF".contains(c));
     if next_is_hex || num_str.is_empty() {
@@ -1703,15 +2528,16 @@ pub trait ParserCommons<'a>: Parser<'a> {
     }
   }
 
-  fn u32_with_radix(&mut self, radix: Radix) -> ParseResult<u32> {
+  /// Parses the digits of a number, returning `None` if it doesn't fit in a `u32`.
+  fn u32_with_radix(&mut self, radix: Radix) -> ParseResult<Option<u32>> {
     let num_str = self.take_while(move |c| c.is_digit(radix as u32) || c == '_');
-    let num_str = num_str.chars().filter(|c| *c != '_').collect::<String>();
+    let num_str = without_digit_separators(num_str);
     let next_is_hex = self.peek_one().is_some_and(|c| "0123456789abcdefABCDEF".contains(c));
+    let next_is_hex = next_is_hex && !(radix == Radix::Dec && self.starts_with_exponent());
     if next_is_hex || num_str.is_empty() {
       self.expected(format!("valid {radix} digit").as_str())
     } else {
-      u32::from_str_radix(&num_str, radix as u32)
-        .map_err(|e| self.expected_and::<u64>("integer", &e.to_string()).unwrap_err())
+      Ok(u32::from_str_radix(&num_str, radix as u32).ok())
     }
   }
 
@@ -1735,22 +2561,59 @@ pub trait ParserCommons<'a>: Parser<'a> {
       }
       _ => Radix::Dec,
     };
-    let num = self.u32_with_radix(radix)?;
+    let Some(num) = self.u32_with_radix(radix)? else {
+      let typ = if self.try_consume_exactly(".") {
+        self.take_while(|c| c.is_digit(radix as u32) || c == '_');
+        "F24"
+      } else if sign.is_some() {
+        "I24"
+      } else {
+        "U24"
+      };
+      return self.num_range_err(ini_idx, typ);
+    };
     let frac = if let Some('.') = self.peek_one() {
       self.advance_one();
       let fra_str = self.take_while(|c| c.is_digit(radix as u32) || c == '_');
-      let fra_str = fra_str.chars().filter(|c| *c != '_').collect::<String>();
-      let fra = u32::from_str_radix(&fra_str, radix as u32)
-        .map_err(|e| self.expected_and::<u64>("integer", &e.to_string()).unwrap_err())?;
-      let fra = fra as f32 / (radix.to_f32()).powi(fra_str.len() as i32);
+      let fra_str = without_digit_separators(fra_str);
+      if fra_str.is_empty() {
+        return self.expected(format!("valid {radix} digit").as_str());
+      }
+      // Adds the digits from the last one, so that any number of them fits.
+      let fra = fra_str
+        .chars()
+        .rev()
+        .fold(0.0, |fra, c| (fra + c.to_digit(radix as u32).unwrap() as f32) / radix.to_f32());
       Some(fra)
     } else {
       None
     };
 
-    if let Some(frac) = frac {
+    let exp = if radix == Radix::Dec && self.starts_with_exponent() {
+      self.advance_one();
+      let exp_sign = if self.try_consume_exactly("-") {
+        -1
+      } else {
+        self.try_consume_exactly("+");
+        1
+      };
+      let exp_str = self.take_while(|c| c.is_ascii_digit() || c == '_');
+      let exp = without_digit_separators(exp_str).parse::<i32>().unwrap_or(i32::MAX);
+      Some(exp_sign * exp)
+    } else {
+      None
+    };
+
+    if frac.is_some() || exp.is_some() {
       let sign = sign.unwrap_or(1);
-      return Ok(Num::F24(sign as f32 * (num as f32 + frac)));
+      let val = num as f64 + frac.unwrap_or(0.0) as f64;
+      // Zero stays zero with any exponent, instead of becoming `0 * inf`.
+      let val = if val == 0.0 { val } else { val * 10f64.powi(exp.unwrap_or(0)) };
+      let val = (sign as f64 * val) as f32;
+      if val.is_infinite() {
+        return self.num_range_err(ini_idx, "F24");
+      }
+      return Ok(Num::F24(val));
     }
 
     if let Some(sign) = sign {
@@ -1767,6 +2630,14 @@ pub trait ParserCommons<'a>: Parser<'a> {
     }
   }
 
+  /// Whether the next characters are the exponent of a decimal number, like `e3`, `E-2` or `e+1`.
+  fn starts_with_exponent(&mut self) -> bool {
+    let rest = &self.input()[*self.index()..];
+    let Some(rest) = rest.strip_prefix(['e', 'E']) else { return false };
+    let rest = rest.strip_prefix(['+', '-']).unwrap_or(rest);
+    rest.starts_with(|c: char| c.is_ascii_digit())
+  }
+
   fn num_range_err<T>(&mut self, ini_idx: usize, typ: &str) -> ParseResult<T> {
     let msg = format!("\x1b[1mNumber literal outside of range for {}.\x1b[0m", typ);
     let end_idx = *self.index();
@@ -1800,6 +2671,189 @@ pub trait ParserCommons<'a>: Parser<'a> {
     Ok(result)
   }
 
+  /// Parses a character of a string or character literal, which can be an escape sequence.
+  /// The unicode escapes, like `\u{1F600}`, must be valid unicode scalar values.
+  fn parse_text_char(&mut self) -> ParseResult<char> {
+    let ini_idx = *self.index();
+    match self.advance_one() {
+      Some('\\') => {
+        match self.advance_one() {
+          Some('u') => {
+            self.consume_exactly("{")?;
+            let hex = self.take_while(|c| c.is_ascii_hexdigit());
+            if hex.is_empty() || !self.starts_with("}") {
+              let end_idx = *self.index();
+              return self.err_msg_spanned(
+                concat!(
+                  "Expected a hexadecimal number between braces in a unicode escape, ",
+                  "like '\\u{1F600}'."
+                ),
+                ini_idx..end_idx,
+              );
+            }
+            // The digits of a number too big for a `u32` are also out of range.
+            let codepoint = u32::from_str_radix(hex, 16).ok();
+            let hex = hex.trim_start_matches('0').to_uppercase();
+            self.consume_exactly("}")?;
+            let end_idx = *self.index();
+            match codepoint.map(|codepoint| (codepoint, char::from_u32(codepoint))) {
+              Some((_, Some(chr))) => Ok(chr),
+              Some((0xD800..=0xDFFF, None)) => self.err_msg_spanned(
+                &format!(
+                  concat!(
+                    "Invalid unicode escape, '{}' is a surrogate codepoint, ",
+                    "which isn't a valid character."
+                  ),
+                  hex
+                ),
+                ini_idx..end_idx,
+              ),
+              _ => self.err_msg_spanned(
+                &format!(
+                  concat!(
+                    "Invalid unicode escape, '{}' is out of range, ",
+                    "the maximum unicode codepoint is '10FFFF'."
+                  ),
+                  hex
+                ),
+                ini_idx..end_idx,
+              ),
+            }
+          }
+          Some('0') => Ok('\0'),
+          Some('n') => Ok('\n'),
+          Some('r') => Ok('\r'),
+          Some('t') => Ok('\t'),
+          Some('\'') => Ok('\''),
+          Some('\"') => Ok('\"'),
+          Some('\\') => Ok('\\'),
+          Some(chr) => {
+            let end_idx = *self.index();
+            self.err_msg_spanned(&format!("Unknown escape sequence '\\{chr}'."), ini_idx..end_idx)
+          }
+          None => self.expected("escaped character"),
+        }
+      }
+      Some(chr) => Ok(chr),
+      None => self.expected("character"),
+    }
+  }
+
+  /// Parses a string literal, either a quoted one, a multi-line one or a raw one.
+  fn parse_string(&mut self) -> ParseResult<String> {
+    self.skip_trivia();
+    if self.starts_with_raw_string() {
+      self.parse_raw_string()
+    } else if self.starts_with("\"\"\"") {
+      self.parse_multi_line_string()
+    } else {
+      self.parse_quoted_string()
+    }
+  }
+
+  /// Whether the next token is a string literal, including the raw ones.
+  fn starts_with_string(&mut self) -> bool {
+    self.starts_with("\"") || self.starts_with_raw_string()
+  }
+
+  fn starts_with_raw_string(&mut self) -> bool {
+    let rest = self.input().get(*self.index()..).unwrap_or_default();
+    rest.strip_prefix('r').is_some_and(|rest| rest.trim_start_matches('#').starts_with('"'))
+  }
+
+  /// Parses a raw string, like `r"C:\path"` or `r#"a "quoted" word"#`, whose contents are kept as they are written.
+  ///
+  /// It ends at the first `"` followed by as many `#` as there are between the `r` and the opening `"`.
+  fn parse_raw_string(&mut self) -> ParseResult<String> {
+    let ini_idx = *self.index();
+    self.consume_exactly("r")?;
+    let hashes = self.take_while(|c| c == '#').len();
+    self.consume_exactly("\"")?;
+    let closing = format!("\"{}", "#".repeat(hashes));
+    let rest = self.input().get(*self.index()..).unwrap_or_default();
+    let Some(len) = rest.find(&closing) else {
+      let end_idx = *self.index();
+      return self.err_msg_spanned(
+        &format!("Unclosed raw string, expected it to end with '{closing}'."),
+        ini_idx..end_idx,
+      );
+    };
+    let text = rest[..len].to_string();
+    *self.index() += len + closing.len();
+    Ok(text)
+  }
+
+  /// Parses a multi-line string, surrounded by `"""`, with its contents starting on the line after the opening one.
+  ///
+  /// The closing `"""` must be on its own line, and its indentation is removed from every line of the string.
+  /// The newlines after the opening `"""` and before the closing one are not part of the string.
+  fn parse_multi_line_string(&mut self) -> ParseResult<String> {
+    let ini_idx = *self.index();
+    self.consume_exactly("\"\"\"")?;
+    self.take_while(|c| c == ' ' || c == '\t' || c == '\r');
+    if !self.try_consume_exactly("\n") {
+      let end_idx = *self.index();
+      return self.err_msg_spanned(
+        "The contents of a multi-line string must start on the line after the opening '\"\"\"'.",
+        ini_idx..end_idx,
+      );
+    }
+
+    // Each line, with its start index and how many literal spaces and tabs it is indented with.
+    let mut lines = vec![];
+    let mut line = (*self.index(), 0, String::new());
+    let mut at_line_start = true;
+    while !self.starts_with("\"\"\"") {
+      match self.peek_one() {
+        Some('\n') => {
+          self.advance_one();
+          if line.2.ends_with('\r') {
+            line.2.pop();
+          }
+          lines.push(std::mem::replace(&mut line, (*self.index(), 0, String::new())));
+          at_line_start = true;
+        }
+        Some(chr @ (' ' | '\t')) if at_line_start => {
+          self.advance_one();
+          line.1 += 1;
+          line.2.push(chr);
+        }
+        Some(_) => {
+          line.2.push(self.parse_text_char()?);
+          at_line_start = false;
+        }
+        None => return self.expected("'\"\"\"'"),
+      }
+    }
+    if !at_line_start {
+      let end_idx = *self.index();
+      return self.err_msg_spanned(
+        "The closing '\"\"\"' of a multi-line string must be on its own line.",
+        line.0..end_idx,
+      );
+    }
+    self.consume_exactly("\"\"\"")?;
+
+    let indent = line.1;
+    let mut result = vec![];
+    for (line_idx, line_indent, text) in lines {
+      if line_indent < indent && text.len() != line_indent {
+        let end_idx = line_idx + line_indent;
+        return self.err_msg_spanned(
+          "A line of a multi-line string is less indented than its closing '\"\"\"'.",
+          line_idx..end_idx,
+        );
+      }
+      if line_indent < indent {
+        // A blank line.
+        result.push(String::new());
+      } else {
+        result.push(text[indent..].to_string());
+      }
+    }
+    Ok(result.join("\n"))
+  }
+
   fn check_repeated_ctr_fields(
     &mut self,
     fields: &[CtrField],
@@ -1,9 +1,10 @@
 use crate::{
   diagnostics::Diagnostics,
-  fun::{num_to_name, Book, FanKind, Name, Op, Pattern, Term},
+  fun::{num_to_name, Book, FanKind, Name, Op, Pattern, SwitchTerm, Term},
   hvm::{net_trees, tree_children},
   maybe_grow,
   net::CtrKind::{self, *},
+  ENTRY_POINT, HVM1_ENTRY_POINT,
 };
 use hvm::ast::{Net, Tree};
 use loaned::LoanedMut;
@@ -15,24 +16,28 @@ use std::{
 #[derive(Debug, Clone)]
 pub struct ViciousCycleErr;
 
+#[tracing::instrument(level = "debug", skip_all)]
 pub fn book_to_hvm(book: &Book, diags: &mut Diagnostics) -> Result<(hvm::ast::Book, Labels), Diagnostics> {
   let mut hvm_book = hvm::ast::Book { defs: Default::default() };
   let mut labels = Labels::default();
 
-  let main = book.entrypoint.as_ref();
-
   for def in book.defs.values() {
     for rule in def.rules.iter() {
       let net = term_to_hvm(&rule.body, &mut labels);
-
-      let name = if main.is_some_and(|m| &def.name == m) {
-        book.hvm_entrypoint().to_string()
+      let is_entrypoint = book.entrypoint.as_ref() == Some(&def.name);
+      let name = if is_entrypoint && def.name == HVM1_ENTRY_POINT {
+        ENTRY_POINT.to_string()
       } else {
         def.name.0.to_string()
       };
 
       match net {
         Ok(net) => {
+          // HVM always starts from `main`, so a custom entry point is also compiled as `main`,
+          // keeping its own net for the references to it.
+          if is_entrypoint && name != ENTRY_POINT {
+            hvm_book.defs.insert(ENTRY_POINT.to_string(), net.clone());
+          }
           hvm_book.defs.insert(name, net);
         }
         Err(err) => diags.add_inet_error(err, name),
@@ -144,7 +149,8 @@ impl<'t> EncodeTermState<'t, '_> {
           self.link(up, node.2);
         }
         // core: & arg ~ ?<(zero succ) ret>
-        Term::Swt { arg, bnd, with_bnd, with_arg, pred, arms,  } => {
+        Term::Swt(swt) => {
+          let SwitchTerm { arg, bnd, with_bnd, with_arg, pred, arms } = swt.as_ref();
           // At this point should be only num matches of 0 and succ.
           assert!(bnd.is_none());
           assert!(with_bnd.is_empty());
@@ -454,7 +460,7 @@ impl LabelGenerator {
 }
 
 impl Op {
-  fn to_native_tag(self) -> hvm::hvm::Tag {
+  pub(crate) fn to_native_tag(self) -> hvm::hvm::Tag {
     match self {
       Op::ADD => hvm::hvm::OP_ADD,
       Op::SUB => hvm::hvm::OP_SUB,
@@ -14,6 +14,7 @@ impl Ctx<'_> {
   /// ```hvm
   /// main = (λx1 λx2 λx3 (MainBody x1 x2 x3) arg1 arg2 arg3)
   /// ```
+  #[tracing::instrument(level = "debug", skip_all)]
   pub fn apply_args(&mut self, args: Option<Vec<Term>>) -> Result<(), Diagnostics> {
     if let Some(entrypoint) = &self.book.entrypoint {
       let main_def = &mut self.book.defs[entrypoint];
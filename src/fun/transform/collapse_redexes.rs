@@ -0,0 +1,102 @@
+use crate::{
+  fun::{Book, FanKind, Name, Pattern, Term},
+  maybe_grow,
+};
+
+impl Book {
+  /// Turns the lambdas that are directly applied to an argument into `let`s,
+  /// like `(λx body arg)` into `let x = arg; body`.
+  ///
+  /// A `let` of a variable compiles to a wire instead of a redex, so the net is the same as the one
+  /// the application would reduce to, without the interaction.
+  /// Applications of a `let`, like `((let x = a; λy body) b)`, are also collapsed by moving the application into the `let`.
+  ///
+  /// Only lambdas of a variable that have the same tag as the application are collapsed.
+  /// Moving applications into `let`s can capture variables, so this must run after making the variable names unique.
+  ///
+  /// The lambdas that only apply a function to their variable are eta-reduced, like `λx (f x)` into `f`,
+  /// and the duplications whose copies are erased are removed, like `let {a *} = val; nxt` into `let a = val; nxt`.
+  #[tracing::instrument(level = "debug", skip_all)]
+  pub fn collapse_redexes(&mut self) {
+    for def in self.defs.values_mut() {
+      for rule in def.rules.iter_mut() {
+        rule.body.collapse_redexes(&def.name);
+      }
+    }
+  }
+}
+
+impl Term {
+  fn collapse_redexes(&mut self, def_name: &Name) {
+    maybe_grow(|| {
+      for child in self.children_mut() {
+        child.collapse_redexes(def_name);
+      }
+      self.collapse_redex();
+      self.eta_reduce(def_name);
+      self.remove_erased_dup();
+    })
+  }
+
+  /// Eta-reduces `λx (f x)` into `f`, if `f` doesn't use `x`.
+  /// A call of the definition to itself is kept, so that `F = λx (F x)` doesn't become `F = F`.
+  fn eta_reduce(&mut self, def_name: &Name) {
+    let Term::Lam { tag, pat, bod } = self else { return };
+    let (Pattern::Var(Some(var)), Term::App { tag: app_tag, fun, arg }) = (pat.as_ref(), bod.as_ref()) else {
+      return;
+    };
+    if tag != app_tag || !matches!(arg.as_ref(), Term::Var { nam } if nam == var) {
+      return;
+    }
+    if matches!(fun.as_ref(), Term::Ref { nam } if nam == def_name) || fun.free_vars().contains_key(var) {
+      return;
+    }
+    let Term::App { fun, .. } = bod.as_mut() else { unreachable!() };
+    *self = std::mem::take(fun.as_mut());
+  }
+
+  /// Removes a duplication whose copies are all erased, or turns it into a `let` if only one copy is used.
+  /// A duplicated value with unscoped variables is kept, since they can be used outside of it.
+  fn remove_erased_dup(&mut self) {
+    let Term::Let { pat, val, nxt } = self else { return };
+    let Pattern::Fan(FanKind::Dup, _, pats) = pat.as_ref() else { return };
+    if !pats.iter().all(|pat| matches!(pat, Pattern::Var(_))) {
+      return;
+    }
+    let free_vars = nxt.free_vars();
+    let used = pats
+      .iter()
+      .flat_map(|pat| pat.binds())
+      .flatten()
+      .filter(|nam| free_vars.contains_key(*nam))
+      .cloned()
+      .collect::<Vec<_>>();
+    match used.as_slice() {
+      [] if !val.has_unscoped() => *self = std::mem::take(nxt.as_mut()),
+      [nam] => **pat = Pattern::Var(Some(nam.clone())),
+      _ => {}
+    }
+  }
+
+  /// Collapses the term if it's an application of a lambda or `let`, whose children were already collapsed.
+  fn collapse_redex(&mut self) {
+    maybe_grow(|| {
+      let Term::App { tag, fun, arg } = self else { return };
+      match fun.as_ref() {
+        Term::Lam { tag: lam_tag, pat, .. } if lam_tag == tag && matches!(pat.as_ref(), Pattern::Var(_)) => {
+          let Term::Lam { pat, bod, .. } = fun.as_mut() else { unreachable!() };
+          let pat = std::mem::replace(pat, Box::new(Pattern::Var(None)));
+          *self = Term::Let { pat, val: std::mem::take(arg), nxt: std::mem::take(bod) };
+        }
+        Term::Let { .. } => {
+          let Term::Let { nxt, .. } = fun.as_mut() else { unreachable!() };
+          let mut app = Term::App { tag: tag.clone(), fun: std::mem::take(nxt), arg: std::mem::take(arg) };
+          app.collapse_redex();
+          **nxt = app;
+          *self = std::mem::take(fun.as_mut());
+        }
+        _ => {}
+      }
+    })
+  }
+}
@@ -14,6 +14,7 @@ impl Book {
   ///
   /// Some of the origins of the rules will be lost in this stage,
   /// Should not be preceded by passes that cares about the origins.
+  #[tracing::instrument(level = "debug", skip_all)]
   pub fn merge_definitions(&mut self) {
     let defs: Vec<_> = self.defs.keys().cloned().collect();
     self.merge(defs.into_iter());
@@ -14,6 +14,8 @@ enum Used {
   NonBuiltin,
   /// Definition is not accessible from main, but is a user-defined constructor.
   Ctr,
+  /// Definition is not accessible from main, but is accessible from a `#[test]` definition.
+  Test,
 }
 
 type Definitions = HashMap<Name, Used>;
@@ -22,7 +24,8 @@ impl Ctx<'_> {
   /// If `prune_all`, removes all unused definitions and adts starting from Main.
   /// Otherwise, prunes only the builtins not accessible from any non-built-in definition.
   ///
-  /// Emits unused definition warnings.
+  /// Emits unused definition warnings, which list the removed definitions when `prune_all`.
+  #[tracing::instrument(level = "debug", skip_all)]
   pub fn prune(&mut self, prune_all: bool) {
     let mut used = Definitions::new();
 
@@ -35,9 +38,16 @@ impl Ctx<'_> {
       }
     }
 
+    // Get the functions that are accessible from the tests, which are the entry points of `bend test`.
+    for test in self.book.tests.iter() {
+      if self.book.defs.contains_key(test) {
+        self.book.insert_used(test, Used::Test, &mut used);
+      }
+    }
+
     // Get the functions that are accessible from non-builtins.
     for def in self.book.defs.values() {
-      if !def.is_builtin() && !(used.get(&def.name) == Some(&Used::Main)) {
+      if !def.is_builtin() && !matches!(used.get(&def.name), Some(Used::Main | Used::Test)) {
         if self.book.ctrs.contains_key(&def.name) {
           used.insert(def.name.clone(), Used::Ctr);
         } else {
@@ -49,7 +59,7 @@ impl Ctx<'_> {
       }
     }
     for def in self.book.hvm_defs.values() {
-      if !def.source.is_builtin() && !(used.get(&def.name) == Some(&Used::Main)) {
+      if !def.source.is_builtin() && !matches!(used.get(&def.name), Some(Used::Main | Used::Test)) {
         used.insert(def.name.clone(), Used::NonBuiltin);
         self.book.find_used_definitions_from_hvm_net(&def.body, Used::NonBuiltin, &mut used);
       }
@@ -78,16 +88,22 @@ impl Ctx<'_> {
           }
           Used::NonBuiltin => {
             // Used by a non-builtin definition.
-            // Prune if `prune_all`, otherwise show a warning.
+            // Prune if `prune_all`, and show a warning for the ones written by the user.
+            let msg = if prune_all {
+              rm_def(self.book, &def);
+              "Definition is unused, so it was removed."
+            } else {
+              "Definition is unused."
+            };
+            if !def.is_generated() && !matches!(src.kind, SourceKind::Generated) {
+              self.info.add_function_warning(msg, WarningType::UnusedDefinition, def, src);
+            }
+          }
+          Used::Test => {
+            // Used by a test, which is only run by `bend test`.
+            // Prune if `prune_all`, otherwise nothing.
             if prune_all {
               rm_def(self.book, &def);
-            } else if !def.is_generated() && !matches!(src.kind, SourceKind::Generated) {
-              self.info.add_function_warning(
-                "Definition is unused.",
-                WarningType::UnusedDefinition,
-                def,
-                src,
-              );
             }
           }
           Used::Ctr => {
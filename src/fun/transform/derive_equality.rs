@@ -0,0 +1,154 @@
+use crate::{
+  diagnostics::Diagnostics,
+  fun::{Adt, Adts, Ctx, Definition, Name, Num, Op, Pattern, Rule, SwitchTerm, Term, Type},
+  maybe_grow,
+};
+use indexmap::IndexSet;
+
+pub const EQUALS_SUFFIX: &str = "/equals";
+
+impl Ctx<'_> {
+  /// Generates the structural equality function `Type/equals` of each ADT
+  /// that has its equality function used, but not defined, by the program.
+  ///
+  /// The generated function receives the two values to compare and returns 1 if they're equal, 0 otherwise.
+  /// Numeric fields are compared with `==` and fields of other ADTs with their own equality function.
+  /// For ADTs with type variables, it also receives first the equality function of each type variable.
+  ///
+  /// Example:
+  /// ```hvm
+  /// type (Pair A) = (Pair (fst: A) (snd: u24))
+  ///
+  /// (Pair/equals %eq_A (Pair/Pair %a0 %a1) (Pair/Pair %b0 %b1)) =
+  ///   switch %cond = (%eq_A %a0 %b0) { 0: 0; _: switch %cond = (== %a1 %b1) { 0: 0; _: 1 } }
+  /// ```
+  #[tracing::instrument(level = "debug", skip_all)]
+  pub fn derive_equality(&mut self) -> Result<(), Diagnostics> {
+    let mut used = IndexSet::new();
+    for def in self.book.defs.values() {
+      for rule in def.rules.iter() {
+        used_equality_fns(&rule.body, &self.book.adts, &mut used);
+      }
+    }
+
+    let mut derived = IndexSet::new();
+    while let Some(adt_name) = used.pop() {
+      let name = Name::new(format!("{adt_name}{EQUALS_SUFFIX}"));
+      if self.book.defs.contains_key(&name) || !derived.insert(adt_name.clone()) {
+        continue;
+      }
+      let adt = &self.book.adts[&adt_name];
+      match equality_rules(adt, &self.book.adts, &mut used) {
+        Ok(rules) => {
+          let def = Definition::new_gen(name.clone(), rules, adt.source.clone(), false);
+          self.book.defs.insert(name, def);
+        }
+        Err(err) => self.info.add_function_error(err, name, adt.source.clone()),
+      }
+    }
+
+    self.info.fatal(())
+  }
+}
+
+/// Collects the ADTs whose equality function is referenced in the term.
+fn used_equality_fns(term: &Term, adts: &Adts, used: &mut IndexSet<Name>) {
+  maybe_grow(|| {
+    if let Term::Var { nam } | Term::Ref { nam } = term {
+      if let Some(adt_name) = nam.strip_suffix(EQUALS_SUFFIX) {
+        let adt_name = Name::new(adt_name);
+        if adts.contains_key(&adt_name) {
+          used.insert(adt_name);
+        }
+      }
+    }
+    for child in term.children() {
+      used_equality_fns(child, adts, used);
+    }
+  })
+}
+
+fn equality_rules(adt: &Adt, adts: &Adts, used: &mut IndexSet<Name>) -> Result<Vec<Rule>, String> {
+  let eq_pats = adt.vars.iter().map(|var| Pattern::Var(Some(eq_var(var)))).collect::<Vec<_>>();
+
+  let mut rules = vec![];
+  for ctr in adt.ctrs.values() {
+    let field = |prefix: &str, idx: usize| Name::new(format!("%{prefix}{idx}"));
+    let fst = (0..ctr.fields.len()).map(|idx| Pattern::Var(Some(field("a", idx)))).collect();
+    let snd = (0..ctr.fields.len()).map(|idx| Pattern::Var(Some(field("b", idx)))).collect();
+
+    let mut body = Term::Num { val: Num::U24(1) };
+    for (idx, ctr_field) in ctr.fields.iter().enumerate().rev() {
+      let (a, b) = (Term::Var { nam: field("a", idx) }, Term::Var { nam: field("b", idx) });
+      let cmp = match &ctr_field.typ {
+        typ if is_numeric(typ) => Term::Oper { opr: Op::EQ, fst: Box::new(a), snd: Box::new(b) },
+        typ => Term::call(
+          eq_fn(typ, &adt.vars, adts, used).map_err(|typ| cant_derive_err(adt, &ctr_field.nam, &typ))?,
+          [a, b],
+        ),
+      };
+      body = Term::Swt(Box::new(SwitchTerm {
+        bnd: Some(Name::new("%cond")),
+        arg: cmp,
+        with_bnd: vec![],
+        with_arg: vec![],
+        pred: Some(Name::new("%cond-1")),
+        arms: vec![Term::Num { val: Num::U24(0) }, body],
+      }));
+    }
+
+    let mut pats = eq_pats.clone();
+    pats.push(Pattern::Ctr(ctr.name.clone(), fst));
+    pats.push(Pattern::Ctr(ctr.name.clone(), snd));
+    rules.push(Rule { pats, body });
+  }
+
+  // Values built with different constructors are never equal.
+  if adt.ctrs.len() > 1 {
+    let mut pats = eq_pats;
+    pats.extend([Pattern::Var(None), Pattern::Var(None)]);
+    rules.push(Rule { pats, body: Term::Num { val: Num::U24(0) } });
+  }
+
+  Ok(rules)
+}
+
+/// The function that compares two values of type `typ`, where `vars` are the type variables in scope.
+/// Returns the type that can't be compared if there's one.
+fn eq_fn(typ: &Type, vars: &[Name], adts: &Adts, used: &mut IndexSet<Name>) -> Result<Term, Type> {
+  match typ {
+    typ if is_numeric(typ) => {
+      let (a, b) = (Name::new("%a"), Name::new("%b"));
+      let cmp = Term::Oper {
+        opr: Op::EQ,
+        fst: Box::new(Term::Var { nam: a.clone() }),
+        snd: Box::new(Term::Var { nam: b.clone() }),
+      };
+      Ok(Term::rfold_lams(cmp, [Some(a), Some(b)].into_iter()))
+    }
+    Type::Var(var) if vars.contains(var) => Ok(Term::Var { nam: eq_var(var) }),
+    // Type constructors without arguments might not be resolved yet.
+    Type::Var(name) if adts.contains_key(name) => eq_fn(&Type::Ctr(name.clone(), vec![]), vars, adts, used),
+    Type::Ctr(name, args) if adts.contains_key(name) => {
+      used.insert(name.clone());
+      let args = args.iter().map(|arg| eq_fn(arg, vars, adts, used)).collect::<Result<Vec<_>, _>>()?;
+      Ok(Term::call(Term::Var { nam: Name::new(format!("{name}{EQUALS_SUFFIX}")) }, args))
+    }
+    typ => Err(typ.clone()),
+  }
+}
+
+fn is_numeric(typ: &Type) -> bool {
+  matches!(typ, Type::U24 | Type::I24 | Type::F24 | Type::Number(_) | Type::Integer(_))
+}
+
+fn eq_var(var: &Name) -> Name {
+  Name::new(format!("%eq_{var}"))
+}
+
+fn cant_derive_err(adt: &Adt, field: &Name, typ: &Type) -> String {
+  format!(
+    "Can't derive the equality of type '{}', the field '{field}' has type '{typ}', which can't be compared. Fields must have a number or datatype type.",
+    adt.name
+  )
+}
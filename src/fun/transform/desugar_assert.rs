@@ -0,0 +1,118 @@
+use crate::{
+  fun::{Book, Ctx, Name, Pattern, Term},
+  maybe_grow, AdtEncoding,
+};
+
+pub const ASSERT_FAILED: &str = "Test/AssertError/failed";
+pub const ASSERT_FAILED_CTR: &str = "Test/AssertError/Failed";
+
+impl Ctx<'_> {
+  /// Converts the applications of the builtin `Test/AssertError/failed`, which the `assert` statements become,
+  /// into values that identify the failed assert, recording the name of the definition where each one is.
+  ///
+  /// The value of the `n`th assert of the book is `λa (a * N)`, where `N` is `n` nested `λb (b ..)` around a `*`.
+  /// It has no numbers in it, so it stays the same when it's an operand of a numeric operator,
+  /// which lets a failed assert reach the result of the program through the expressions that use it.
+  /// The readback turns it back into a `Test/AssertError/Failed`, see [Term::resugar_failed_asserts].
+  ///
+  /// Calls with a location that isn't a string literal become a `Test/AssertError/Failed` directly.
+  ///
+  /// Example:
+  /// ```hvm
+  /// foo = (Test/AssertError/failed "file.bend:2:3")
+  ///
+  /// // Becomes
+  /// foo = λa (a * *)
+  ///
+  /// // And the book records
+  /// asserts = [("foo", "file.bend:2:3")]
+  /// ```
+  #[tracing::instrument(level = "debug", skip_all)]
+  pub fn desugar_assert(&mut self) {
+    if !self.book.defs.get(&Name::new(ASSERT_FAILED)).is_some_and(|def| def.is_builtin()) {
+      return;
+    }
+    let Book { defs, asserts, .. } = &mut *self.book;
+    for def in defs.values_mut() {
+      if def.name == ASSERT_FAILED {
+        continue;
+      }
+      let def_name = def.name.def_name_from_generated();
+      for rule in def.rules.iter_mut() {
+        rule.body.desugar_assert(&def_name, asserts);
+      }
+    }
+  }
+}
+
+impl Term {
+  fn desugar_assert(&mut self, def_name: &Name, asserts: &mut Vec<(Name, String)>) {
+    maybe_grow(|| {
+      if let Term::App { fun, arg, .. } = self {
+        if matches!(fun.as_ref(), Term::Ref { nam } if nam == ASSERT_FAILED) {
+          // The string literals are already encoded at this point.
+          if let Some(loc) = arg.as_encoded_str() {
+            asserts.push((def_name.clone(), loc));
+            *self = Term::failed_assert(asserts.len() - 1);
+          } else {
+            **fun = Term::app(Term::r#ref(ASSERT_FAILED_CTR), Term::encode_str(def_name));
+          }
+        }
+      }
+      for child in self.children_mut() {
+        child.desugar_assert(def_name, asserts);
+      }
+    })
+  }
+
+  /// The value of the failed assert with the given index, see [Ctx::desugar_assert].
+  fn failed_assert(idx: usize) -> Term {
+    let var = |nam: &str| Term::Var { nam: Name::new(nam) };
+    let lam = |nam: &str, bod: Term| Term::lam(Pattern::Var(Some(Name::new(nam))), bod);
+    let idx = (0..idx).fold(Term::Era, |idx, _| lam("%n", Term::app(var("%n"), idx)));
+    lam("%a", Term::call(var("%a"), [Term::Era, idx]))
+  }
+
+  /// Returns the index of a failed assert read back from the result of a program, see [Ctx::desugar_assert].
+  fn as_failed_assert(&self) -> Option<usize> {
+    let Term::Lam { pat, bod, .. } = self else { return None };
+    let Pattern::Var(Some(var)) = pat.as_ref() else { return None };
+    let Term::App { fun, arg: idx, .. } = bod.as_ref() else { return None };
+    let Term::App { fun, arg, .. } = fun.as_ref() else { return None };
+    if !matches!((fun.as_ref(), arg.as_ref()), (Term::Var { nam }, Term::Era) if nam == var) {
+      return None;
+    }
+    let mut idx = idx.as_ref();
+    let mut n = 0;
+    while let Term::Lam { pat, bod, .. } = idx {
+      let Pattern::Var(Some(var)) = pat.as_ref() else { return None };
+      let Term::App { fun, arg, .. } = bod.as_ref() else { return None };
+      if !matches!(fun.as_ref(), Term::Var { nam } if nam == var) {
+        return None;
+      }
+      idx = arg;
+      n += 1;
+    }
+    matches!(idx, Term::Era).then_some(n)
+  }
+
+  /// Converts the failed asserts in the result of a program into `Test/AssertError/Failed` values,
+  /// with the name of the definition and the location of each assert.
+  pub fn resugar_failed_asserts(&mut self, book: &Book, adt_encoding: AdtEncoding) {
+    maybe_grow(|| {
+      if let Some((def_name, loc)) = self.as_failed_assert().and_then(|idx| book.asserts.get(idx)) {
+        let fields = [Term::str(def_name), Term::str(loc)];
+        let var = Term::Var { nam: Name::new("a") };
+        let ctr = match adt_encoding {
+          AdtEncoding::NumScott => Term::app(var, Term::r#ref(&format!("{ASSERT_FAILED_CTR}/tag"))),
+          AdtEncoding::Scott => var,
+        };
+        *self = Term::lam(Pattern::Var(Some(Name::new("a"))), Term::call(ctr, fields));
+        return;
+      }
+      for child in self.children_mut() {
+        child.resugar_failed_asserts(book, adt_encoding);
+      }
+    })
+  }
+}
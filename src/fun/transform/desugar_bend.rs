@@ -1,6 +1,6 @@
 use crate::{
   diagnostics::Diagnostics,
-  fun::{Ctx, Definition, Name, Rule, Source, Term},
+  fun::{BendTerm, Ctx, Definition, Name, Rule, Source, SwitchTerm, Term},
   maybe_grow,
 };
 use indexmap::IndexMap;
@@ -9,6 +9,7 @@ pub const RECURSIVE_KW: &str = "fork";
 const NEW_FN_SEP: &str = "__bend";
 
 impl Ctx<'_> {
+  #[tracing::instrument(level = "debug", skip_all)]
   pub fn desugar_bend(&mut self) -> Result<(), Diagnostics> {
     let mut new_defs = IndexMap::new();
     for def in self.book.defs.values_mut() {
@@ -50,55 +51,65 @@ impl Term {
         if self.has_unscoped_diff() {
           return Err("Can't have non self-contained unscoped variables in a 'bend'".into());
         }
-        let Term::Bend { bnd, arg, cond, step, base } = self else { unreachable!() };
+        let Term::Bend(bend) = self else { unreachable!() };
+        let BendTerm { bnd, arg, cond, step, base } = bend.as_mut();
 
         let new_nam = Name::new(format!("{}{}{}", def_name, NEW_FN_SEP, fresh));
         *fresh += 1;
 
-        // Gather the free variables
-        // They will be implicitly captured by the new function
-        let mut free_vars = step.free_vars();
-        free_vars.shift_remove(&Name::new(RECURSIVE_KW));
-        free_vars.extend(base.free_vars());
-        free_vars.extend(cond.free_vars());
-        for bnd in bnd.iter().flatten() {
-          free_vars.shift_remove(bnd);
-        }
-        let free_vars = free_vars.into_keys().collect::<Vec<_>>();
-
-        // Add a substitution of `fork`, a use term with a partially applied recursive call
-        let step = Term::Use {
-          nam: Some(Name::new(RECURSIVE_KW)),
-          val: Box::new(Term::call(
-            Term::Ref { nam: new_nam.clone() },
-            free_vars.iter().cloned().map(|nam| Term::Var { nam }),
-          )),
-          nxt: Box::new(std::mem::take(step.as_mut())),
-        };
-
         // Create the function body for the bend.
-        let body = Term::Swt {
-          arg: Box::new(std::mem::take(cond)),
+        let body = Term::Swt(Box::new(SwitchTerm {
+          arg: std::mem::take(cond),
           bnd: Some(Name::new("_")),
           with_bnd: vec![],
           with_arg: vec![],
           pred: Some(Name::new("_-1")),
-          arms: vec![std::mem::take(base.as_mut()), step],
-        };
-        let body = Term::rfold_lams(body, std::mem::take(bnd).into_iter());
-        let body = Term::rfold_lams(body, free_vars.iter().cloned().map(Some));
-
-        // Make a definition from the new function
-        let def = Definition::new_gen(new_nam.clone(), vec![Rule { pats: vec![], body }], source, check);
-        new_defs.insert(new_nam.clone(), def);
+          arms: vec![std::mem::take(base), std::mem::take(step)],
+        }));
+        let params = std::mem::take(bnd).into_iter().collect();
+        let rec = Some(Name::new(RECURSIVE_KW));
+        let call = Term::lift_recursive_fn(new_nam, rec, params, body, new_defs, source, check);
 
         // Call the new function in the original term.
-        let call =
-          Term::call(Term::Ref { nam: new_nam }, free_vars.iter().map(|v| Term::Var { nam: v.clone() }));
         *self = Term::call(call, arg.drain(..));
       }
 
       Ok(())
     })
   }
+
+  /// Lifts a recursive function into a new definition called `new_nam`, returning the term that calls it.
+  ///
+  /// The new definition takes the free variables of `body` and then the `params`,
+  /// and the recursive binder `rec` is substituted by the new definition applied to the free variables.
+  pub(super) fn lift_recursive_fn(
+    new_nam: Name,
+    rec: Option<Name>,
+    params: Vec<Option<Name>>,
+    body: Term,
+    new_defs: &mut IndexMap<Name, Definition>,
+    source: Source,
+    check: bool,
+  ) -> Term {
+    // Gather the free variables
+    // They will be implicitly captured by the new function
+    let mut free_vars = body.free_vars();
+    for bnd in rec.iter().chain(params.iter().flatten()) {
+      free_vars.shift_remove(bnd);
+    }
+    let free_vars = free_vars.into_keys().collect::<Vec<_>>();
+    let call =
+      Term::call(Term::Ref { nam: new_nam.clone() }, free_vars.iter().map(|v| Term::Var { nam: v.clone() }));
+
+    // Substitute the recursive binder with a partially applied recursive call
+    let body = Term::Use { nam: rec, val: Box::new(call.clone()), nxt: Box::new(body) };
+    let body = Term::rfold_lams(body, params.into_iter());
+    let body = Term::rfold_lams(body, free_vars.into_iter().map(Some));
+
+    // Make a definition from the new function
+    let def = Definition::new_gen(new_nam.clone(), vec![Rule { pats: vec![], body }], source, check);
+    new_defs.insert(new_nam, def);
+
+    call
+  }
 }
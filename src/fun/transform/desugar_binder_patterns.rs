@@ -0,0 +1,144 @@
+use crate::{
+  diagnostics::Diagnostics,
+  fun::{Adts, Constructors, Ctx, MatchTerm, Name, Pattern, Term},
+  maybe_grow,
+};
+
+impl Ctx<'_> {
+  /// Desugars constructor patterns in lambda, `let` and `ask` binders.
+  ///
+  /// The constructor must be of a type with a single constructor, so that the pattern is irrefutable.
+  /// The binder is converted into a match on its only constructor, binding each field to its sub-pattern.
+  ///
+  /// Example:
+  /// ```hvm
+  /// type Pair = (Pair fst snd)
+  /// λ(Pair a (b, c)) (+ a b)
+  ///
+  /// λ%pat0 match %pat0 { Pair: let a = %pat0.fst; let (b, c) = %pat0.snd; (+ a b) }
+  /// ```
+  #[tracing::instrument(level = "debug", skip_all)]
+  pub fn desugar_binder_patterns(&mut self) -> Result<(), Diagnostics> {
+    for def in self.book.defs.values_mut() {
+      let mut fresh = 0;
+      for rule in def.rules.iter_mut() {
+        if let Err(err) = rule.body.desugar_binder_patterns(&self.book.ctrs, &self.book.adts, &mut fresh) {
+          self.info.add_function_error(err, def.name.clone(), def.source.clone());
+        }
+      }
+    }
+
+    self.info.fatal(())
+  }
+}
+
+impl Term {
+  fn desugar_binder_patterns(
+    &mut self,
+    ctrs: &Constructors,
+    adts: &Adts,
+    fresh: &mut usize,
+  ) -> Result<(), String> {
+    maybe_grow(|| {
+      match self {
+        Term::Lam { pat, bod, .. } | Term::Ask { pat, nxt: bod, .. } => {
+          extract_ctr_patterns(pat, bod, fresh);
+        }
+        Term::Let { pat, val, nxt } => {
+          if let Pattern::Ctr(ctr, pats) = pat.as_mut() {
+            let (ctr, pats) = (std::mem::take(ctr), std::mem::take(pats));
+            *self = ctr_binder_match(
+              ctr,
+              pats,
+              std::mem::take(val.as_mut()),
+              std::mem::take(nxt.as_mut()),
+              ctrs,
+              adts,
+              fresh,
+            )?;
+          } else {
+            extract_ctr_patterns(pat, nxt, fresh);
+          }
+        }
+        _ => {}
+      }
+      match self {
+        Term::Def { def, nxt } => {
+          for rule in def.rules.iter_mut() {
+            rule.body.desugar_binder_patterns(ctrs, adts, fresh)?;
+          }
+          nxt.desugar_binder_patterns(ctrs, adts, fresh)?;
+        }
+        _ => {
+          for child in self.children_mut() {
+            child.desugar_binder_patterns(ctrs, adts, fresh)?;
+          }
+        }
+      }
+      Ok(())
+    })
+  }
+}
+
+/// Replaces the constructor patterns inside a binder pattern with fresh variables,
+/// destructuring them with a `let` at the start of the body instead.
+fn extract_ctr_patterns(pat: &mut Pattern, bod: &mut Term, fresh: &mut usize) {
+  if let Pattern::Ctr(..) = pat {
+    let nam = fresh_name(fresh);
+    let ctr = std::mem::replace(pat, Pattern::Var(Some(nam.clone())));
+    *bod =
+      Term::Let { pat: Box::new(ctr), val: Box::new(Term::Var { nam }), nxt: Box::new(std::mem::take(bod)) };
+  } else {
+    for child in pat.children_mut() {
+      extract_ctr_patterns(child, bod, fresh);
+    }
+  }
+}
+
+fn ctr_binder_match(
+  ctr: Name,
+  pats: Vec<Pattern>,
+  val: Term,
+  nxt: Term,
+  ctrs: &Constructors,
+  adts: &Adts,
+  fresh: &mut usize,
+) -> Result<Term, String> {
+  let Some(adt) = ctrs.get(&ctr).map(|typ| &adts[typ]) else {
+    return Err(format!("Unknown constructor '{ctr}' in binder pattern."));
+  };
+  if adt.ctrs.len() != 1 {
+    return Err(format!(
+      "Constructor pattern '{ctr}' in a binder is not irrefutable. Type '{}' has more than one constructor.",
+      adt.name
+    ));
+  }
+  let fields = &adt.ctrs[&ctr].fields;
+  if fields.len() != pats.len() {
+    return Err(format!(
+      "Constructor pattern '{ctr}' in a binder has {} fields, but the constructor has {}.",
+      pats.len(),
+      fields.len()
+    ));
+  }
+
+  let bnd = fresh_name(fresh);
+  let bod = fields.iter().zip(pats).rfold(nxt, |nxt, (field, pat)| Term::Let {
+    pat: Box::new(pat),
+    val: Box::new(Term::Var { nam: Name::new(format!("{bnd}.{}", field.nam)) }),
+    nxt: Box::new(nxt),
+  });
+  Ok(Term::Mat(Box::new(MatchTerm {
+    arg: val,
+    bnd: Some(bnd),
+    with_bnd: vec![],
+    with_arg: vec![],
+    arms: vec![(Some(ctr), vec![], bod)],
+  })))
+}
+
+fn fresh_name(fresh: &mut usize) -> Name {
+  let nam = Name::new(format!("%pat{fresh}"));
+  *fresh += 1;
+  nam
+}
@@ -0,0 +1,150 @@
+use crate::{
+  diagnostics::{Diagnostics, WarningType},
+  fun::{Ctx, Definition, Name, Pattern, Source, Term},
+  maybe_grow,
+};
+use indexmap::IndexMap;
+
+pub const FIX: &str = "fix";
+const NEW_FN_SEP: &str = "__fix";
+
+impl Ctx<'_> {
+  /// Converts each application of the builtin `fix` to a lambda into a new recursive definition.
+  ///
+  /// The recursive binder of the lambda becomes a reference to the new definition,
+  /// partially applied to the free variables of the lambda,
+  /// so the function is never duplicated by itself like it would be with a Y combinator.
+  ///
+  /// Example:
+  /// ```hvm
+  /// (fix λrec λn switch n { 0: 0; _: (+ x (rec n-1)) })
+  ///
+  /// // Becomes
+  /// (main__fix0 x)
+  /// main__fix0 = λx use rec = (main__fix0 x); λn switch n { 0: 0; _: (+ x (rec n-1)) }
+  /// ```
+  ///
+  /// Also warns about manual fixed point combinators, that apply a lambda to itself to recurse.
+  #[tracing::instrument(level = "debug", skip_all)]
+  pub fn desugar_fix(&mut self) -> Result<(), Diagnostics> {
+    // Only rewrite the builtin, a user definition called `fix` is a normal function.
+    let is_builtin = self.book.defs.get(&Name::new(FIX)).is_some_and(|def| def.is_builtin());
+
+    let mut new_defs = IndexMap::new();
+    for def in self.book.defs.values_mut() {
+      let mut fresh = 0;
+      for rule in def.rules.iter_mut() {
+        if let Some(nam) = rule.body.find_self_application() {
+          let msg = format!(
+            "Variable '{nam}' is applied to itself in a fixed point combinator. Recursion through self-application, like in the Y combinator, duplicates lambdas and doesn't work in Bend. Use 'fix' or a recursive definition instead."
+          );
+          self.info.add_function_warning(
+            msg,
+            WarningType::SelfApplication,
+            def.name.clone(),
+            def.source.clone(),
+          );
+        }
+        // The builtin itself is only a reference for its type, all its uses are rewritten.
+        if is_builtin && def.name != FIX {
+          if let Err(err) =
+            rule.body.desugar_fix(&def.name, &mut fresh, &mut new_defs, def.source.clone(), def.check)
+          {
+            self.info.add_function_error(err, def.name.clone(), def.source.clone());
+            break;
+          }
+        }
+      }
+    }
+
+    self.book.defs.extend(new_defs);
+
+    self.info.fatal(())
+  }
+}
+
+impl Term {
+  fn desugar_fix(
+    &mut self,
+    def_name: &Name,
+    fresh: &mut usize,
+    new_defs: &mut IndexMap<Name, Definition>,
+    source: Source,
+    check: bool,
+  ) -> Result<(), String> {
+    maybe_grow(|| {
+      if let Term::App { fun, arg, .. } = self {
+        if matches!(fun.as_ref(), Term::Ref { nam } if nam == FIX) {
+          let Term::Lam { pat, bod, .. } = arg.as_mut() else {
+            return Err(fix_not_on_lambda_err());
+          };
+          let rec = match pat.as_ref() {
+            Pattern::Var(rec) => rec.clone(),
+            _ => return Err("The recursive binder of a 'fix' lambda must be a variable.".to_string()),
+          };
+          // Can't have unmatched unscoped because this'll be extracted
+          if bod.has_unscoped_diff() {
+            return Err("Can't have non self-contained unscoped variables in a 'fix'".into());
+          }
+
+          let new_nam = Name::new(format!("{}{}{}", def_name, NEW_FN_SEP, fresh));
+          *fresh += 1;
+
+          let mut body = std::mem::take(bod.as_mut());
+          body.desugar_fix(def_name, fresh, new_defs, source.clone(), check)?;
+          let call = Term::lift_recursive_fn(new_nam, rec, vec![], body, new_defs, source, check);
+
+          *self = call;
+          return Ok(());
+        }
+      }
+
+      if matches!(self, Term::Ref { nam } if nam == FIX) {
+        return Err(fix_not_on_lambda_err());
+      }
+
+      for child in self.children_mut() {
+        child.desugar_fix(def_name, fresh, new_defs, source.clone(), check)?;
+      }
+      Ok(())
+    })
+  }
+
+  /// Returns the binder of a lambda that applies its argument to itself,
+  /// when it's applied to another such lambda, like in `(λx (f (x x)) λx (f (x x)))`.
+  fn find_self_application(&self) -> Option<&Name> {
+    maybe_grow(|| {
+      if let Term::App { fun, arg, .. } = self {
+        if let (Some(nam), Some(_)) = (fun.self_applied_binder(), arg.self_applied_binder()) {
+          return Some(nam);
+        }
+      }
+      self.children().find_map(Term::find_self_application)
+    })
+  }
+
+  /// If the term is a lambda `λx` with `(x x)` in its body, returns `x`.
+  fn self_applied_binder(&self) -> Option<&Name> {
+    fn applies_to_itself(term: &Term, nam: &Name) -> bool {
+      maybe_grow(|| match term {
+        Term::App { fun, arg, .. } => {
+          matches!((fun.as_ref(), arg.as_ref()), (Term::Var { nam: fun }, Term::Var { nam: arg }) if fun == nam && arg == nam)
+            || applies_to_itself(fun, nam)
+            || applies_to_itself(arg, nam)
+        }
+        _ => term.children().any(|child| applies_to_itself(child, nam)),
+      })
+    }
+    match self {
+      Term::Lam { pat, bod, .. } => match pat.as_ref() {
+        Pattern::Var(Some(nam)) if applies_to_itself(bod, nam) => Some(nam),
+        _ => None,
+      },
+      _ => None,
+    }
+  }
+}
+
+fn fix_not_on_lambda_err() -> String {
+  "The builtin 'fix' must be applied directly to a lambda, like 'fix(lambda rec: ...)'.".to_string()
+}
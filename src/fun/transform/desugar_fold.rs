@@ -2,7 +2,7 @@ use std::collections::HashSet;
 
 use crate::{
   diagnostics::Diagnostics,
-  fun::{Adts, Constructors, Ctx, Definition, Name, Pattern, Rule, Source, Term},
+  fun::{Adts, Constructors, Ctx, Definition, MatchTerm, Name, Pattern, Rule, Source, Term},
   maybe_grow,
 };
 
@@ -27,6 +27,7 @@ impl Ctx<'_> {
   ///   Type/Ctr2: (Bar bind.fld x1 x2)
   /// }
   /// ```
+  #[tracing::instrument(level = "debug", skip_all)]
   pub fn desugar_fold(&mut self) -> Result<(), Diagnostics> {
     let mut new_defs = vec![];
     for def in self.book.defs.values_mut() {
@@ -77,7 +78,8 @@ impl Term {
         if self.has_unscoped_diff() {
           return Err("Can't have non self-contained unscoped variables in a 'fold'".into());
         }
-        let Term::Fold { bnd: _, arg, with_bnd, with_arg, arms } = self else { unreachable!() };
+        let Term::Fold(fold) = self else { unreachable!() };
+        let MatchTerm { bnd: _, arg, with_bnd, with_arg, arms } = fold.as_mut();
 
         // Gather the free variables
         let mut free_vars = HashSet::new();
@@ -113,13 +115,13 @@ impl Term {
 
         // Create the new function
         let x_nam = Name::new("%x");
-        let body = Term::Mat {
-          arg: Box::new(Term::Var { nam: x_nam.clone() }),
+        let body = Term::Mat(Box::new(MatchTerm {
+          arg: Term::Var { nam: x_nam.clone() },
           bnd: None,
           with_bnd: with_bnd.clone(),
           with_arg: with_bnd.iter().map(|nam| Term::var_or_era(nam.clone())).collect(),
           arms: std::mem::take(arms),
-        };
+        }));
         let body = Term::rfold_lams(body, with_bnd.iter().cloned());
         let body = Term::rfold_lams(body, free_vars.iter().map(|nam| Some(nam.clone())));
         let body = Term::lam(Pattern::Var(Some(x_nam)), body);
@@ -133,7 +135,7 @@ impl Term {
         ctx.new_defs.push(def);
 
         // Call the new function
-        let call = Term::call(Term::Ref { nam: new_nam.clone() }, [std::mem::take(arg.as_mut())]);
+        let call = Term::call(Term::Ref { nam: new_nam.clone() }, [std::mem::take(arg)]);
         let call = Term::call(call, free_vars.iter().cloned().map(|nam| Term::Var { nam }));
         let call = Term::call(call, with_arg.iter().cloned());
         *self = call;
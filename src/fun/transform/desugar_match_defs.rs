@@ -1,60 +1,123 @@
 use crate::{
-  diagnostics::{Diagnostics, WarningType},
-  fun::{builtins, Adts, Constructors, Ctx, Definition, FanKind, Name, Num, Pattern, Rule, Tag, Term},
+  diagnostics::{Diagnostics, TextSpan, WarningType},
+  fun::{
+    builtins, Adts, Constructors, Ctx, Definition, FanKind, MatchTerm, Name, Num, Pattern, Rule, RuleNames,
+    Source, SwitchTerm, Tag, Term,
+  },
   maybe_grow,
 };
 use itertools::Itertools;
-use std::collections::{BTreeSet, HashSet};
+use std::collections::{hash_map::Entry, BTreeSet, HashMap, HashSet};
 
 pub enum DesugarMatchDefErr {
   AdtNotExhaustive { adt: Name, ctr: Name },
   NumMissingDefault,
   TypeMismatch { expected: Type, found: Type, pat: Pattern },
-  RepeatedBind { bind: Name },
+  RepeatedBind { bind: Name, span: Option<TextSpan> },
   UnreachableRule { idx: usize, nam: Name, pats: Vec<Pattern> },
+  DuplicateRule { idx: usize, fst: usize, nam: Name, pats: Vec<Pattern> },
 }
 
 impl Ctx<'_> {
   /// Converts equational-style pattern matching function definitions into trees of match terms.
+  #[tracing::instrument(level = "debug", skip_all)]
   pub fn desugar_match_defs(&mut self) -> Result<(), Diagnostics> {
     for (def_name, def) in self.book.defs.iter_mut() {
       let errs = def.desugar_match_def(&self.book.ctrs, &self.book.adts);
-      for err in errs {
-        match err {
-          DesugarMatchDefErr::AdtNotExhaustive { .. }
-          | DesugarMatchDefErr::NumMissingDefault
-          | DesugarMatchDefErr::TypeMismatch { .. } => {
-            self.info.add_function_error(err, def_name.clone(), def.source.clone())
-          }
-          DesugarMatchDefErr::RepeatedBind { .. } => self.info.add_function_warning(
-            err,
-            WarningType::RepeatedBind,
-            def_name.clone(),
-            def.source.clone(),
-          ),
-          DesugarMatchDefErr::UnreachableRule { .. } => self.info.add_function_warning(
-            err,
-            WarningType::UnreachableMatch,
-            def_name.clone(),
-            def.source.clone(),
-          ),
-        }
+      report_desugar_match_def_errs(&mut self.info, errs, def_name, &def.source);
+    }
+
+    self.info.fatal(())
+  }
+
+  /// Converts pattern matching function definitions into backtracking chains of rules.
+  ///
+  /// Each rule is tested in order, and when it fails to match, the arguments are
+  /// passed on to a generated definition containing the remaining rules.
+  /// Each rule body appears only once, producing smaller nets than the decision tree
+  /// at the cost of re-inspecting the arguments for every rule that is tried.
+  ///
+  /// The diagnostics are the same ones produced by [`Ctx::desugar_match_defs`].
+  #[tracing::instrument(level = "debug", skip_all)]
+  pub fn desugar_match_defs_backtracking(&mut self) -> Result<(), Diagnostics> {
+    let def_names = self.book.defs.keys().cloned().collect::<Vec<_>>();
+    for def_name in def_names {
+      let def = self.book.defs.get_mut(&def_name).unwrap();
+      if def.rules.len() <= 1 || def.arity() == 0 {
+        let errs = def.desugar_match_def(&self.book.ctrs, &self.book.adts);
+        report_desugar_match_def_errs(&mut self.info, errs, &def_name, &def.source);
+        continue;
+      }
+
+      // Check the whole definition at once to get precise diagnostics.
+      let errs = def.clone().desugar_match_def(&self.book.ctrs, &self.book.adts);
+      let has_errs = errs.iter().any(DesugarMatchDefErr::is_error);
+      report_desugar_match_def_errs(&mut self.info, errs, &def_name, &def.source);
+      if has_errs {
+        continue;
       }
+
+      // The diagnostics of each link of the chain were already reported for the whole definition.
+      let mut chain = def.split_backtracking_rules(&self.book.ctrs, &self.book.adts);
+      for def in chain.iter_mut() {
+        def.desugar_match_def(&self.book.ctrs, &self.book.adts);
+      }
+      let mut chain = chain.into_iter();
+      *self.book.defs.get_mut(&def_name).unwrap() = chain.next().unwrap();
+      self.book.defs.extend(chain.map(|def| (def.name.clone(), def)));
     }
 
     self.info.fatal(())
   }
 }
 
+fn report_desugar_match_def_errs(
+  info: &mut Diagnostics,
+  errs: Vec<DesugarMatchDefErr>,
+  def_name: &Name,
+  source: &Source,
+) {
+  for err in errs {
+    match err {
+      DesugarMatchDefErr::AdtNotExhaustive { .. }
+      | DesugarMatchDefErr::NumMissingDefault
+      | DesugarMatchDefErr::TypeMismatch { .. } => {
+        info.add_function_error(err, def_name.clone(), source.clone())
+      }
+      DesugarMatchDefErr::RepeatedBind { .. } => {
+        info.add_function_warning(err, WarningType::RepeatedBind, def_name.clone(), source.clone())
+      }
+      DesugarMatchDefErr::UnreachableRule { .. } | DesugarMatchDefErr::DuplicateRule { .. } => {
+        info.add_function_warning(err, WarningType::UnreachableMatch, def_name.clone(), source.clone())
+      }
+    }
+  }
+}
+
+impl DesugarMatchDefErr {
+  fn is_error(&self) -> bool {
+    match self {
+      DesugarMatchDefErr::AdtNotExhaustive { .. }
+      | DesugarMatchDefErr::NumMissingDefault
+      | DesugarMatchDefErr::TypeMismatch { .. } => true,
+      DesugarMatchDefErr::RepeatedBind { .. }
+      | DesugarMatchDefErr::UnreachableRule { .. }
+      | DesugarMatchDefErr::DuplicateRule { .. } => false,
+    }
+  }
+}
+
 impl Definition {
   pub fn desugar_match_def(&mut self, ctrs: &Constructors, adts: &Adts) -> Vec<DesugarMatchDefErr> {
     let mut errs = vec![];
     for rule in self.rules.iter_mut() {
       desugar_inner_match_defs(&mut rule.body, ctrs, adts, &mut errs);
     }
-    let repeated_bind_errs = fix_repeated_binds(&mut self.rules);
+    let repeated_bind_errs = fix_repeated_binds(&mut self.rules, &self.names);
     errs.extend(repeated_bind_errs);
 
+    let duplicates = find_duplicate_rules(&self.rules);
+
     let args = (0..self.arity()).map(|i| Name::new(format!("%arg{i}"))).collect::<Vec<_>>();
     let rules = std::mem::take(&mut self.rules);
     let idx = (0..rules.len()).collect::<Vec<_>>();
@@ -64,7 +127,15 @@ impl Definition {
         let body = Term::rfold_lams(body, args.into_iter().map(Some));
         self.rules = vec![Rule { pats: vec![], body }];
         for i in idx {
-          if !used.contains(&i) {
+          if let Some(&fst) = duplicates.get(&i) {
+            let e = DesugarMatchDefErr::DuplicateRule {
+              idx: i,
+              fst,
+              nam: self.name.clone(),
+              pats: rules[i].pats.clone(),
+            };
+            errs.push(e);
+          } else if !used.contains(&i) {
             let e = DesugarMatchDefErr::UnreachableRule {
               idx: i,
               nam: self.name.clone(),
@@ -78,6 +149,66 @@ impl Definition {
     }
     errs
   }
+
+  /// Splits the rules of a definition into a chain of definitions with one rule each.
+  ///
+  /// Every definition in the chain has an extra catch-all rule that calls the next one.
+  /// ```hvm
+  /// (Foo (Cons x xs) 0) = A
+  /// (Foo xs n) = B
+  /// // Becomes
+  /// (Foo (Cons x xs) 0) = A
+  /// (Foo %bt0 %bt1) = (Foo__bt1 %bt0 %bt1)
+  /// (Foo__bt1 xs n) = B
+  /// ```
+  /// If the last rule doesn't cover all cases by itself, it gets a catch-all that calls `unreachable`.
+  /// It's only there to complete the patterns and is never reached,
+  /// since the original definition was already checked to be exhaustive.
+  fn split_backtracking_rules(&self, ctrs: &Constructors, adts: &Adts) -> Vec<Definition> {
+    let seg_name = |i: usize| {
+      if i == 0 {
+        self.name.clone()
+      } else {
+        Name::new(format!("{}__bt{}", self.name, i))
+      }
+    };
+    let args = (0..self.arity()).map(|i| Name::new(format!("%bt{i}"))).collect::<Vec<_>>();
+
+    let mut chain = vec![];
+    for (i, rule) in self.rules.iter().enumerate() {
+      let is_last = i == self.rules.len() - 1;
+      let irrefutable = rule.pats.iter().all(|p| p.is_wildcard());
+      let mut rules = vec![rule.clone()];
+      if irrefutable {
+        // The remaining rules are unreachable.
+      } else if is_last {
+        let mut alone = Definition { rules: rules.clone(), ..self.clone() };
+        if alone.desugar_match_def(ctrs, adts).iter().any(DesugarMatchDefErr::is_error) {
+          let pats = vec![Pattern::Var(None); args.len()];
+          rules.push(Rule { pats, body: Term::r#ref(builtins::UNREACHABLE) });
+        }
+      } else {
+        let pats = args.iter().cloned().map(|nam| Pattern::Var(Some(nam))).collect();
+        let body =
+          Term::call(Term::Ref { nam: seg_name(i + 1) }, args.iter().cloned().map(|nam| Term::Var { nam }));
+        rules.push(Rule { pats, body });
+      }
+
+      let def = if i == 0 {
+        Definition { rules, ..self.clone() }
+      } else {
+        let mut def = Definition::new_gen(seg_name(i), rules, self.source.clone(), self.check);
+        def.typ = self.typ.clone();
+        def
+      };
+      chain.push(def);
+
+      if irrefutable {
+        break;
+      }
+    }
+    chain
+  }
 }
 
 fn desugar_inner_match_defs(
@@ -99,6 +230,53 @@ fn desugar_inner_match_defs(
   })
 }
 
+/// Finds the rules that are equal to an earlier rule of the definition, up to the names of their variables.
+/// Returns a map from the index of each duplicate to the index of the first rule it's equal to.
+///
+/// These are usually accidental copies, specially in generated code,
+/// and are unreachable like any other rule covered by the ones before it.
+fn find_duplicate_rules(rules: &[Rule]) -> HashMap<usize, usize> {
+  let mut seen = HashMap::new();
+  let mut duplicates = HashMap::new();
+  if rules.len() < 2 {
+    return duplicates;
+  }
+  for (idx, rule) in rules.iter().enumerate() {
+    if let Some(canonical) = canonical_rule(rule) {
+      match seen.entry(canonical) {
+        Entry::Occupied(fst) => _ = duplicates.insert(idx, *fst.get()),
+        Entry::Vacant(e) => _ = e.insert(idx),
+      }
+    }
+  }
+  duplicates
+}
+
+/// The rule as a term with the variables renamed in the order they're bound,
+/// so that two rules that only differ in the names of their variables give the same term.
+fn canonical_rule(rule: &Rule) -> Option<Term> {
+  // Local definitions are desugared separately and can't be renamed yet.
+  if rule.body.has_local_def() {
+    return None;
+  }
+  let mut term = rule.pats.iter().rfold(rule.body.clone(), |bod, pat| Term::Lam {
+    tag: Tag::Static,
+    pat: Box::new(pat.clone()),
+    bod: Box::new(bod),
+  });
+  term.make_var_names_unique();
+  Some(term)
+}
+
+impl Term {
+  fn has_local_def(&self) -> bool {
+    maybe_grow(|| match self {
+      Term::Def { .. } | Term::Open { .. } => true,
+      _ => self.children().any(Term::has_local_def),
+    })
+  }
+}
+
 /// When a rule has repeated bind, the only one that is actually useful is the last one.
 ///
 /// Example: In `(Foo x x x x) = x`, the function should return the fourth argument.
@@ -109,22 +287,30 @@ fn desugar_inner_match_defs(
 /// // After this transformation, becomes:
 /// (Foo * (Succ *) (Cons a)) = (a a)
 /// ```
-fn fix_repeated_binds(rules: &mut [Rule]) -> Vec<DesugarMatchDefErr> {
+///
+/// The warnings point at the erased binds, found among the binders of the `names` of the rules.
+fn fix_repeated_binds(rules: &mut [Rule], names: &[RuleNames]) -> Vec<DesugarMatchDefErr> {
   let mut errs = vec![];
-  for rule in rules {
-    let mut binds = HashSet::new();
-    rule.pats.iter_mut().flat_map(|p| p.binds_mut()).rev().for_each(|nam| {
-      if binds.contains(nam) {
+  for (rule_idx, rule) in rules.iter_mut().enumerate() {
+    let mut binds = rule.pats.iter_mut().flat_map(|p| p.binds_mut()).collect::<Vec<_>>();
+    let mut seen = HashSet::new();
+    for idx in (0..binds.len()).rev() {
+      if seen.contains(&*binds[idx]) {
         // Repeated bind, not reachable and can be erased.
-        if let Some(nam) = nam {
-          errs.push(DesugarMatchDefErr::RepeatedBind { bind: nam.clone() });
+        if let Some(nam) = binds[idx].clone() {
+          // The patterns are written before the body, so the binds of the patterns come first.
+          let occurrence = binds[..idx].iter().filter(|other| other.as_ref() == Some(&nam)).count();
+          let span = names
+            .get(rule_idx)
+            .and_then(|names| names.binders.iter().filter(|(other, _)| *other == nam).nth(occurrence))
+            .map(|(_, span)| *span);
+          errs.push(DesugarMatchDefErr::RepeatedBind { bind: nam, span });
         }
-        *nam = None;
-        // TODO: Send a repeated bind warning
+        *binds[idx] = None;
       } else {
-        binds.insert(&*nam);
+        seen.insert(binds[idx].clone());
       }
-    });
+    }
   }
   errs
 }
@@ -404,14 +590,14 @@ fn num_rule(
       Term::sub_num(Term::Var { nam: arg.clone() }, Num::U24(nums[i]))
     };
 
-    Term::Swt {
-      arg: Box::new(val),
+    Term::Swt(Box::new(SwitchTerm {
+      arg: val,
       bnd: Some(arg.clone()),
       with_bnd: with_bnd.clone(),
       with_arg: with_arg.clone(),
       pred: Some(pred_var.clone()),
       arms: vec![body, term],
-    }
+    }))
   });
 
   Ok(term)
@@ -557,13 +743,13 @@ fn switch_rule(
   let with_bnd = with.iter().cloned().map(Some).collect::<Vec<_>>();
   let with_arg = with.iter().cloned().map(|nam| Term::Var { nam }).collect::<Vec<_>>();
 
-  let term = Term::Mat {
-    arg: Box::new(Term::Var { nam: arg.clone() }),
+  let term = Term::Mat(Box::new(MatchTerm {
+    arg: Term::Var { nam: arg.clone() },
     bnd: Some(arg.clone()),
     with_bnd,
     with_arg,
     arms: new_arms,
-  };
+  }));
   Ok(term)
 }
 
@@ -649,9 +835,25 @@ impl std::fmt::Display for DesugarMatchDefErr {
       DesugarMatchDefErr::NumMissingDefault => {
         write!(f, "Non-exhaustive pattern matching rule. Default case of number type not covered.")
       }
-      DesugarMatchDefErr::RepeatedBind { bind } => {
+      DesugarMatchDefErr::RepeatedBind { bind, span: None } => {
         write!(f, "Repeated bind in pattern matching rule: '{bind}'.")
       }
+      DesugarMatchDefErr::RepeatedBind { bind, span: Some(span) } => {
+        write!(
+          f,
+          "Repeated bind in pattern matching rule: '{bind}', at line {}, column {}.",
+          span.start.line + 1,
+          span.start.char + 1
+        )
+      }
+      DesugarMatchDefErr::DuplicateRule { idx, fst, nam, pats } => {
+        write!(
+          f,
+          "Pattern matching rule '({}{})' (rule index {idx}) is a duplicate of the rule index {fst}.",
+          nam,
+          pats.iter().map(|p| format!(" {p}")).join("")
+        )
+      }
       DesugarMatchDefErr::UnreachableRule { idx, nam, pats } => {
         write!(
           f,
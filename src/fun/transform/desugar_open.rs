@@ -1,10 +1,11 @@
 use crate::{
   diagnostics::Diagnostics,
-  fun::{Adts, Ctx, Term},
+  fun::{Adts, Ctx, MatchTerm, Term},
   maybe_grow,
 };
 
 impl Ctx<'_> {
+  #[tracing::instrument(level = "debug", skip_all)]
   pub fn desugar_open(&mut self) -> Result<(), Diagnostics> {
     for def in self.book.defs.values_mut() {
       for rule in def.rules.iter_mut() {
@@ -27,13 +28,13 @@ impl Term {
           if let Some(adt) = adts.get(&*typ) {
             if adt.ctrs.len() == 1 {
               let ctr = adt.ctrs.keys().next().unwrap();
-              *self = Term::Mat {
-                arg: Box::new(Term::Var { nam: var.clone() }),
+              *self = Term::Mat(Box::new(MatchTerm {
+                arg: Term::Var { nam: var.clone() },
                 bnd: Some(std::mem::take(var)),
                 with_bnd: vec![],
                 with_arg: vec![],
                 arms: vec![(Some(ctr.clone()), vec![], std::mem::take(bod))],
-              }
+              }))
             } else {
               return Err(format!("Type '{typ}' of an 'open' has more than one constructor"));
             }
@@ -0,0 +1,266 @@
+use crate::{
+  diagnostics::Diagnostics,
+  fun::{
+    builtins::{SCONS, SNIL},
+    Book, Ctx, Name, Num, Op, Pattern, Tag, Term,
+  },
+  maybe_grow,
+};
+use std::collections::HashSet;
+
+pub const QUOTE: &str = "Quote/quote";
+pub const UNQUOTE: &str = "Quote/unquote";
+
+const TERM_VAR: &str = "Quote/Term/Var";
+const TERM_REF: &str = "Quote/Term/Ref";
+const TERM_LAM: &str = "Quote/Term/Lam";
+const TERM_APP: &str = "Quote/Term/App";
+const TERM_NUM: &str = "Quote/Term/Num";
+const TERM_OPR: &str = "Quote/Term/Opr";
+const TERM_ERA: &str = "Quote/Term/Era";
+
+/// The name of an erased lambda binder in a quoted term.
+const ERASED: &str = "*";
+
+const OPS: [Op; 17] = [
+  Op::ADD,
+  Op::SUB,
+  Op::MUL,
+  Op::DIV,
+  Op::REM,
+  Op::EQ,
+  Op::NEQ,
+  Op::LT,
+  Op::GT,
+  Op::AND,
+  Op::OR,
+  Op::XOR,
+  Op::SHL,
+  Op::SHR,
+  Op::POW,
+  Op::LE,
+  Op::GE,
+];
+
+impl Ctx<'_> {
+  /// Converts the applications of the builtin `Quote/quote` into the builtin `Quote/Term` data of their argument,
+  /// and the applications of the builtin `Quote/unquote` to known `Quote/Term` data back into the term it describes.
+  ///
+  /// Both are done at compile time, so `Quote/unquote` only accepts data built out of constructors,
+  /// either directly, with `Quote/quote` or in definitions without arguments that return it.
+  ///
+  /// Example:
+  /// ```hvm
+  /// (Quote/quote λx (+ x 1))
+  ///
+  /// // Becomes
+  /// (Quote/Term/Lam "x" (Quote/Term/Opr "+" (Quote/Term/Var "x") (Quote/Term/Num 1)))
+  /// ```
+  #[tracing::instrument(level = "debug", skip_all)]
+  pub fn desugar_quote(&mut self) -> Result<(), Diagnostics> {
+    // Only rewrite the builtins, the definitions themselves are only references for their types.
+    let is_builtin =
+      |book: &Book, nam: &str| book.defs.get(&Name::new(nam)).is_some_and(|def| def.is_builtin());
+    if !is_builtin(self.book, QUOTE) || !is_builtin(self.book, UNQUOTE) {
+      return Ok(());
+    }
+
+    for def in self.book.defs.values_mut() {
+      if def.name == QUOTE || def.name == UNQUOTE {
+        continue;
+      }
+      for rule in def.rules.iter_mut() {
+        if let Err(err) = rule.body.desugar_quote() {
+          self.info.add_function_error(err, def.name.clone(), def.source.clone());
+          break;
+        }
+      }
+    }
+    self.info.fatal(())?;
+
+    // The spliced data can come from other definitions, which already have their quotes desugared,
+    // so each body is taken out of the book while its unquotes are desugared.
+    for idx in 0..self.book.defs.len() {
+      let def = &self.book.defs[idx];
+      if def.name == QUOTE || def.name == UNQUOTE {
+        continue;
+      }
+      for rule_idx in 0..def.rules.len() {
+        let mut body = std::mem::take(&mut self.book.defs[idx].rules[rule_idx].body);
+        let res = body.desugar_unquote(self.book);
+        let def = &mut self.book.defs[idx];
+        def.rules[rule_idx].body = body;
+        if let Err(err) = res {
+          self.info.add_function_error(err, def.name.clone(), def.source.clone());
+          break;
+        }
+      }
+    }
+
+    self.info.fatal(())
+  }
+}
+
+impl Term {
+  fn desugar_quote(&mut self) -> Result<(), String> {
+    maybe_grow(|| {
+      if let Term::App { fun, arg, .. } = self {
+        if matches!(fun.as_ref(), Term::Ref { nam } if nam == QUOTE) {
+          *self = arg.quote()?;
+          return Ok(());
+        }
+      }
+      if matches!(self, Term::Ref { nam } if nam == QUOTE) {
+        return Err(not_applied_err(QUOTE));
+      }
+      for child in self.children_mut() {
+        child.desugar_quote()?;
+      }
+      Ok(())
+    })
+  }
+
+  fn desugar_unquote(&mut self, book: &Book) -> Result<(), String> {
+    maybe_grow(|| {
+      if let Term::App { fun, arg, .. } = self {
+        if matches!(fun.as_ref(), Term::Ref { nam } if nam == UNQUOTE) {
+          *self = arg.unquote(book, &mut HashSet::new())?;
+          return Ok(());
+        }
+      }
+      if matches!(self, Term::Ref { nam } if nam == UNQUOTE) {
+        return Err(not_applied_err(UNQUOTE));
+      }
+      for child in self.children_mut() {
+        child.desugar_unquote(book)?;
+      }
+      Ok(())
+    })
+  }
+
+  /// The `Term` data that describes this term.
+  fn quote(&self) -> Result<Term, String> {
+    maybe_grow(|| {
+      let ctr = |nam: &str, args: Vec<Term>| Term::call(Term::r#ref(nam), args);
+      match self {
+        Term::Var { nam } => Ok(ctr(TERM_VAR, vec![Term::encode_str(nam)])),
+        Term::Ref { nam } => Ok(ctr(TERM_REF, vec![Term::encode_str(nam)])),
+        Term::Lam { tag: Tag::Static, pat, bod } => match pat.as_ref() {
+          Pattern::Var(nam) => {
+            let nam = nam.as_ref().map_or(ERASED, |nam| nam.as_ref());
+            Ok(ctr(TERM_LAM, vec![Term::encode_str(nam), bod.quote()?]))
+          }
+          _ => Err(cant_quote_err("lambdas with patterns")),
+        },
+        // A let binding of a variable is the same as applying a lambda.
+        Term::Let { pat, val, nxt } if matches!(pat.as_ref(), Pattern::Var(_)) => {
+          Term::app(Term::lam(*pat.clone(), *nxt.clone()), *val.clone()).quote()
+        }
+        Term::App { tag: Tag::Static, fun, arg } => Ok(ctr(TERM_APP, vec![fun.quote()?, arg.quote()?])),
+        Term::Num { val } => Ok(ctr(TERM_NUM, vec![Term::Num { val: *val }])),
+        Term::Oper { opr, fst, snd } => {
+          Ok(ctr(TERM_OPR, vec![Term::encode_str(&opr.to_string()), fst.quote()?, snd.quote()?]))
+        }
+        Term::Era => Ok(Term::r#ref(TERM_ERA)),
+        Term::Lam { .. } | Term::App { .. } => Err(cant_quote_err("tagged lambdas and applications")),
+        _ => Err(cant_quote_err("this kind of term")),
+      }
+    })
+  }
+
+  /// The term described by this `Term` data.
+  /// References to definitions without arguments are followed to find the data.
+  fn unquote(&self, book: &Book, seen: &mut HashSet<Name>) -> Result<Term, String> {
+    maybe_grow(|| {
+      let (nam, args) = self.as_ctr_call().ok_or_else(|| not_data_err(self))?;
+      match (nam.as_ref(), args.as_slice()) {
+        (TERM_VAR, [nam]) => Ok(Term::Var { nam: Name::new(nam.as_str_data()?) }),
+        (TERM_REF, [nam]) => {
+          let nam = Name::new(nam.as_str_data()?);
+          if !book.defs.contains_key(&nam) && !book.hvm_defs.contains_key(&nam) {
+            return Err(format!(
+              "Can't unquote a reference to '{nam}', there's no definition with that name."
+            ));
+          }
+          Ok(Term::Ref { nam })
+        }
+        (TERM_LAM, [nam, bod]) => {
+          let nam = nam.as_str_data()?;
+          let pat = Pattern::Var((nam != ERASED).then(|| Name::new(nam)));
+          Ok(Term::lam(pat, bod.unquote(book, seen)?))
+        }
+        (TERM_APP, [fun, arg]) => Ok(Term::app(fun.unquote(book, seen)?, arg.unquote(book, seen)?)),
+        (TERM_NUM, [Term::Num { val }]) => Ok(Term::Num { val: *val }),
+        (TERM_OPR, [opr, fst, snd]) => {
+          let opr = opr.as_str_data()?;
+          let opr = OPS
+            .into_iter()
+            .find(|op| op.to_string() == opr)
+            .ok_or_else(|| format!("Can't unquote the operation '{opr}', it's not a valid operator."))?;
+          Ok(Term::Oper {
+            opr,
+            fst: Box::new(fst.unquote(book, seen)?),
+            snd: Box::new(snd.unquote(book, seen)?),
+          })
+        }
+        (TERM_ERA, []) => Ok(Term::Era),
+        _ => match self {
+          // A definition that returns the data to unquote.
+          Term::Ref { nam } => match book.defs.get(nam).map(|def| def.rules.as_slice()) {
+            Some([rule]) if rule.pats.is_empty() && seen.insert(nam.clone()) => rule.body.unquote(book, seen),
+            _ => Err(not_data_err(self)),
+          },
+          _ => Err(not_data_err(self)),
+        },
+      }
+    })
+  }
+
+  /// If the term is a reference applied to some arguments, like a constructor call, returns them.
+  fn as_ctr_call(&self) -> Option<(&Name, Vec<&Term>)> {
+    let mut args = vec![];
+    let mut term = self;
+    while let Term::App { tag: Tag::Static, fun, arg } = term {
+      args.push(arg.as_ref());
+      term = fun;
+    }
+    args.reverse();
+    match term {
+      Term::Ref { nam } => Some((nam, args)),
+      _ => None,
+    }
+  }
+
+  /// The text of an encoded string literal.
+  fn as_str_data(&self) -> Result<String, String> {
+    let mut text = String::new();
+    let mut term = self;
+    loop {
+      match term.as_ctr_call() {
+        Some((nam, args)) if nam == SNIL && args.is_empty() => return Ok(text),
+        Some((nam, args)) if nam == SCONS => match args.as_slice() {
+          [Term::Num { val: Num::U24(chr) }, tail] => {
+            text.push(char::from_u32(*chr).ok_or_else(|| not_data_err(self))?);
+            term = tail;
+          }
+          _ => return Err(not_data_err(self)),
+        },
+        _ => return Err(not_data_err(self)),
+      }
+    }
+  }
+}
+
+fn not_applied_err(builtin: &str) -> String {
+  format!("The builtin '{builtin}' must be applied directly to its argument, like '{builtin}(x)'.")
+}
+
+fn cant_quote_err(what: &str) -> String {
+  format!("Can't quote {what}. Only variables, references, lambdas, applications, numbers, operations, erasers and 'let' bindings of variables can be quoted.")
+}
+
+fn not_data_err(term: &Term) -> String {
+  format!(
+    "Can't unquote '{term}', it must be known at compile time, built with the constructors of 'Quote/Term' or with 'Quote/quote'."
+  )
+}
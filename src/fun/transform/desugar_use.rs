@@ -14,6 +14,7 @@ impl Book {
   /// // Transforms to:
   /// (λx x λx x λx x)
   /// ```
+  #[tracing::instrument(level = "debug", skip_all)]
   pub fn desugar_use(&mut self) {
     for def in self.defs.values_mut() {
       for rule in def.rules.iter_mut() {
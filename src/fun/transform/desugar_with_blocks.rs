@@ -7,6 +7,7 @@ use std::collections::HashSet;
 
 impl Ctx<'_> {
   /// Converts `ask` terms inside `with` blocks into calls to a monadic bind operation.
+  #[tracing::instrument(level = "debug", skip_all)]
   pub fn desugar_with_blocks(&mut self) -> Result<(), Diagnostics> {
     let def_names = self.book.defs.keys().cloned().collect::<HashSet<_>>();
 
@@ -5,6 +5,7 @@ use crate::{
 
 impl Book {
   /// Defines a function for each constructor in each ADT in the book.
+  #[tracing::instrument(level = "debug", skip_all)]
   pub fn encode_adts(&mut self, adt_encoding: AdtEncoding) {
     let mut defs = vec![];
 
@@ -28,6 +29,7 @@ impl Book {
           check: true,
           rules,
           source: adt.source.clone(),
+          names: vec![],
         };
         defs.push((ctr_name.clone(), def));
       }
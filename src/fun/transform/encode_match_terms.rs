@@ -1,5 +1,5 @@
 use crate::{
-  fun::{Book, MatchRule, Name, Pattern, Term},
+  fun::{Book, MatchRule, MatchTerm, Name, Pattern, SwitchTerm, Term},
   maybe_grow, AdtEncoding,
 };
 
@@ -10,6 +10,7 @@ impl Book {
   /// ADT matches are encoded based on `adt_encoding`.
   ///
   /// Num matches are encoded as a sequence of native num matches (on 0 and 1+).
+  #[tracing::instrument(level = "debug", skip_all)]
   pub fn encode_matches(&mut self, adt_encoding: AdtEncoding) {
     for def in self.defs.values_mut() {
       for rule in &mut def.rules {
@@ -26,16 +27,18 @@ impl Term {
         child.encode_matches(adt_encoding)
       }
 
-      if let Term::Mat { arg, bnd: _, with_bnd, with_arg, arms } = self {
+      if let Term::Mat(mat) = self {
+        let MatchTerm { arg, bnd: _, with_bnd, with_arg, arms } = mat.as_mut();
         assert!(with_bnd.is_empty());
         assert!(with_arg.is_empty());
-        let arg = std::mem::take(arg.as_mut());
+        let arg = std::mem::take(arg);
         let rules = std::mem::take(arms);
         *self = encode_match(arg, rules, adt_encoding);
-      } else if let Term::Swt { arg, bnd: _, with_bnd, with_arg, pred, arms } = self {
+      } else if let Term::Swt(swt) = self {
+        let SwitchTerm { arg, bnd: _, with_bnd, with_arg, pred, arms } = swt.as_mut();
         assert!(with_bnd.is_empty());
         assert!(with_arg.is_empty());
-        let arg = std::mem::take(arg.as_mut());
+        let arg = std::mem::take(arg);
         let pred = std::mem::take(pred);
         let rules = std::mem::take(arms);
         *self = encode_switch(arg, pred, rules);
@@ -57,14 +60,14 @@ fn encode_match(arg: Term, rules: Vec<MatchRule>, adt_encoding: AdtEncoding) ->
           [arm] => Term::lam(Pattern::Var(None), std::mem::take(arm)),
           [arm, rest @ ..] => Term::lam(
             Pattern::Var(Some(Name::new("%tag"))),
-            Term::Swt {
-              arg: Box::new(Term::Var { nam: Name::new("%tag") }),
+            Term::Swt(Box::new(SwitchTerm {
+              arg: Term::Var { nam: Name::new("%tag") },
               bnd: None,
               with_bnd: vec![],
               with_arg: vec![],
               pred: None,
               arms: vec![std::mem::take(arm), make_switches(rest)],
-            },
+            })),
           ),
         })
       }
@@ -73,14 +76,14 @@ fn encode_match(arg: Term, rules: Vec<MatchRule>, adt_encoding: AdtEncoding) ->
       let term = if arms.len() == 1 {
         // λx (x λtag switch tag {0: Ctr0; _: * })
         let arm = arms.pop().unwrap();
-        let term = Term::Swt {
-          arg: Box::new(Term::Var { nam: Name::new("%tag") }),
+        let term = Term::Swt(Box::new(SwitchTerm {
+          arg: Term::Var { nam: Name::new("%tag") },
           bnd: None,
           with_bnd: vec![],
           with_arg: vec![],
           pred: None,
           arms: vec![arm, Term::Era],
-        };
+        }));
         Term::lam(Pattern::Var(Some(Name::new("%tag"))), term)
       } else {
         // λx (x λtag switch tag {0: Ctr0; _: switch tag-1 { ... } })
@@ -102,23 +105,23 @@ fn encode_switch(arg: Term, pred: Option<Name>, mut rules: Vec<Term>) -> Term {
   nums.iter_mut().enumerate().rfold(last_arm, |term, (i, rule)| {
     let arms = vec![std::mem::take(rule), term];
     if i == 0 {
-      Term::Swt {
-        arg: Box::new(arg.clone()),
+      Term::Swt(Box::new(SwitchTerm {
+        arg: arg.clone(),
         bnd: None,
         with_bnd: vec![],
         with_arg: vec![],
         pred: None,
         arms,
-      }
+      }))
     } else {
-      let swt = Term::Swt {
-        arg: Box::new(Term::Var { nam: match_var.clone() }),
+      let swt = Term::Swt(Box::new(SwitchTerm {
+        arg: Term::Var { nam: match_var.clone() },
         bnd: None,
         with_bnd: vec![],
         with_arg: vec![],
         pred: None,
         arms,
-      };
+      }));
       Term::lam(Pattern::Var(Some(match_var.clone())), swt)
     }
   })
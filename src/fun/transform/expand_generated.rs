@@ -23,6 +23,35 @@ impl Term {
   }
 }
 
+impl Term {
+  /// Dereferences the references to definitions in the term, up to `depth` nested expansions.
+  /// Used after readback, so the result can be shown with concrete terms instead of references.
+  ///
+  /// Constructors and their tags are kept as references, since they're the data the result is made of.
+  pub fn expand_refs(&mut self, book: &Book, depth: usize) {
+    maybe_grow(|| {
+      if depth == 0 {
+        return;
+      }
+      if let Term::Ref { nam } = &*self {
+        let is_data = |nam: &Name| {
+          book.ctrs.contains_key(nam)
+            || nam.strip_suffix("/tag").is_some_and(|ctr| book.ctrs.contains_key(&Name::new(ctr)))
+        };
+        if let Some(def) = book.defs.get(nam).filter(|_| !is_data(nam)) {
+          *self = def.rule().body.clone();
+          self.expand_refs(book, depth - 1);
+          return;
+        }
+      }
+
+      for child in self.children_mut() {
+        child.expand_refs(book, depth);
+      }
+    })
+  }
+}
+
 type DepGraph = HashMap<Name, HashSet<Name>>;
 type Cycles = Vec<Vec<Name>>;
 type RecursiveDefs = BTreeSet<Name>;
@@ -0,0 +1,360 @@
+use crate::{
+  diagnostics::Diagnostics,
+  fun::{Ctx, Definition, FanKind, MatchTerm, Name, Num, Pattern, Rule, SwitchTerm, Tag, Term},
+  maybe_grow,
+};
+use indexmap::IndexMap;
+use std::collections::{HashMap, HashSet};
+
+/// How many macro calls can be nested inside the expansion of another one, when the `macro-depth` limit isn't set.
+pub const DEFAULT_MAX_EXPANSION_DEPTH: usize = 256;
+
+/// A definition marked as `macro`, with the rules whose templates replace its calls.
+///
+/// A call is replaced by the template of the first rule whose patterns match the terms of its arguments.
+struct Macro {
+  arity: usize,
+  rules: Vec<Rule>,
+}
+
+struct Expander {
+  macros: IndexMap<Name, Macro>,
+  /// The names of the definitions and macros, that the free variables of a template refer to.
+  globals: HashSet<Name>,
+  /// How many macro calls can be nested inside the expansion of another one.
+  max_depth: usize,
+}
+
+impl Ctx<'_> {
+  /// Replaces the calls to the definitions marked as `macro` with their body,
+  /// with the arguments of the call substituted for the parameters, then removes the macros from the book.
+  ///
+  /// The expansion is hygienic. The variables bound by a macro are renamed in each expansion,
+  /// so they never capture the variables of the arguments, and the free variables of a macro
+  /// always refer to the definitions that they refer to where the macro is defined.
+  ///
+  /// Example:
+  /// ```hvm
+  /// macro (Unless cond then else) = let x = cond; switch x { 0: then; _: else }
+  /// macro (Swap (a, b)) = (b, a)
+  /// main = λx (Unless x (Swap (1, 2)) 3)
+  ///
+  /// // Becomes
+  /// main = λx let x%m0 = x; switch x%m0 { 0: (2, 1); _: 3 }
+  /// ```
+  #[tracing::instrument(level = "debug", skip_all)]
+  pub fn expand_macros(&mut self, max_depth: Option<usize>) -> Result<(), Diagnostics> {
+    if self.book.macros.is_empty() {
+      return Ok(());
+    }
+
+    let mut macros = IndexMap::new();
+    for nam in std::mem::take(&mut self.book.macros) {
+      let Some(def) = self.book.defs.shift_remove(&nam) else { continue };
+      if self.book.entrypoint.as_ref() == Some(&nam) {
+        self.info.add_function_error("The entrypoint can't be a macro.", nam, def.source);
+        continue;
+      }
+      match Macro::new(def.clone()) {
+        Ok(mac) => {
+          macros.insert(nam, mac);
+        }
+        Err(err) => self.info.add_function_error(err, def.name, def.source),
+      }
+    }
+    self.info.fatal(())?;
+
+    let globals =
+      self.book.defs.keys().chain(self.book.hvm_defs.keys()).chain(macros.keys()).cloned().collect();
+    let max_depth = max_depth.unwrap_or(DEFAULT_MAX_EXPANSION_DEPTH);
+    let expander = Expander { macros, globals, max_depth };
+
+    for def in self.book.defs.values_mut() {
+      let mut fresh = 0;
+      for rule in def.rules.iter_mut() {
+        // The parameters of the definition shadow the macros with the same name.
+        let mut scope =
+          rule.pats.iter().flat_map(|pat| pat.binds()).flatten().map(|bind| (bind.clone(), None)).collect();
+        if let Err(err) = rule.body.expand_macros(&expander, &mut scope, &mut fresh, 0) {
+          self.info.add_function_error(err, def.name.clone(), def.source.clone());
+          break;
+        }
+      }
+    }
+    self.info.fatal(())?;
+
+    // The `use`s of the macros, like the ones added by the imports, have nothing left to refer to.
+    let removed = expander.macros.into_keys().collect();
+    for def in self.book.defs.values_mut() {
+      for rule in def.rules.iter_mut() {
+        rule.body.remove_uses_of(&removed);
+      }
+    }
+
+    Ok(())
+  }
+}
+
+impl Macro {
+  fn new(def: Definition) -> Result<Self, String> {
+    let arity = def.arity();
+    for pat in def.rules.iter().flat_map(|rule| &rule.pats) {
+      if !pat.is_macro_pattern() {
+        return Err(format!(
+          "The pattern '{pat}' can't be used by a macro, its patterns can only be variables, constructors, tuples, numbers, strings and lists."
+        ));
+      }
+    }
+    Ok(Macro { arity, rules: def.rules })
+  }
+
+  /// The first rule whose patterns match the arguments, with the terms that its variables are bound to.
+  fn matching_rule<'a>(&'a self, args: &[Term]) -> Option<(&'a Rule, HashMap<&'a Name, Term>)> {
+    self.rules.iter().find_map(|rule| {
+      let mut binds = HashMap::new();
+      let matches = rule.pats.iter().zip(args).all(|(pat, arg)| pat.match_term(arg, &mut binds));
+      matches.then_some((rule, binds))
+    })
+  }
+}
+
+impl Pattern {
+  fn is_macro_pattern(&self) -> bool {
+    match self {
+      Pattern::Var(_) | Pattern::Ctr(..) | Pattern::Num(_) | Pattern::Str(_) | Pattern::Lst(_) => {
+        self.children().all(Pattern::is_macro_pattern)
+      }
+      Pattern::Fan(FanKind::Tup, ..) => self.children().all(Pattern::is_macro_pattern),
+      Pattern::Fan(FanKind::Dup, ..) | Pattern::Chn(_) => false,
+    }
+  }
+
+  /// Whether the term is written with the shape of the pattern, binding its variables to the parts of the term.
+  fn match_term<'a>(&'a self, term: &Term, binds: &mut HashMap<&'a Name, Term>) -> bool {
+    maybe_grow(|| match (self, term) {
+      (Pattern::Var(None), _) => true,
+      (Pattern::Var(Some(nam)), _) => {
+        binds.insert(nam, term.clone());
+        true
+      }
+      (Pattern::Num(num), Term::Num { val: Num::U24(val) }) => num == val,
+      (Pattern::Str(pat), Term::Str { val }) => pat == val,
+      (Pattern::Fan(FanKind::Tup, _, pats), Term::Fan { fan: FanKind::Tup, els, .. })
+      | (Pattern::Lst(pats), Term::List { els }) => {
+        pats.len() == els.len() && pats.iter().zip(els).all(|(pat, el)| pat.match_term(el, binds))
+      }
+      (Pattern::Ctr(ctr, pats), _) => {
+        let mut args = vec![];
+        let mut head = term;
+        while let Term::App { tag: Tag::Static, fun, arg } = head {
+          args.push(arg.as_ref());
+          head = fun;
+        }
+        let (Term::Var { nam } | Term::Ref { nam }) = head else { return false };
+        nam == ctr
+          && pats.len() == args.len()
+          && pats.iter().zip(args.into_iter().rev()).all(|(pat, arg)| pat.match_term(arg, binds))
+      }
+      _ => false,
+    })
+  }
+}
+
+impl Term {
+  /// Expands the macro calls of the term.
+  /// `scope` has the local variables, with the macro they're an alias of, if they are one.
+  fn expand_macros(
+    &mut self,
+    expander: &Expander,
+    scope: &mut HashMap<Name, Option<Name>>,
+    fresh: &mut usize,
+    depth: usize,
+  ) -> Result<(), String> {
+    maybe_grow(|| {
+      if let Some(nam) = self.called_macro(expander, scope) {
+        if depth >= expander.max_depth {
+          return Err(format!(
+            "Maximum macro expansion depth reached while expanding '{nam}'. Its expansion might be infinite."
+          ));
+        }
+        let mac = &expander.macros[&nam];
+
+        let mut args = vec![];
+        let mut head = std::mem::take(self);
+        while let Term::App { tag: Tag::Static, fun, arg } = &mut head {
+          args.push(std::mem::take(arg.as_mut()));
+          let fun = std::mem::take(fun.as_mut());
+          head = fun;
+        }
+        args.reverse();
+        if args.len() < mac.arity {
+          return Err(format!(
+            "Macro '{nam}' expects {} arguments, but was called with {}.",
+            mac.arity,
+            args.len()
+          ));
+        }
+        let extra = args.split_off(mac.arity);
+
+        let Some((rule, args)) = mac.matching_rule(&args) else {
+          return Err(format!(
+            "The arguments of a call to macro '{nam}' don't match the patterns of any of its rules."
+          ));
+        };
+        let mut body = rule.body.clone();
+        body.instantiate_macro(&args, &mut HashSet::new(), &format!("%m{fresh}"), &expander.globals);
+        *fresh += 1;
+
+        *self = Term::call(body, extra);
+        return self.expand_macros(expander, scope, fresh, depth + 1);
+      }
+
+      match self {
+        // A local alias of a macro, like the ones the imports add.
+        Term::Use { nam: Some(nam), val, nxt } if val.macro_name(expander, scope).is_some() => {
+          let mac = val.macro_name(expander, scope);
+          let old = scope.insert(nam.clone(), mac);
+          let res = nxt.expand_macros(expander, scope, fresh, depth);
+          restore_scope(scope, [(nam.clone(), old)]);
+          res
+        }
+        Term::Def { def, nxt } => {
+          let old = scope.insert(def.name.clone(), None);
+          for rule in def.rules.iter_mut() {
+            let binds = rule.pats.iter().flat_map(|pat| pat.binds()).flatten().cloned().collect::<Vec<_>>();
+            let olds =
+              binds.into_iter().map(|bind| (bind.clone(), scope.insert(bind, None))).collect::<Vec<_>>();
+            let res = rule.body.expand_macros(expander, scope, fresh, depth);
+            restore_scope(scope, olds);
+            res?;
+          }
+          let res = nxt.expand_macros(expander, scope, fresh, depth);
+          restore_scope(scope, [(def.name.clone(), old)]);
+          res
+        }
+        _ => {
+          for (child, binds) in self.children_mut_with_binds() {
+            let olds = binds
+              .flatten()
+              .map(|bind| (bind.clone(), scope.insert(bind.clone(), None)))
+              .collect::<Vec<_>>();
+            let res = child.expand_macros(expander, scope, fresh, depth);
+            restore_scope(scope, olds);
+            res?;
+          }
+          Ok(())
+        }
+      }
+    })
+  }
+
+  /// If the term is a call to a macro, or a macro used without arguments, returns the name of the macro.
+  fn called_macro(&self, expander: &Expander, scope: &HashMap<Name, Option<Name>>) -> Option<Name> {
+    let mut head = self;
+    while let Term::App { tag: Tag::Static, fun, .. } = head {
+      head = fun;
+    }
+    head.macro_name(expander, scope)
+  }
+
+  /// If the term is a variable or reference that refers to a macro, returns the name of the macro.
+  fn macro_name(&self, expander: &Expander, scope: &HashMap<Name, Option<Name>>) -> Option<Name> {
+    match self {
+      Term::Var { nam } => match scope.get(nam) {
+        Some(mac) => mac.clone(),
+        None => expander.macros.contains_key(nam).then(|| nam.clone()),
+      },
+      Term::Ref { nam } => expander.macros.contains_key(nam).then(|| nam.clone()),
+      _ => None,
+    }
+  }
+
+  /// Turns the body of a macro into the term of one of its expansions.
+  ///
+  /// The bound variables get the `suffix` added to their names, the parameters are replaced by the arguments
+  /// and the free variables that refer to definitions become references, so they can't be captured either.
+  fn instantiate_macro(
+    &mut self,
+    args: &HashMap<&Name, Term>,
+    bound: &mut HashSet<Name>,
+    suffix: &str,
+    globals: &HashSet<Name>,
+  ) {
+    maybe_grow(|| {
+      let rename = |nam: &Name| Name::new(format!("{nam}{suffix}"));
+      match self {
+        Term::Var { nam } => {
+          // Also renames the fields of a matched variable, like `x.head`.
+          let renamed = bound.contains(nam).then(|| rename(nam)).or_else(|| {
+            nam.match_indices('.').find_map(|(idx, _)| {
+              let (var, field) = nam.split_at(idx);
+              bound.contains(&Name::new(var)).then(|| Name::new(format!("{var}{suffix}{field}")))
+            })
+          });
+          if let Some(renamed) = renamed {
+            *nam = renamed;
+          } else if let Some(arg) = args.get(nam) {
+            *self = arg.clone();
+          } else if globals.contains(nam) {
+            *self = Term::Ref { nam: nam.clone() };
+          }
+        }
+        Term::Def { def, nxt } => {
+          let mut def_bound = bound.clone();
+          def_bound.insert(def.name.clone());
+          for rule in def.rules.iter_mut() {
+            let mut rule_bound = def_bound.clone();
+            rule_bound.extend(rule.pats.iter().flat_map(|pat| pat.binds()).flatten().cloned());
+            rule.body.instantiate_macro(args, &mut rule_bound, suffix, globals);
+            for bind in rule.pats.iter_mut().flat_map(|pat| pat.binds_mut()).flatten() {
+              *bind = rename(bind);
+            }
+          }
+          nxt.instantiate_macro(args, &mut def_bound, suffix, globals);
+          def.name = rename(&def.name);
+        }
+        _ => {
+          for (child, binds) in self.children_mut_with_binds() {
+            let mut child_bound = bound.clone();
+            child_bound.extend(binds.flatten().cloned());
+            child.instantiate_macro(args, &mut child_bound, suffix, globals);
+          }
+          for bind in self.binders_mut().into_iter().flatten() {
+            *bind = rename(bind);
+          }
+        }
+      }
+    })
+  }
+
+  /// The variables bound by this term, for any of its children.
+  fn binders_mut(&mut self) -> Vec<&mut Option<Name>> {
+    match self {
+      Term::Lam { pat, .. } | Term::Let { pat, .. } | Term::Ask { pat, .. } => pat.binds_mut().collect(),
+      Term::Use { nam, .. } => vec![nam],
+      Term::Mat(mat) | Term::Fold(mat) => {
+        let MatchTerm { bnd, with_bnd, arms, .. } = mat.as_mut();
+        [bnd].into_iter().chain(with_bnd).chain(arms.iter_mut().flat_map(|arm| &mut arm.1)).collect()
+      }
+      Term::Swt(swt) => {
+        let SwitchTerm { bnd, with_bnd, pred, .. } = swt.as_mut();
+        [bnd, pred].into_iter().chain(with_bnd).collect()
+      }
+      Term::Bend(bend) => bend.bnd.iter_mut().collect(),
+      _ => vec![],
+    }
+  }
+}
+
+fn restore_scope(
+  scope: &mut HashMap<Name, Option<Name>>,
+  olds: impl IntoIterator<Item = (Name, Option<Option<Name>>)>,
+) {
+  // In reverse, in case the same variable was bound more than once.
+  for (nam, old) in olds.into_iter().collect::<Vec<_>>().into_iter().rev() {
+    match old {
+      Some(old) => scope.insert(nam, old),
+      None => scope.remove(&nam),
+    };
+  }
+}
@@ -7,6 +7,7 @@ use std::collections::HashMap;
 impl Book {
   /// Expands the main function so that it is not just a reference.
   /// While technically correct, directly returning a reference is never what users want.
+  #[tracing::instrument(level = "debug", skip_all)]
   pub fn expand_main(&mut self) {
     if self.entrypoint.is_none() {
       return;
@@ -7,6 +7,7 @@ impl Ctx<'_> {
   /// Makes every pattern matching definition have correct a left-hand side.
   ///
   /// Does not check exhaustiveness of rules and type mismatches. (Inter-ctr/type proprieties)
+  #[tracing::instrument(level = "debug", skip_all)]
   pub fn fix_match_defs(&mut self) -> Result<(), Diagnostics> {
     for def in self.book.defs.values_mut() {
       let mut errs = vec![];
@@ -46,18 +47,18 @@ impl Rule {
 
 impl Term {
   fn fix_match_defs(&mut self, ctrs: &Constructors, adts: &Adts, errs: &mut Vec<String>) {
-    match self {
-      Term::Def { def, nxt } => {
-        let def_arity = def.arity();
-        for rule in &mut def.rules {
-          rule.fix_match_defs(def_arity, ctrs, adts, errs);
-        }
-        nxt.fix_match_defs(ctrs, adts, errs);
-      }
-      _ => {
-        for children in self.children_mut() {
-          children.fix_match_defs(ctrs, adts, errs);
+    // Uses an explicit stack so that deeply nested terms don't overflow the native stack.
+    let mut to_visit = vec![self];
+    while let Some(term) = to_visit.pop() {
+      match term {
+        Term::Def { def, nxt } => {
+          let def_arity = def.arity();
+          for rule in &mut def.rules {
+            rule.fix_match_defs(def_arity, ctrs, adts, errs);
+          }
+          to_visit.push(nxt);
         }
+        term => to_visit.extend(term.children_mut().rev()),
       }
     }
   }
@@ -1,6 +1,6 @@
 use crate::{
-  diagnostics::{Diagnostics, WarningType, ERR_INDENT_SIZE},
-  fun::{Adts, Constructors, CtrField, Ctx, MatchRule, Name, Num, Term},
+  diagnostics::{Diagnostics, Severity, WarningType, ERR_INDENT_SIZE},
+  fun::{builtins, Adts, Constructors, CtrField, Ctx, MatchRule, MatchTerm, Name, Num, SwitchTerm, Term},
   maybe_grow,
 };
 use std::collections::HashMap;
@@ -18,6 +18,7 @@ impl Ctx<'_> {
   /// * For matches, resolve the constructors and create the name of the field variables.
   /// * For switches, the resolution and name bind is already done during parsing.
   /// * Check for redundant arms and non-exhaustive matches.
+  /// * If non-exhaustive matches are not an error, fill the missing cases with a `MatchError` value.
   /// * Converts the initial bind to an alias on every arm, rebuilding the eliminated constructor
   /// * Since the bind is not needed anywhere else, it's erased from the term.
   ///
@@ -45,16 +46,30 @@ impl Ctx<'_> {
   ///       }
   /// }
   /// ```
+  #[tracing::instrument(level = "debug", skip_all)]
   pub fn fix_match_terms(&mut self) -> Result<(), Diagnostics> {
+    let partial_matches =
+      self.info.config.warning_severity(WarningType::NonExhaustiveMatch) != Severity::Error;
     for def in self.book.defs.values_mut() {
+      // The value of the cases not covered by a match, when they're allowed.
+      let missing = partial_matches.then(|| {
+        let def_name = def.name.def_name_from_generated();
+        Term::call(Term::r#ref(builtins::MATCH_ERROR), [Term::encode_str(&def_name)])
+      });
       for rule in def.rules.iter_mut() {
-        let errs = rule.body.fix_match_terms(&self.book.ctrs, &self.book.adts);
+        let errs = rule.body.fix_match_terms(&self.book.ctrs, &self.book.adts, missing.as_ref());
 
         for err in errs {
           match err {
-            FixMatchErr::AdtMismatch { .. } | FixMatchErr::NonExhaustiveMatch { .. } => {
+            FixMatchErr::AdtMismatch { .. } => {
               self.info.add_function_error(err, def.name.clone(), def.source.clone())
             }
+            FixMatchErr::NonExhaustiveMatch { .. } => self.info.add_function_warning(
+              err,
+              WarningType::NonExhaustiveMatch,
+              def.name.clone(),
+              def.source.clone(),
+            ),
             FixMatchErr::IrrefutableMatch { .. } => self.info.add_function_warning(
               err,
               WarningType::IrrefutableMatch,
@@ -83,28 +98,33 @@ impl Ctx<'_> {
 }
 
 impl Term {
-  fn fix_match_terms(&mut self, ctrs: &Constructors, adts: &Adts) -> Vec<FixMatchErr> {
+  fn fix_match_terms(
+    &mut self,
+    ctrs: &Constructors,
+    adts: &Adts,
+    missing: Option<&Term>,
+  ) -> Vec<FixMatchErr> {
     maybe_grow(|| {
       let mut errs = Vec::new();
 
       for child in self.children_mut() {
-        let mut e = child.fix_match_terms(ctrs, adts);
+        let mut e = child.fix_match_terms(ctrs, adts, missing);
         errs.append(&mut e);
       }
 
       if matches!(self, Term::Mat { .. } | Term::Fold { .. }) {
-        self.fix_match(&mut errs, ctrs, adts);
+        self.fix_match(&mut errs, ctrs, adts, missing);
       }
       match self {
         Term::Def { def, nxt } => {
           for rule in def.rules.iter_mut() {
-            errs.extend(rule.body.fix_match_terms(ctrs, adts));
+            errs.extend(rule.body.fix_match_terms(ctrs, adts, missing));
           }
-          errs.extend(nxt.fix_match_terms(ctrs, adts));
+          errs.extend(nxt.fix_match_terms(ctrs, adts, missing));
         }
         // Add a use term to each arm rebuilding the matched variable
-        Term::Mat { arg: _, bnd, with_bnd: _, with_arg: _, arms }
-        | Term::Fold { bnd, arg: _, with_bnd: _, with_arg: _, arms } => {
+        Term::Mat(mat) | Term::Fold(mat) => {
+          let MatchTerm { arg: _, bnd, with_bnd: _, with_arg: _, arms } = mat.as_mut();
           for (ctr, fields, body) in arms {
             if let Some(ctr) = ctr {
               *body = Term::Use {
@@ -118,7 +138,8 @@ impl Term {
             }
           }
         }
-        Term::Swt { arg: _, bnd, with_bnd: _, with_arg: _, pred, arms } => {
+        Term::Swt(swt) => {
+          let SwitchTerm { arg: _, bnd, with_bnd: _, with_arg: _, pred, arms } = swt.as_mut();
           let n_nums = arms.len() - 1;
           for (i, arm) in arms.iter_mut().enumerate() {
             let orig = if i == n_nums {
@@ -134,7 +155,8 @@ impl Term {
 
       // Remove the bound name
       match self {
-        Term::Mat { bnd, .. } | Term::Swt { bnd, .. } | Term::Fold { bnd, .. } => *bnd = None,
+        Term::Mat(mat) | Term::Fold(mat) => mat.bnd = None,
+        Term::Swt(swt) => swt.bnd = None,
         _ => {}
       }
 
@@ -142,12 +164,15 @@ impl Term {
     })
   }
 
-  fn fix_match(&mut self, errs: &mut Vec<FixMatchErr>, ctrs: &Constructors, adts: &Adts) {
-    let (Term::Mat { bnd, arg, with_bnd, with_arg, arms }
-    | Term::Fold { bnd, arg, with_bnd, with_arg, arms }) = self
-    else {
-      unreachable!()
-    };
+  fn fix_match(
+    &mut self,
+    errs: &mut Vec<FixMatchErr>,
+    ctrs: &Constructors,
+    adts: &Adts,
+    missing: Option<&Term>,
+  ) {
+    let (Term::Mat(mat) | Term::Fold(mat)) = self else { unreachable!() };
+    let MatchTerm { bnd, arg, with_bnd, with_arg, arms } = mat.as_mut();
     let bnd = bnd.clone().unwrap();
 
     // Normalize arms, making one arm for each constructor of the matched adt.
@@ -167,7 +192,7 @@ impl Term {
             body
           } else {
             errs.push(FixMatchErr::NonExhaustiveMatch { typ: adt_nam.clone(), missing: ctr_nam.clone() });
-            Term::Err
+            missing.cloned().unwrap_or(Term::Err)
           };
           new_rules.push((Some(ctr_nam.clone()), fields, body));
         }
@@ -195,7 +220,7 @@ impl Term {
     if let Some(var) = match_var {
       *self = Term::Use {
         nam: Some(bnd.clone()),
-        val: arg,
+        val: Box::new(arg),
         nxt: Box::new(Term::Use {
           nam: Some(var),
           val: Box::new(Term::Var { nam: bnd }),
@@ -211,6 +236,9 @@ impl Term {
 ///
 /// If no rules match a certain constructor, return None in the map,
 /// indicating a non-exhaustive match.
+///
+/// The `unreachable` arms added by a guarded match are not reported as unreachable,
+/// unlike the ones written after a variable arm.
 fn fixed_match_arms<'a>(
   bnd: &Name,
   rules: &mut Vec<MatchRule>,
@@ -221,6 +249,18 @@ fn fixed_match_arms<'a>(
   errs: &mut Vec<FixMatchErr>,
 ) -> HashMap<&'a Name, Option<Term>> {
   let mut bodies = HashMap::<&Name, Option<Term>>::from_iter(adt_ctrs.map(|ctr| (ctr, None)));
+  // The arms added by a guarded match, that are only reached when a guard fails.
+  let generated = rules
+    .iter_mut()
+    .map(|(_, _, body)| {
+      let is_generated = matches!(body, Term::Ref { nam } if nam == builtins::GUARD_UNREACHABLE);
+      if is_generated {
+        *body = Term::r#ref(builtins::UNREACHABLE);
+      }
+      is_generated
+    })
+    .collect::<Vec<_>>();
+
   for rule_idx in 0..rules.len() {
     // If Ctr arm, use the body of this rule for this constructor.
     if let Some(ctr_nam) = &rules[rule_idx].0 {
@@ -257,10 +297,10 @@ fn fixed_match_arms<'a>(
         *body = Some(new_body);
       }
     }
-    if rule_idx != rules.len() - 1 {
+    if !generated[rule_idx + 1..].iter().all(|generated| *generated) {
       errs.push(FixMatchErr::UnreachableMatchArms { var: rules[rule_idx].0.clone() });
-      rules.truncate(rule_idx + 1);
     }
+    rules.truncate(rule_idx + 1);
     break;
   }
 
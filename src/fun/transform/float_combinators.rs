@@ -1,5 +1,5 @@
 use crate::{
-  fun::{Book, Definition, Name, Pattern, Rule, Source, Term},
+  fun::{Book, Definition, MatchTerm, Name, Pattern, Rule, Source, SwitchTerm, Term},
   maybe_grow, multi_iterator,
 };
 use std::collections::{BTreeMap, HashSet};
@@ -30,6 +30,7 @@ impl Book {
   ///
   /// See [`Term::size`] for the measurement of size.
   /// It should more or less correspond to the compiled inet size.
+  #[tracing::instrument(level = "debug", skip_all)]
   pub fn float_combinators(&mut self, max_size: usize) {
     let book = self.clone();
     let mut ctx = FloatCombinatorsCtx::new(&book, max_size);
@@ -218,8 +219,8 @@ impl Term {
     match self {
       Term::Let { pat, .. } => pat.size(),
       Term::Fan { els, .. } => els.len() - 1,
-      Term::Mat { arms, .. } => arms.len(),
-      Term::Swt { arms, .. } => 2 * (arms.len() - 1),
+      Term::Mat(mat) => mat.arms.len(),
+      Term::Swt(swt) => 2 * (swt.arms.len() - 1),
       Term::Lam { .. } => 1,
       Term::App { .. } => 1,
       Term::Oper { .. } => 1,
@@ -264,11 +265,13 @@ impl Term {
           }
         }))
       }
-      Term::Mat { arg, bnd: _, with_bnd: _, with_arg, arms } => FloatIter::Mat(
-        [arg.as_mut()].into_iter().chain(with_arg.iter_mut()).chain(arms.iter_mut().map(|r| &mut r.2)),
-      ),
-      Term::Swt { arg, bnd: _, with_bnd: _, with_arg, pred: _, arms } => {
-        FloatIter::Swt([arg.as_mut()].into_iter().chain(with_arg.iter_mut()).chain(arms.iter_mut()))
+      Term::Mat(mat) => {
+        let MatchTerm { arg, bnd: _, with_bnd: _, with_arg, arms } = mat.as_mut();
+        FloatIter::Mat([arg].into_iter().chain(with_arg.iter_mut()).chain(arms.iter_mut().map(|r| &mut r.2)))
+      }
+      Term::Swt(swt) => {
+        let SwitchTerm { arg, bnd: _, with_bnd: _, with_arg, pred: _, arms } = swt.as_mut();
+        FloatIter::Swt([arg].into_iter().chain(with_arg.iter_mut()).chain(arms.iter_mut()))
       }
       Term::Fan { els, .. } | Term::List { els } => FloatIter::Vec(els),
       Term::Let { val: fst, nxt: snd, .. }
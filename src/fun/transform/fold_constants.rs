@@ -0,0 +1,70 @@
+use crate::{
+  fun::{Book, Num, Op, Term},
+  maybe_grow,
+};
+use hvm::hvm::{Numb, OP_EQ};
+
+impl Book {
+  /// Replaces the numeric operations on two number literals by their result,
+  /// including the ones whose operands become literals when they're folded, like `(* (+ 1 2) 3)`.
+  ///
+  /// The results are computed with the numeric operations of the HVM runtime, so they're the same as at runtime.
+  /// Operations whose result isn't fully specified by HVM are kept, like operations between numbers of different types,
+  /// integer divisions by zero and the float operations that call math functions.
+  ///
+  /// Identities like `(* x 1)` can't be simplified, since without knowing the type of `x` the literal could be reinterpreted as another type of number.
+  #[tracing::instrument(level = "debug", skip_all)]
+  pub fn fold_constants(&mut self) {
+    for def in self.defs.values_mut() {
+      for rule in def.rules.iter_mut() {
+        rule.body.fold_constants();
+      }
+    }
+  }
+}
+
+impl Term {
+  fn fold_constants(&mut self) {
+    maybe_grow(|| {
+      for child in self.children_mut() {
+        child.fold_constants();
+      }
+      if let Term::Oper { opr, fst, snd } = self {
+        if let (Term::Num { val: fst }, Term::Num { val: snd }) = (fst.as_ref(), snd.as_ref()) {
+          if let Some(val) = opr.fold(*fst, *snd) {
+            *self = Term::Num { val };
+          }
+        }
+      }
+    })
+  }
+}
+
+impl Op {
+  /// The result of the operation on two numbers, if it can be computed at compile time.
+  fn fold(self, fst: Num, snd: Num) -> Option<Num> {
+    let foldable = match (self, fst, snd) {
+      (Op::DIV | Op::REM, Num::U24(_), Num::U24(0)) | (Op::DIV | Op::REM, Num::I24(_), Num::I24(0)) => false,
+      (Op::POW, Num::U24(_), Num::U24(_)) => false,
+      (Op::POW | Op::SHL | Op::SHR, Num::I24(_), Num::I24(_)) => false,
+      (Op::POW | Op::AND | Op::OR | Op::XOR | Op::SHL | Op::SHR, Num::F24(_), Num::F24(_)) => false,
+      (_, Num::U24(_), Num::U24(_)) | (_, Num::I24(_), Num::I24(_)) | (_, Num::F24(_), Num::F24(_)) => true,
+      _ => false,
+    };
+    if !foldable {
+      return None;
+    }
+
+    // The same numbers that the compiled net gives to the operation node, see `term_to_net`.
+    let partial = Numb((fst.to_bits() & !0x1F) | self.to_native_tag() as u32);
+    let mut res = Numb::operate(partial, Numb(snd.to_bits()));
+    if let Op::LE | Op::GE = self {
+      res = Numb::operate(Numb(OP_EQ as u32), res);
+    }
+    match Num::from_bits(res.0) {
+      // Results that overflow are truncated when they're read back, so they're truncated here too.
+      Num::U24(val) => Some(Num::U24(val & 0xFFFFFF)),
+      val => Some(val),
+    }
+  }
+}
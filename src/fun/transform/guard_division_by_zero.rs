@@ -0,0 +1,65 @@
+use crate::{
+  fun::{Book, Name, Num, Op, Pattern, SwitchTerm, Term},
+  maybe_grow,
+};
+
+impl Book {
+  /// Makes the divisions and remainders by zero return `0`, instead of stopping the program.
+  ///
+  /// Example:
+  /// ```hvm
+  /// (/ a b)
+  ///
+  /// // Becomes
+  /// let %div = b; (switch %div { 0: λ%div %div; _: λ%div (/ a %div) } %div)
+  /// ```
+  ///
+  /// The operations with a float literal are kept as they are, since they follow the IEEE 754 rules.
+  /// The `switch` has no matched variable, so this must run after fixing the match terms,
+  /// and after type checking, since the `switch` only accepts a `u24`.
+  #[tracing::instrument(level = "debug", skip_all)]
+  pub fn guard_division_by_zero(&mut self) {
+    for def in self.defs.values_mut() {
+      for rule in def.rules.iter_mut() {
+        rule.body.guard_division_by_zero();
+      }
+    }
+  }
+}
+
+impl Term {
+  fn guard_division_by_zero(&mut self) {
+    maybe_grow(|| {
+      for child in self.children_mut() {
+        child.guard_division_by_zero();
+      }
+
+      let Term::Oper { opr: opr @ (Op::DIV | Op::REM), fst, snd } = self else { return };
+      if [fst.as_ref(), snd.as_ref()].iter().any(|term| matches!(term, Term::Num { val: Num::F24(_) })) {
+        return;
+      }
+      let nam = Name::new("%div");
+      let opr = *opr;
+      let fst = std::mem::take(fst);
+      let snd = std::mem::take(snd);
+      let var = || Term::Var { nam: nam.clone() };
+      let lam = |bod| Term::lam(Pattern::Var(Some(nam.clone())), bod);
+      // The divisor is passed to the arms, like a linearized match,
+      // so that the division is not reduced before the arm is chosen.
+      // The zero arm returns the divisor, so the result is a zero of the same type as the operands.
+      let swt = Term::Swt(Box::new(SwitchTerm {
+        arg: var(),
+        bnd: None,
+        with_bnd: vec![],
+        with_arg: vec![],
+        pred: Some(Name::new("%div-1")),
+        arms: vec![lam(var()), lam(Term::Oper { opr, fst, snd: Box::new(var()) })],
+      }));
+      *self = Term::Let {
+        pat: Box::new(Pattern::Var(Some(nam.clone()))),
+        val: snd,
+        nxt: Box::new(Term::app(swt, var())),
+      };
+    })
+  }
+}
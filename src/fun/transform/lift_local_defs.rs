@@ -8,6 +8,7 @@ use crate::{
 };
 
 impl Book {
+  #[tracing::instrument(level = "debug", skip_all)]
   pub fn lift_local_defs(&mut self) {
     let mut defs = IndexMap::new();
     for (name, def) in self.defs.iter_mut() {
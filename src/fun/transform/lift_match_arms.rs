@@ -0,0 +1,154 @@
+use crate::{
+  fun::{Book, Definition, Name, Pattern, Rule, Source, SwitchTerm, Term},
+  maybe_grow,
+};
+use indexmap::IndexMap;
+
+pub const ARM_SEP: &str = "__arm";
+
+/// A match arm that was moved into its own definition by [`Book::lift_match_arms`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LiftedArm {
+  /// The definition where the arm was written.
+  pub def: Name,
+  /// Which arm of which match it is, like `arm 'List/Nil' of match 1`.
+  pub desc: String,
+}
+
+impl Book {
+  /// Moves the arms of the `match` and `switch` terms of each definition into new generated definitions,
+  /// so that taking an arm at runtime shows up as a call to its definition.
+  /// Used to measure which arms a program executes.
+  ///
+  /// Matches are numbered in the order they appear in the definition, after its rules were compiled to matches.
+  /// The arms of the definitions generated from a definition, like the ones of its `bend`s, are counted as its own.
+  /// Only arms without free variables can be moved, so this must run after match linearization.
+  /// The arm of a `switch` that binds the predecessor is left as `(Arm pred)`, which is eta-reduced to a reference.
+  /// Every arm gets an extra erased argument, so that an arm is called as soon as it's taken.
+  #[tracing::instrument(level = "debug", skip_all)]
+  pub fn lift_match_arms(&mut self) {
+    // The generated definitions are attributed to the definition whose code they were generated from.
+    let parents = self
+      .defs
+      .values()
+      .map(|def| self.generated_from(def).map_or(&def.name, |parent| &parent.name).clone())
+      .collect::<Vec<_>>();
+    let mut lifted = IndexMap::new();
+    let mut n_matches = IndexMap::new();
+    for ((def_name, def), parent) in self.defs.iter_mut().zip(parents) {
+      if def.is_builtin() {
+        continue;
+      }
+      let mut ctx = LiftArmsCtx {
+        def: def_name,
+        parent: &parent,
+        source: &def.source,
+        check: def.check,
+        n_matches: n_matches.entry(parent.clone()).or_insert(0),
+        lifted: &mut lifted,
+      };
+      for rule in def.rules.iter_mut() {
+        rule.body.lift_match_arms(&mut ctx);
+      }
+    }
+
+    for (nam, (arm, def)) in lifted {
+      self.defs.insert(nam.clone(), def);
+      self.lifted_arms.insert(nam, arm);
+    }
+  }
+}
+
+struct LiftArmsCtx<'a> {
+  def: &'a Name,
+  /// The user definition that the arms are attributed to.
+  parent: &'a Name,
+  source: &'a Source,
+  check: bool,
+  n_matches: &'a mut usize,
+  lifted: &'a mut IndexMap<Name, (LiftedArm, Definition)>,
+}
+
+impl LiftArmsCtx<'_> {
+  /// Creates the definition of an arm with the given body, returning a reference to it.
+  fn lift(&mut self, desc: String, body: Term) -> Term {
+    let nam = Name::new(format!("{}{ARM_SEP}{}", self.def, self.lifted.len()));
+    let def =
+      Definition::new_gen(nam.clone(), vec![Rule { pats: vec![], body }], self.source.clone(), self.check);
+    self.lifted.insert(nam.clone(), (LiftedArm { def: self.parent.clone(), desc }, def));
+    Term::Ref { nam }
+  }
+}
+
+impl Term {
+  fn lift_match_arms(&mut self, ctx: &mut LiftArmsCtx) {
+    maybe_grow(|| {
+      let n_match = if matches!(self, Term::Mat { .. } | Term::Swt { .. }) {
+        *ctx.n_matches += 1;
+        *ctx.n_matches
+      } else {
+        0
+      };
+
+      // Nested matches are lifted first, getting their own definitions.
+      for child in self.children_mut() {
+        child.lift_match_arms(ctx);
+      }
+
+      // Each arm takes an extra erased argument that the match is applied to,
+      // so that the taken arm is always called, even when its result is never used.
+      let dummy = || Pattern::Var(None);
+      match self {
+        Term::Mat(mat) => {
+          for (ctr, fields, body) in mat.arms.iter_mut() {
+            if !is_closed(body, fields) {
+              *body = Term::lam(dummy(), std::mem::take(body));
+              continue;
+            }
+            let ctr = ctr.as_ref().map_or("_", |ctr| ctr.as_ref());
+            let desc = format!("arm '{ctr}' of match {n_match}");
+            // The lifted definition binds the fields, which the matched value applies to the arm.
+            let fields = std::mem::take(fields);
+            let arm = Term::rfold_lams(Term::lam(dummy(), std::mem::take(body)), fields.into_iter());
+            *body = ctx.lift(desc, arm);
+          }
+        }
+        Term::Swt(swt) => {
+          let SwitchTerm { pred, arms, .. } = swt.as_mut();
+          let n_arms = arms.len();
+          for (i, body) in arms.iter_mut().enumerate() {
+            let is_succ = i + 1 == n_arms;
+            let binds = if is_succ { std::slice::from_ref(pred) } else { &[] };
+            if !is_closed(body, binds) {
+              *body = Term::lam(dummy(), std::mem::take(body));
+              continue;
+            }
+            let desc = if is_succ {
+              format!("arm '_' of switch {n_match}")
+            } else {
+              format!("arm '{i}' of switch {n_match}")
+            };
+            let arm = Term::lam(dummy(), std::mem::take(body));
+            if let (true, Some(pred)) = (is_succ, pred.as_ref()) {
+              let arm = Term::lam(Pattern::Var(Some(pred.clone())), arm);
+              *body = Term::app(ctx.lift(desc, arm), Term::Var { nam: pred.clone() });
+            } else {
+              *body = ctx.lift(desc, arm);
+            }
+          }
+        }
+        _ => return,
+      }
+      *self = Term::app(std::mem::take(self), Term::Era);
+    })
+  }
+}
+
+/// Whether the arm only uses the variables it binds, and no unscoped variables.
+fn is_closed(body: &Term, binds: &[Option<Name>]) -> bool {
+  let mut free_vars = body.free_vars();
+  for bind in binds.iter().flatten() {
+    free_vars.shift_remove(bind);
+  }
+  free_vars.is_empty() && !body.has_unscoped()
+}
@@ -1,5 +1,5 @@
 use crate::{
-  fun::{Book, Name, Pattern, Term},
+  fun::{Book, MatchTerm, Name, Pattern, SwitchTerm, Term},
   maybe_grow,
 };
 use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
@@ -23,6 +23,7 @@ impl Book {
   ///   _: @b @c let d = (b c); (B a-1 b c d)
   /// }
   /// ```
+  #[tracing::instrument(level = "debug", skip_all)]
   pub fn linearize_match_binds(&mut self) {
     for def in self.defs.values_mut() {
       for rule in def.rules.iter_mut() {
@@ -82,12 +83,14 @@ impl Term {
 
   fn linearize_binds_single_match(&mut self, mut bind_terms: Vec<Term>) {
     let (used_vars, with_bnd, with_arg, arms) = match self {
-      Term::Mat { arg, bnd: _, with_bnd, with_arg, arms } => {
+      Term::Mat(mat) => {
+        let MatchTerm { arg, bnd: _, with_bnd, with_arg, arms } = mat.as_mut();
         let vars = arg.free_vars().into_keys().collect::<HashSet<_>>();
         let arms = arms.iter_mut().map(|arm| &mut arm.2).collect::<Vec<_>>();
         (vars, with_bnd, with_arg, arms)
       }
-      Term::Swt { arg, bnd: _, with_bnd, with_arg, pred: _, arms } => {
+      Term::Swt(swt) => {
+        let SwitchTerm { arg, bnd: _, with_bnd, with_arg, pred: _, arms } = swt.as_mut();
         let vars = arg.free_vars().into_keys().collect::<HashSet<_>>();
         let arms = arms.iter_mut().collect();
         (vars, with_bnd, with_arg, arms)
@@ -360,6 +363,7 @@ fn update_with_clause(
 
 impl Book {
   /// Linearizes all variables used in a matches' arms.
+  #[tracing::instrument(level = "debug", skip_all)]
   pub fn linearize_matches(&mut self) {
     for def in self.defs.values_mut() {
       for rule in def.rules.iter_mut() {
@@ -393,12 +397,14 @@ impl Term {
 pub fn lift_match_vars(match_term: &mut Term) -> &mut Term {
   // Collect match arms with binds
   let (with_bnd, with_arg, arms) = match match_term {
-    Term::Mat { arg: _, bnd: _, with_bnd, with_arg, arms: rules } => {
+    Term::Mat(mat) => {
+      let MatchTerm { arg: _, bnd: _, with_bnd, with_arg, arms: rules } = mat.as_mut();
       let args =
         rules.iter().map(|(_, binds, body)| (binds.iter().flatten().cloned().collect(), body)).collect();
       (with_bnd.clone(), with_arg.clone(), args)
     }
-    Term::Swt { arg: _, bnd: _, with_bnd, with_arg, pred, arms } => {
+    Term::Swt(swt) => {
+      let SwitchTerm { arg: _, bnd: _, with_bnd, with_arg, pred, arms } = swt.as_mut();
       let (succ, nums) = arms.split_last_mut().unwrap();
       let mut arms = nums.iter().map(|body| (vec![], body)).collect::<Vec<_>>();
       arms.push((vec![pred.clone().unwrap()], succ));
@@ -423,14 +429,16 @@ pub fn lift_match_vars(match_term: &mut Term) -> &mut Term {
 
   // Add lambdas to the arms
   match match_term {
-    Term::Mat { arg: _, bnd: _, with_bnd, with_arg, arms } => {
+    Term::Mat(mat) => {
+      let MatchTerm { arg: _, bnd: _, with_bnd, with_arg, arms } = mat.as_mut();
       update_with_clause(with_bnd, with_arg, &vars_to_lift);
       for arm in arms {
         let old_body = std::mem::take(&mut arm.2);
         arm.2 = Term::rfold_lams(old_body, vars_to_lift.iter().cloned().map(Some));
       }
     }
-    Term::Swt { arg: _, bnd: _, with_bnd, with_arg, pred: _, arms } => {
+    Term::Swt(swt) => {
+      let SwitchTerm { arg: _, bnd: _, with_bnd, with_arg, pred: _, arms } = swt.as_mut();
       update_with_clause(with_bnd, with_arg, &vars_to_lift);
       for arm in arms {
         let old_body = std::mem::take(arm);
@@ -474,6 +482,7 @@ fn get_match_reference(mut match_term: &mut Term) -> &mut Term {
 
 impl Book {
   /// Linearizes all variables specified in the `with` clauses of match terms.
+  #[tracing::instrument(level = "debug", skip_all)]
   pub fn linearize_match_with(&mut self) {
     for def in self.defs.values_mut() {
       for rule in def.rules.iter_mut() {
@@ -491,7 +500,8 @@ impl Term {
       }
     });
     match self {
-      Term::Mat { arg: _, bnd: _, with_bnd, with_arg, arms } => {
+      Term::Mat(mat) => {
+        let MatchTerm { arg: _, bnd: _, with_bnd, with_arg, arms } = mat.as_mut();
         for rule in arms {
           rule.2 = Term::rfold_lams(std::mem::take(&mut rule.2), with_bnd.clone().into_iter());
         }
@@ -499,7 +509,8 @@ impl Term {
         let call_args = std::mem::take(with_arg).into_iter();
         *self = Term::call(std::mem::take(self), call_args);
       }
-      Term::Swt { arg: _, bnd: _, with_bnd, with_arg, pred: _, arms } => {
+      Term::Swt(swt) => {
+        let SwitchTerm { arg: _, bnd: _, with_bnd, with_arg, pred: _, arms } = swt.as_mut();
         for rule in arms {
           *rule = Term::rfold_lams(std::mem::take(rule), with_bnd.clone().into_iter());
         }
@@ -1,5 +1,5 @@
 use crate::{
-  fun::{Book, FanKind, Name, Pattern, Tag, Term},
+  fun::{Book, FanKind, Name, Pattern, SwitchTerm, Tag, Term},
   maybe_grow, multi_iterator,
 };
 use std::collections::HashMap;
@@ -17,6 +17,7 @@ use std::collections::HashMap;
 ///   If they're use more times: add dups for all the uses, put the let body at the root dup.
 /// Precondition: All variables are bound and have unique names within each definition.
 impl Book {
+  #[tracing::instrument(level = "debug", skip_all)]
   pub fn linearize_vars(&mut self) {
     for def in self.defs.values_mut() {
       def.rule_mut().body.linearize_vars();
@@ -121,15 +122,14 @@ impl Term {
     multi_iterator!(ChildrenIter { Zero, One, Two, Vec, Swt });
     multi_iterator!(BindsIter { Zero, One, Pat });
     match self {
-      Term::Swt { arg, bnd, with_bnd, with_arg, pred, arms } => {
+      Term::Swt(swt) => {
+        let SwitchTerm { arg, bnd, with_bnd, with_arg, pred, arms } = swt.as_mut();
         debug_assert!(bnd.is_none());
         debug_assert!(with_bnd.is_empty());
         debug_assert!(with_arg.is_empty());
         debug_assert!(pred.is_none());
         ChildrenIter::Swt(
-          [(arg.as_mut(), BindsIter::Zero([]))]
-            .into_iter()
-            .chain(arms.iter_mut().map(|x| (x, BindsIter::Zero([])))),
+          [(arg, BindsIter::Zero([]))].into_iter().chain(arms.iter_mut().map(|x| (x, BindsIter::Zero([])))),
         )
       }
       Term::Fan { els, .. } | Term::List { els } => {
@@ -1,22 +1,34 @@
 pub mod apply_args;
+pub mod collapse_redexes;
 pub mod definition_merge;
 pub mod definition_pruning;
+pub mod derive_equality;
+pub mod desugar_assert;
 pub mod desugar_bend;
+pub mod desugar_binder_patterns;
+pub mod desugar_fix;
 pub mod desugar_fold;
 pub mod desugar_match_defs;
 pub mod desugar_open;
+pub mod desugar_quote;
 pub mod desugar_use;
 pub mod desugar_with_blocks;
 pub mod encode_adts;
 pub mod encode_match_terms;
 pub mod expand_generated;
+pub mod expand_macros;
 pub mod expand_main;
 pub mod fix_match_defs;
 pub mod fix_match_terms;
 pub mod float_combinators;
+pub mod fold_constants;
+pub mod guard_division_by_zero;
 pub mod lift_local_defs;
+pub mod lift_match_arms;
 pub mod linearize_matches;
 pub mod linearize_vars;
+pub mod prune_imports;
+pub mod resolve_aliases;
 pub mod resolve_refs;
 pub mod resolve_type_ctrs;
 pub mod resugar_list;
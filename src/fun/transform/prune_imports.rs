@@ -0,0 +1,150 @@
+use crate::{
+  fun::{Book, Name, SourceKind, Term},
+  maybe_grow,
+};
+use hvm::ast::{Net, Tree};
+use std::collections::HashSet;
+
+impl Book {
+  /// Removes the imported definitions that can't be reached from the local definitions of the program,
+  /// so that the functions of a big library that a program doesn't use are never compiled.
+  /// This runs after all the imports are loaded and parsed, it doesn't make loading them lazy.
+  ///
+  /// Must run before the references are resolved, so any variable with the name of a definition
+  /// is considered a use of it, and before the constructors are encoded into definitions.
+  /// The `args` passed to the entrypoint also count as uses.
+  #[tracing::instrument(level = "debug", skip_all)]
+  pub fn prune_imports(&mut self, args: Option<&[Term]>) {
+    let mut used = HashSet::new();
+    let mut to_visit = vec![];
+
+    let is_imported = |kind: &SourceKind| matches!(kind, SourceKind::Imported);
+    let roots = self.defs.values().filter(|def| !is_imported(&def.source.kind)).map(|def| &def.name);
+    let hvm_roots = self.hvm_defs.values().filter(|def| !is_imported(&def.source.kind)).map(|def| &def.name);
+    for name in roots.chain(hvm_roots).chain(&self.entrypoint) {
+      if used.insert(name.clone()) {
+        to_visit.push(name.clone());
+      }
+    }
+    for arg in args.into_iter().flatten() {
+      self.find_used_names(arg, &mut used, &mut to_visit);
+    }
+
+    while let Some(name) = to_visit.pop() {
+      if let Some(def) = self.defs.get(&name) {
+        for rule in &def.rules {
+          self.find_used_names(&rule.body, &mut used, &mut to_visit);
+        }
+      } else if let Some(def) = self.hvm_defs.get(&name) {
+        self.find_used_names_in_net(&def.body, &mut used, &mut to_visit);
+      }
+    }
+
+    let keep = |name: &Name, kind: &SourceKind| !is_imported(kind) || used.contains(name);
+    let mut removed = HashSet::new();
+    for (name, kind) in self.defs.values().map(|def| (&def.name, &def.source.kind)) {
+      if !keep(name, kind) {
+        removed.insert(name.clone());
+      }
+    }
+    for (name, kind) in self.hvm_defs.values().map(|def| (&def.name, &def.source.kind)) {
+      if !keep(name, kind) {
+        removed.insert(name.clone());
+      }
+    }
+    self.defs.retain(|name, _| !removed.contains(name));
+    self.hvm_defs.retain(|name, _| !removed.contains(name));
+
+    // The `use`s that bind the removed definitions are unused, otherwise they wouldn't be removed.
+    for def in self.defs.values_mut() {
+      for rule in def.rules.iter_mut() {
+        rule.body.remove_uses_of(&removed);
+      }
+    }
+  }
+
+  fn find_used_names(&self, term: &Term, used: &mut HashSet<Name>, to_visit: &mut Vec<Name>) {
+    for name in term.used_names() {
+      if self.is_def_name(&name) && used.insert(name.clone()) {
+        to_visit.push(name);
+      }
+    }
+  }
+
+  fn find_used_names_in_net(&self, net: &Net, used: &mut HashSet<Name>, to_visit: &mut Vec<Name>) {
+    let mut to_find =
+      [&net.root].into_iter().chain(net.rbag.iter().flat_map(|(_, lft, rgt)| [lft, rgt])).collect::<Vec<_>>();
+
+    while let Some(tree) = to_find.pop() {
+      match tree {
+        Tree::Ref { nam } => {
+          let name = Name::new(nam);
+          if self.is_def_name(&name) && used.insert(name.clone()) {
+            to_visit.push(name);
+          }
+        }
+        Tree::Con { fst, snd } | Tree::Dup { fst, snd } | Tree::Opr { fst, snd } | Tree::Swi { fst, snd } => {
+          to_find.push(fst);
+          to_find.push(snd);
+        }
+        Tree::Era | Tree::Var { .. } | Tree::Num { .. } => {}
+      }
+    }
+  }
+
+  fn is_def_name(&self, name: &Name) -> bool {
+    self.defs.contains_key(name) || self.hvm_defs.contains_key(name)
+  }
+}
+
+impl Term {
+  /// The names of the free variables and references of the term that are actually used.
+  ///
+  /// The imports bind every name of a file with a chain of `use`s in each of its definitions,
+  /// so the value of a `use` only counts if its variable is used.
+  fn used_names(&self) -> HashSet<Name> {
+    maybe_grow(|| match self {
+      Term::Var { nam } | Term::Ref { nam } => HashSet::from([nam.clone()]),
+      Term::Use { nam, val, nxt } => {
+        let mut names = nxt.used_names();
+        if nam.as_ref().map_or(true, |nam| names.remove(nam)) {
+          names.extend(val.used_names());
+        }
+        names
+      }
+      _ => {
+        let mut names = HashSet::new();
+        // The monadic operations are referenced only by name, when the `with` block is desugared.
+        if let Term::With { typ, .. } = self {
+          names.insert(Name::new(format!("{typ}/bind")));
+          names.insert(Name::new(format!("{typ}/wrap")));
+        }
+        for (child, binds) in self.children_with_binds() {
+          let mut child_names = child.used_names();
+          for nam in binds.flatten() {
+            child_names.remove(nam);
+          }
+          names.extend(child_names);
+        }
+        names
+      }
+    })
+  }
+
+  /// Removes the `use`s whose value is a variable or reference with one of the `removed` names.
+  pub fn remove_uses_of(&mut self, removed: &HashSet<Name>) {
+    maybe_grow(|| {
+      while let Term::Use { val, nxt, .. } = self {
+        match val.as_ref() {
+          Term::Var { nam } | Term::Ref { nam } if removed.contains(nam) => {
+            *self = std::mem::take(nxt.as_mut())
+          }
+          _ => break,
+        }
+      }
+      for child in self.children_mut() {
+        child.remove_uses_of(removed);
+      }
+    })
+  }
+}
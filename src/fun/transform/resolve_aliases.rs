@@ -0,0 +1,50 @@
+use crate::fun::{Book, Name, Term};
+use std::collections::{BTreeMap, BTreeSet};
+
+impl Book {
+  /// Replaces the references to alias definitions with references to the definition they're an alias of.
+  ///
+  /// An alias is a definition whose whole body is a reference to another definition, like `Foo = Bar`.
+  /// This way, using an alias has no runtime cost, since there's no extra reference to expand.
+  ///
+  /// The alias definitions are kept, only the references to them are changed.
+  #[tracing::instrument(level = "debug", skip_all)]
+  pub fn resolve_aliases(&mut self) {
+    let aliases = self
+      .defs
+      .values()
+      .filter_map(|def| match def.rules.as_slice() {
+        [rule] if rule.pats.is_empty() => match &rule.body {
+          Term::Ref { nam } if nam != &def.name && self.defs.contains_key(nam) => {
+            Some((def.name.clone(), nam.clone()))
+          }
+          _ => None,
+        },
+        _ => None,
+      })
+      .collect::<BTreeMap<_, _>>();
+
+    // Follow chains of aliases until the original definition.
+    let targets = aliases
+      .keys()
+      .filter_map(|alias| {
+        let mut visited = BTreeSet::from([alias]);
+        let mut target = &aliases[alias];
+        while let Some(next) = aliases.get(target) {
+          if !visited.insert(target) {
+            // A cycle of aliases, not possible to resolve.
+            return None;
+          }
+          target = next;
+        }
+        Some((alias.clone(), target.clone()))
+      })
+      .collect::<BTreeMap<Name, Name>>();
+
+    for def in self.defs.values_mut() {
+      for rule in def.rules.iter_mut() {
+        Term::subst_ref_to_ref(&mut rule.body, &targets);
+      }
+    }
+  }
+}
@@ -17,6 +17,7 @@ impl Ctx<'_> {
   /// Precondition: Refs are encoded as vars, Constructors are resolved.
   ///
   /// Postcondition: Refs are encoded as refs, with the correct def id.
+  #[tracing::instrument(level = "debug", skip_all)]
   pub fn resolve_refs(&mut self) -> Result<(), Diagnostics> {
     let def_names =
       self.book.defs.keys().cloned().chain(self.book.hvm_defs.keys().cloned()).collect::<HashSet<_>>();
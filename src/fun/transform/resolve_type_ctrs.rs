@@ -6,6 +6,7 @@ use crate::{
 
 impl Ctx<'_> {
   /// Resolves type constructors in the book.
+  #[tracing::instrument(level = "debug", skip_all)]
   pub fn resolve_type_ctrs(&mut self) -> Result<(), Diagnostics> {
     for def in self.book.defs.values_mut() {
       let res = def.typ.resolve_type_ctrs(&self.book.adts);
@@ -45,6 +45,14 @@ impl Term {
   }
 
   /// Attempts to resugar a cons term using the common extraction method.
+  /// Returns the string of a chain of `String/Cons` ending with `String/Nil`, like an encoded string literal.
+  pub fn as_encoded_str(&self) -> Option<String> {
+    match self {
+      Term::Ref { nam } if nam == builtins::SNIL => Some(String::new()),
+      _ => self.try_resugar_strings_cons_common(),
+    }
+  }
+
   fn try_resugar_strings_cons_common(&self) -> Option<String> {
     if let Term::App { tag: Tag::Static, fun, arg: tail } = self {
       if let Term::App { tag: Tag::Static, fun: inner_fun, arg: head } = fun.as_ref() {
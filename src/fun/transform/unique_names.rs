@@ -1,7 +1,7 @@
 // Pass to give all variables in a definition unique names.
 
 use crate::{
-  fun::{Book, Name, Term},
+  fun::{BendTerm, Book, MatchTerm, Name, SwitchTerm, Term},
   maybe_grow,
 };
 use std::collections::HashMap;
@@ -10,6 +10,7 @@ impl Book {
   /// Makes all variables in each definition have a new unique name.
   /// Skips unbound variables.
   /// Precondition: Definition references have been resolved.
+  #[tracing::instrument(level = "debug", skip_all)]
   pub fn make_var_names_unique(&mut self) {
     for def in self.defs.values_mut() {
       def.rule_mut().body.make_var_names_unique();
@@ -39,8 +40,8 @@ impl UniqueNameGenerator {
     maybe_grow(|| match term {
       Term::Var { nam } => *nam = self.use_var(nam),
 
-      Term::Mat { bnd, arg, with_bnd, with_arg, arms }
-      | Term::Fold { bnd, arg, with_bnd, with_arg, arms } => {
+      Term::Mat(mat) | Term::Fold(mat) => {
+        let MatchTerm { bnd, arg, with_bnd, with_arg, arms } = mat.as_mut();
         // Process args
         self.unique_names_in_term(arg);
         for arg in with_arg {
@@ -76,7 +77,8 @@ impl UniqueNameGenerator {
         *bnd = self.pop(bnd.as_ref());
       }
 
-      Term::Swt { bnd, arg, with_bnd, with_arg, pred, arms } => {
+      Term::Swt(swt) => {
+        let SwitchTerm { bnd, arg, with_bnd, with_arg, pred, arms } = swt.as_mut();
         self.unique_names_in_term(arg);
         for arg in with_arg {
           self.unique_names_in_term(arg);
@@ -102,7 +104,8 @@ impl UniqueNameGenerator {
         *bnd = self.pop(bnd.as_ref());
       }
 
-      Term::Bend { bnd, arg, cond, step, base } => {
+      Term::Bend(bend) => {
+        let BendTerm { bnd, arg, cond, step, base } = bend.as_mut();
         for arg in arg {
           self.unique_names_in_term(arg);
         }
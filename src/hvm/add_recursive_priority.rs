@@ -3,6 +3,7 @@ use crate::maybe_grow;
 use hvm::ast::{Book, Net, Tree};
 use std::collections::{HashMap, HashSet};
 
+#[tracing::instrument(level = "debug", skip_all)]
 pub fn add_recursive_priority(book: &mut Book) {
   // Direct dependencies
   let deps = book.defs.iter().map(|(nam, net)| (nam.clone(), dependencies(net))).collect::<HashMap<_, _>>();
@@ -112,11 +113,12 @@ fn dependencies(net: &Net) -> HashSet<String> {
 }
 
 fn dependencies_tree(tree: &Tree, deps: &mut HashSet<String>) {
-  if let Tree::Ref { nam, .. } = tree {
-    deps.insert(nam.clone());
-  } else {
-    for subtree in tree_children(tree) {
-      dependencies_tree(subtree, deps);
+  let mut to_visit = vec![tree];
+  while let Some(tree) = to_visit.pop() {
+    if let Tree::Ref { nam, .. } = tree {
+      deps.insert(nam.clone());
+    } else {
+      to_visit.extend(tree_children(tree));
     }
   }
 }
@@ -5,20 +5,27 @@ use hvm::ast::{Book, Net, Tree};
 pub const MAX_NET_SIZE_C: usize = 4095;
 pub const MAX_NET_SIZE_CUDA: usize = 64;
 
+#[tracing::instrument(level = "debug", skip_all)]
 pub fn check_net_sizes(
   book: &Book,
   diagnostics: &mut Diagnostics,
   target: &CompilerTarget,
+  max_net_size: Option<usize>,
 ) -> Result<(), Diagnostics> {
-  let (net_size_bound, target_lang) = match target {
-    CompilerTarget::Cuda => (MAX_NET_SIZE_CUDA, "Cuda"),
-    _ => (MAX_NET_SIZE_C, "C"),
+  let (net_size_bound, target_lang) = match (max_net_size, target) {
+    (Some(max_net_size), _) => (max_net_size, None),
+    (None, CompilerTarget::Cuda) => (MAX_NET_SIZE_CUDA, Some("Cuda")),
+    (None, _) => (MAX_NET_SIZE_C, Some("C")),
   };
   for (name, net) in &book.defs {
     let nodes = count_nodes(net);
     if nodes > net_size_bound {
+      let too_large = match target_lang {
+        Some(target_lang) => format!("too large for HVM {target_lang}"),
+        None => "too large".to_string(),
+      };
       diagnostics.add_function_error(
-        format!("Definition is too large for HVM {target_lang} (size={nodes}, max size={net_size_bound}). Please break it into smaller pieces."),
+        format!("Definition is {too_large} (size={nodes}, max size={net_size_bound}). Please break it into smaller pieces."),
         Name::new(name),
         Default::default()
       );
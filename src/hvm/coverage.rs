@@ -0,0 +1,111 @@
+use hvm::{
+  ast,
+  hvm::{GNet, Pair, Port, TMem, ANNI, CALL, COMM, DUP, ERAS, LINK, NONE, OPER, REF, ROOT, SWIT, VOID},
+};
+
+/// The version of HVM whose runtime is driven by [run_with_coverage].
+/// The runtime has no public entry point that reports the calls, so the evaluation loop,
+/// the size of the memory and the encoding of the references are the ones of this version,
+/// and must be checked again when HVM is updated.
+pub const HVM_VERSION: &str = "2.0.22";
+
+/// The number of nodes and variables of the memory, like in the `run` of HVM.
+const MEMORY_LEN: usize = 1 << 29;
+
+/// The bits of the value of a reference with the id of its definition, like in `interact_call`.
+const REF_ID_MASK: usize = 0xFFFFFFF;
+
+/// Runs an HVM book with the Rust runtime of HVM, counting how many times each definition was expanded.
+///
+/// Reuses the interactions of the runtime, but drives them itself so that every call can be seen.
+/// Calls that only copy a definition, without expanding it, are not counted.
+/// Returns the resulting net and the number of calls of each definition that was called at least once.
+/// Fails if the book references definitions that only exist in the runtime, like the ones for IO.
+pub fn run_with_coverage(
+  book: &ast::Book,
+  entrypoint: &str,
+) -> Result<(ast::Net, Vec<(String, u64)>), String> {
+  for (name, def) in &book.defs {
+    for tree in super::net_trees(def) {
+      let mut to_visit = vec![tree];
+      while let Some(tree) = to_visit.pop() {
+        if let ast::Tree::Ref { nam } = tree {
+          if !book.defs.contains_key(nam) {
+            return Err(format!(
+              "Definition '{name}' uses '{nam}', which is provided by the HVM runtime. Programs that do IO can't be run in coverage mode."
+            ));
+          }
+        }
+        to_visit.extend(super::tree_children(tree));
+      }
+    }
+  }
+
+  let book = book.build();
+  let Some(main_id) = book.defs.iter().position(|def| def.name == entrypoint) else {
+    return Err(format!("The program has no '{entrypoint}' definition."));
+  };
+
+  let net = GNet::new(MEMORY_LEN, MEMORY_LEN);
+  let mut tm = TMem::new(0, 1);
+  let mut calls = vec![0u64; book.defs.len()];
+
+  net.vars_create(ROOT.get_val() as usize, NONE);
+  let mut next = Some(Port::new(REF, main_id as u32));
+  while let Some(fun) = next.take() {
+    tm.rbag.push_redex(Pair::new(fun, ROOT));
+    while let Some(redex) = tm.rbag.pop_redex() {
+      let (mut a, mut b) = (redex.get_fst(), redex.get_snd());
+      let mut rule = Port::get_rule(a, b);
+      if a.get_tag() == REF && b == ROOT {
+        rule = CALL;
+      } else if Port::should_swap(a, b) {
+        (a, b) = (b, a);
+      }
+      let success = match rule {
+        LINK => tm.interact_link(&net, a, b),
+        CALL => tm.interact_call(&net, a, b, &book),
+        VOID => tm.interact_void(&net, a, b),
+        ERAS => tm.interact_eras(&net, a, b),
+        ANNI => tm.interact_anni(&net, a, b),
+        COMM => tm.interact_comm(&net, a, b),
+        OPER => tm.interact_oper(&net, a, b),
+        SWIT => tm.interact_swit(&net, a, b),
+        _ => unreachable!(),
+      };
+      if !success {
+        return Err("The HVM runtime ran out of memory.".to_string());
+      }
+      if rule == CALL && b.get_tag() != DUP {
+        calls[(a.get_val() as usize) & REF_ID_MASK] += 1;
+      }
+    }
+
+    // A reference returned by the program is not a redex, but it is what the program evaluates to.
+    let root = net.enter(ROOT);
+    if root.get_tag() == REF {
+      net.vars_create(ROOT.get_val() as usize, NONE);
+      next = Some(root);
+    } else if root != ROOT {
+      // Entering the root takes its value, so it's put back for the readback.
+      net.vars_create(ROOT.get_val() as usize, root);
+    }
+  }
+
+  let Some(result) = ast::Net::readback(&net, &book) else {
+    return Err("Failed to read back the result of the program.".to_string());
+  };
+  let calls = book.defs.iter().zip(calls).filter(|(_, n)| *n > 0).map(|(def, n)| (def.name.clone(), n));
+  Ok((result, calls.collect()))
+}
+
+#[test]
+fn hvm_version() {
+  let manifest = include_str!("../../Cargo.toml");
+  let dependency = manifest.lines().find(|line| line.starts_with("hvm =")).unwrap();
+  assert_eq!(
+    dependency,
+    format!("hvm = \"={HVM_VERSION}\""),
+    "check the coverage runner with the new HVM version"
+  );
+}
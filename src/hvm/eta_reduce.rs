@@ -53,7 +53,7 @@
 //!
 //! The pass also reduces subnets such as `(* *) -> *`
 
-use crate::hvm::net_trees_mut;
+use crate::{hvm::net_trees_mut, maybe_grow};
 
 use super::{tree_children, tree_children_mut};
 use core::ops::RangeFrom;
@@ -89,34 +89,26 @@ struct Phase1<'a> {
 
 impl<'a> Phase1<'a> {
   fn walk_tree(&mut self, tree: &'a Tree) {
-    match tree {
-      Tree::Con { fst, snd } => {
-        self.nodes.push(NodeType::Ctr(0));
-        self.walk_tree(fst);
-        self.walk_tree(snd);
-      }
-      Tree::Dup { fst, snd } => {
-        self.nodes.push(NodeType::Ctr(1));
-        self.walk_tree(fst);
-        self.walk_tree(snd);
-      }
-      Tree::Var { nam } => {
-        if let Some(i) = self.vars.get(&**nam) {
-          let j = self.nodes.len() as isize;
-          self.nodes.push(NodeType::Var(*i as isize - j));
-          self.nodes[*i] = NodeType::Var(j - *i as isize);
-        } else {
-          self.vars.insert(nam, self.nodes.len());
-          self.nodes.push(NodeType::Hole);
-        }
-      }
-      Tree::Era => self.nodes.push(NodeType::Era),
-      _ => {
-        self.nodes.push(NodeType::Other);
-        for i in tree_children(tree) {
-          self.walk_tree(i);
+    // Pre-order traversal with an explicit stack, so that deep trees don't overflow the native stack.
+    let mut to_visit = vec![tree];
+    while let Some(tree) = to_visit.pop() {
+      match tree {
+        Tree::Con { .. } => self.nodes.push(NodeType::Ctr(0)),
+        Tree::Dup { .. } => self.nodes.push(NodeType::Ctr(1)),
+        Tree::Var { nam } => {
+          if let Some(i) = self.vars.get(&**nam) {
+            let j = self.nodes.len() as isize;
+            self.nodes.push(NodeType::Var(*i as isize - j));
+            self.nodes[*i] = NodeType::Var(j - *i as isize);
+          } else {
+            self.vars.insert(nam, self.nodes.len());
+            self.nodes.push(NodeType::Hole);
+          }
         }
+        Tree::Era => self.nodes.push(NodeType::Era),
+        _ => self.nodes.push(NodeType::Other),
       }
+      to_visit.extend(tree_children(tree).rev());
     }
   }
 }
@@ -154,15 +146,17 @@ impl Phase2 {
   }
 
   fn reduce_tree(&mut self, tree: &mut Tree) -> NodeType {
-    let idx = self.index.next().unwrap();
-    match tree {
-      Tree::Con { .. } | Tree::Dup { .. } => self.reduce_ctr(tree, idx),
-      _ => {
-        for child in tree_children_mut(tree) {
-          self.reduce_tree(child);
+    maybe_grow(|| {
+      let idx = self.index.next().unwrap();
+      match tree {
+        Tree::Con { .. } | Tree::Dup { .. } => self.reduce_ctr(tree, idx),
+        _ => {
+          for child in tree_children_mut(tree) {
+            self.reduce_tree(child);
+          }
+          self.nodes[idx]
         }
-        self.nodes[idx]
       }
-    }
+    })
   }
 }
@@ -4,14 +4,21 @@ use core::ops::BitOr;
 use hvm::ast::{Book, Net, Tree};
 use std::collections::{HashMap, HashSet};
 
-pub fn inline_hvm_book(book: &mut Book) -> Result<HashSet<String>, String> {
+/// Replaces the references to the definitions whose nets are a single node, like numbers and erasers,
+/// and to the definitions marked with `#[inline]` in `attrs`, by their nets.
+/// The definitions marked with `#[noinline]` are never inlined.
+///
+/// Fails if a definition marked with `#[inline]` can't be inlined, see [can_inline].
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn inline_hvm_book(book: &mut Book, attrs: &HashMap<String, bool>) -> Result<HashSet<String>, String> {
   let mut state = InlineState::default();
-  state.populate_inlinees(book)?;
+  state.populate_inlinees(book, attrs)?;
   let mut all_changed = HashSet::new();
+  let mut n_inlined = 0;
   for (name, net) in &mut book.defs {
     let mut inlined = false;
     for tree in net_trees_mut(net) {
-      inlined |= state.inline_into(tree);
+      inlined |= state.inline_into(tree, &mut n_inlined);
     }
     if inlined {
       all_changed.insert(name.to_owned());
@@ -26,9 +33,17 @@ struct InlineState {
 }
 
 impl InlineState {
-  fn populate_inlinees(&mut self, book: &Book) -> Result<(), String> {
+  fn populate_inlinees(&mut self, book: &Book, attrs: &HashMap<String, bool>) -> Result<(), String> {
+    let should_inline = |name: &str, net: &Net| match attrs.get(name) {
+      Some(true) if can_inline(net) => Ok(true),
+      Some(true) => Err(format!(
+        "`@{name}` is marked #[inline], but it can't be inlined because it duplicates values or has redexes."
+      )),
+      Some(false) => Ok(false),
+      None => Ok(is_single_node(net)),
+    };
     for (name, net) in &book.defs {
-      if should_inline(net) {
+      if should_inline(name, net)? {
         // Detect cycles with tortoise and hare algorithm
         let mut hare = &net.root;
         let mut tortoise = &net.root;
@@ -36,7 +51,7 @@ impl InlineState {
         let mut parity = false;
         while let Tree::Ref { nam, .. } = hare {
           let Some(net) = &book.defs.get(nam) else { break };
-          if should_inline(net) {
+          if should_inline(nam, net)? {
             hare = &net.root;
           } else {
             break;
@@ -55,13 +70,17 @@ impl InlineState {
     }
     Ok(())
   }
-  fn inline_into(&self, tree: &mut Tree) -> bool {
+  /// Inlines the references in the tree. `n_inlined` counts the inlined nets,
+  /// to give unique names to the variables of each copy.
+  fn inline_into(&self, tree: &mut Tree, n_inlined: &mut usize) -> bool {
     maybe_grow(|| {
       let Tree::Ref { nam, .. } = &*tree else {
-        return tree_children_mut(tree).map(|t| self.inline_into(t)).fold(false, bool::bitor);
+        return tree_children_mut(tree).map(|t| self.inline_into(t, n_inlined)).fold(false, bool::bitor);
       };
       if let Some(inlined) = self.inlinees.get(nam) {
         *tree = inlined.clone();
+        rename_vars(tree, *n_inlined);
+        *n_inlined += 1;
         true
       } else {
         false
@@ -70,6 +89,33 @@ impl InlineState {
   }
 }
 
-fn should_inline(net: &Net) -> bool {
+fn is_single_node(net: &Net) -> bool {
   net.rbag.is_empty() && tree_children(&net.root).next().is_none()
 }
+
+/// Whether a reference to the net can be replaced by the net without changing the result of the program.
+///
+/// The net can't have DUP nodes, since they would annihilate with the DUP nodes that copy the inlined net,
+/// instead of commuting with them like when a reference is copied.
+/// It also can't have redexes, which would be reduced even where the reference would never be expanded.
+fn can_inline(net: &Net) -> bool {
+  let mut to_visit = vec![&net.root];
+  while let Some(tree) = to_visit.pop() {
+    if matches!(tree, Tree::Dup { .. }) {
+      return false;
+    }
+    to_visit.extend(tree_children(tree));
+  }
+  net.rbag.is_empty()
+}
+
+/// Gives the variables of a copy of an inlined net names that aren't used by the net it's inlined into.
+fn rename_vars(tree: &mut Tree, n_inlined: usize) {
+  let mut to_visit = vec![tree];
+  while let Some(tree) = to_visit.pop() {
+    if let Tree::Var { nam } = tree {
+      *nam = format!("{nam}$i{n_inlined}");
+    }
+    to_visit.extend(tree_children_mut(tree));
+  }
+}
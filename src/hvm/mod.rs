@@ -3,6 +3,7 @@ use hvm::ast::{Net, Tree};
 
 pub mod add_recursive_priority;
 pub mod check_net_size;
+pub mod coverage;
 pub mod eta_reduce;
 pub mod inline;
 pub mod mutual_recursion;
@@ -39,20 +40,73 @@ pub fn net_trees_mut(net: &mut Net) -> impl DoubleEndedIterator<Item = &mut Tree
 pub fn hvm_book_show_pretty(book: &hvm::ast::Book) -> String {
   let mut s = String::new();
   for (nam, def) in book.defs.iter() {
-    s.push_str(&format!("@{} = {}\n", nam, def.root.show()));
-    for (pri, a, b) in def.rbag.iter() {
-      s.push_str("  &");
-      if *pri {
-        s.push('!');
-      } else {
-        s.push(' ');
-      }
-      s.push_str(&a.show());
-      s.push_str(" ~ ");
-      s.push_str(&b.show());
-      s.push('\n');
+    s.push_str(&hvm_def_show_pretty(nam, def));
+  }
+  s
+}
+
+/// Shows a definition with each redex of the net in its own line.
+pub fn hvm_def_show_pretty(nam: &str, def: &Net) -> String {
+  let mut s = format!("@{} = {}\n", nam, show_tree(&def.root));
+  for (pri, a, b) in def.rbag.iter() {
+    s.push_str("  &");
+    if *pri {
+      s.push('!');
+    } else {
+      s.push(' ');
     }
+    s.push_str(&show_tree(a));
+    s.push_str(" ~ ");
+    s.push_str(&show_tree(b));
     s.push('\n');
   }
+  s.push('\n');
+  s
+}
+
+/// Shows a tree in the same syntax as [`Tree::show`],
+/// but without recursion so that very deep trees don't overflow the stack.
+pub fn show_tree(tree: &Tree) -> String {
+  enum Item<'a> {
+    Tree(&'a Tree),
+    Str(&'static str),
+  }
+
+  let mut s = String::new();
+  let mut to_show = vec![Item::Tree(tree)];
+  while let Some(item) = to_show.pop() {
+    let tree = match item {
+      Item::Str(str) => {
+        s.push_str(str);
+        continue;
+      }
+      Item::Tree(tree) => tree,
+    };
+    let (open, close, fst, snd) = match tree {
+      Tree::Var { nam } => {
+        s.push_str(nam);
+        continue;
+      }
+      Tree::Ref { nam } => {
+        s.push('@');
+        s.push_str(nam);
+        continue;
+      }
+      Tree::Era => {
+        s.push('*');
+        continue;
+      }
+      Tree::Num { val } => {
+        s.push_str(&val.show());
+        continue;
+      }
+      Tree::Con { fst, snd } => ("(", ")", fst, snd),
+      Tree::Dup { fst, snd } => ("{", "}", fst, snd),
+      Tree::Opr { fst, snd } => ("$(", ")", fst, snd),
+      Tree::Swi { fst, snd } => ("?(", ")", fst, snd),
+    };
+    s.push_str(open);
+    to_show.extend([Item::Str(close), Item::Tree(snd), Item::Str(" "), Item::Tree(fst)]);
+  }
   s
 }
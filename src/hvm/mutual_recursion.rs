@@ -15,6 +15,7 @@ type RefSet = IndexSet<Ref>;
 #[derive(Default)]
 pub struct Graph(IndexMap<Ref, RefSet>);
 
+#[tracing::instrument(level = "debug", skip_all)]
 pub fn check_cycles(book: &Book, diagnostics: &mut Diagnostics) -> Result<(), Diagnostics> {
   let graph = Graph::from(book);
   let cycles = graph.cycles();
@@ -3,6 +3,7 @@ use crate::maybe_grow;
 use hvm::ast::{Book, Tree};
 use std::collections::HashSet;
 
+#[tracing::instrument(level = "debug", skip_all)]
 pub fn prune_hvm_book(book: &mut Book, entrypoints: &[String]) {
   let mut state = PruneState { book, unvisited: book.defs.keys().map(|x| x.to_owned()).collect() };
   for name in entrypoints {
@@ -1,4 +1,4 @@
-use crate::fun::Name;
+use crate::{fun::Name, maybe_grow};
 
 use super::{AssignPattern, Definition, Expr, Stmt};
 
@@ -14,7 +14,7 @@ impl Definition {
 
 impl Stmt {
   fn gen_map_get(&mut self, id: &mut usize) {
-    match self {
+    maybe_grow(|| match self {
       Stmt::LocalDef { def, nxt } => {
         nxt.gen_map_get(id);
         def.gen_map_get()
@@ -86,6 +86,9 @@ impl Stmt {
         for arg in with_arg {
           substitutions.extend(arg.substitute_map_gets(id));
         }
+        for guard in arms.iter_mut().filter_map(|arm| arm.guard.as_mut()) {
+          substitutions.extend(guard.substitute_map_gets(id));
+        }
         if !substitutions.is_empty() {
           *self = gen_get(self, substitutions);
         }
@@ -142,7 +145,7 @@ impl Stmt {
         }
       }
       Stmt::Err => {}
-    }
+    })
   }
 }
 
@@ -151,7 +154,7 @@ type Substitutions = Vec<(Name, Name, Box<Expr>)>;
 impl Expr {
   fn substitute_map_gets(&mut self, id: &mut usize) -> Substitutions {
     fn go(e: &mut Expr, substitutions: &mut Substitutions, id: &mut usize) {
-      match e {
+      maybe_grow(|| match e {
         Expr::MapGet { nam, key } => {
           go(key, substitutions, id);
           let new_var = gen_map_var(id);
@@ -170,7 +173,7 @@ impl Expr {
         Expr::Lam { bod, .. } => {
           go(bod, substitutions, id);
         }
-        Expr::Opr { lhs, rhs, .. } => {
+        Expr::Opr { lhs, rhs, .. } | Expr::Logic { lhs, rhs, .. } => {
           go(lhs, substitutions, id);
           go(rhs, substitutions, id);
         }
@@ -204,7 +207,7 @@ impl Expr {
           go(val, substitutions, id);
         }
         Expr::Era | Expr::Str { .. } | Expr::Var { .. } | Expr::Chn { .. } | Expr::Num { .. } => {}
-      }
+      })
     }
     let mut substitutions = Substitutions::new();
     go(self, &mut substitutions, id);
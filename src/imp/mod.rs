@@ -3,7 +3,7 @@ mod order_kwargs;
 pub mod parser;
 pub mod to_fun;
 
-use crate::fun::{Name, Num, Op, Source, Type};
+use crate::fun::{Name, Num, Op, RuleNames, Source, Type};
 use interner::global::GlobalString;
 
 #[derive(Clone, Debug)]
@@ -22,6 +22,8 @@ pub enum Expr {
   Lam { names: Vec<(Name, bool)>, bod: Box<Expr> },
   // {lhs} {op} {rhs}
   Opr { op: Op, lhs: Box<Expr>, rhs: Box<Expr> },
+  // {lhs} ("&&" | "||") {rhs}
+  Logic { op: LogicOp, lhs: Box<Expr>, rhs: Box<Expr> },
   // "\"" ... "\""
   Str { val: GlobalString },
   // "[" ... "]"
@@ -44,9 +46,17 @@ pub enum Expr {
   TreeLeaf { val: Box<Expr> },
 }
 
+/// The short-circuiting logical operators, that only evaluate the right side if needed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LogicOp {
+  And,
+  Or,
+}
+
 #[derive(Clone, Debug)]
 pub struct MatchArm {
   pub lft: Option<Name>,
+  pub guard: Option<Expr>,
   pub rgt: Stmt,
 }
 
@@ -209,6 +219,8 @@ pub struct Definition {
   pub args: Vec<Name>,
   pub body: Stmt,
   pub source: Source,
+  /// The names written in the definition.
+  pub names: RuleNames,
 }
 
 impl InPlaceOp {
@@ -1,6 +1,7 @@
 use crate::{
   fun::{parser::ParseBook, Name},
   imp::{Definition, Expr, Stmt},
+  maybe_grow,
 };
 use indexmap::IndexMap;
 
@@ -15,171 +16,170 @@ impl Definition {
 
 impl Stmt {
   fn order_kwargs(&mut self, book: &ParseBook, use_map: &mut IndexMap<Name, Name>) -> Result<(), String> {
-    match self {
-      Stmt::LocalDef { def, nxt } => {
-        def.order_kwargs(book)?;
-        nxt.order_kwargs(book, use_map)?;
-      }
-      Stmt::Assign { val, nxt, .. } => {
-        val.order_kwargs(book, use_map)?;
-        if let Some(nxt) = nxt {
-          nxt.order_kwargs(book, use_map)?;
-        }
-      }
-      Stmt::Ask { val, nxt, .. } => {
-        val.order_kwargs(book, use_map)?;
-        if let Some(nxt) = nxt {
-          nxt.order_kwargs(book, use_map)?;
-        }
-      }
-      Stmt::InPlace { val, nxt, .. } => {
-        val.order_kwargs(book, use_map)?;
-        nxt.order_kwargs(book, use_map)?;
-      }
-      Stmt::If { cond, then, otherwise, nxt } => {
-        cond.order_kwargs(book, use_map)?;
-        then.order_kwargs(book, use_map)?;
-        otherwise.order_kwargs(book, use_map)?;
-        if let Some(nxt) = nxt {
+    maybe_grow(|| {
+      match self {
+        Stmt::LocalDef { def, nxt } => {
+          def.order_kwargs(book)?;
           nxt.order_kwargs(book, use_map)?;
         }
-      }
-      Stmt::Match { arg, arms, nxt, .. } => {
-        arg.order_kwargs(book, use_map)?;
-        for arm in arms {
-          arm.rgt.order_kwargs(book, use_map)?;
-        }
-        if let Some(nxt) = nxt {
-          nxt.order_kwargs(book, use_map)?;
+        Stmt::Assign { val, nxt, .. } => {
+          val.order_kwargs(book, use_map)?;
+          if let Some(nxt) = nxt {
+            nxt.order_kwargs(book, use_map)?;
+          }
         }
-      }
-      Stmt::Switch { arg, arms, nxt, .. } => {
-        arg.order_kwargs(book, use_map)?;
-        for arm in arms {
-          arm.order_kwargs(book, use_map)?;
+        Stmt::Ask { val, nxt, .. } => {
+          val.order_kwargs(book, use_map)?;
+          if let Some(nxt) = nxt {
+            nxt.order_kwargs(book, use_map)?;
+          }
         }
-        if let Some(nxt) = nxt {
+        Stmt::InPlace { val, nxt, .. } => {
+          val.order_kwargs(book, use_map)?;
           nxt.order_kwargs(book, use_map)?;
         }
-      }
-      Stmt::Fold { arg, arms, nxt, .. } => {
-        arg.order_kwargs(book, use_map)?;
-        for arm in arms {
-          arm.rgt.order_kwargs(book, use_map)?;
+        Stmt::If { cond, then, otherwise, nxt } => {
+          cond.order_kwargs(book, use_map)?;
+          then.order_kwargs(book, use_map)?;
+          otherwise.order_kwargs(book, use_map)?;
+          if let Some(nxt) = nxt {
+            nxt.order_kwargs(book, use_map)?;
+          }
         }
-        if let Some(nxt) = nxt {
-          nxt.order_kwargs(book, use_map)?;
+        Stmt::Match { arg, arms, nxt, .. } => {
+          arg.order_kwargs(book, use_map)?;
+          for arm in arms {
+            if let Some(guard) = &mut arm.guard {
+              guard.order_kwargs(book, use_map)?;
+            }
+            arm.rgt.order_kwargs(book, use_map)?;
+          }
+          if let Some(nxt) = nxt {
+            nxt.order_kwargs(book, use_map)?;
+          }
         }
-      }
-      Stmt::Bend { bnd: _, arg, cond, step, base, nxt } => {
-        for arg in arg {
+        Stmt::Switch { arg, arms, nxt, .. } => {
           arg.order_kwargs(book, use_map)?;
+          for arm in arms {
+            arm.order_kwargs(book, use_map)?;
+          }
+          if let Some(nxt) = nxt {
+            nxt.order_kwargs(book, use_map)?;
+          }
         }
-        cond.order_kwargs(book, use_map)?;
-        step.order_kwargs(book, use_map)?;
-        base.order_kwargs(book, use_map)?;
-        if let Some(nxt) = nxt {
-          nxt.order_kwargs(book, use_map)?;
+        Stmt::Fold { arg, arms, nxt, .. } => {
+          arg.order_kwargs(book, use_map)?;
+          for arm in arms {
+            arm.rgt.order_kwargs(book, use_map)?;
+          }
+          if let Some(nxt) = nxt {
+            nxt.order_kwargs(book, use_map)?;
+          }
         }
-      }
-      Stmt::With { typ: _, bod, nxt } => {
-        bod.order_kwargs(book, use_map)?;
-        if let Some(nxt) = nxt {
-          nxt.order_kwargs(book, use_map)?;
+        Stmt::Bend { bnd: _, arg, cond, step, base, nxt } => {
+          for arg in arg {
+            arg.order_kwargs(book, use_map)?;
+          }
+          cond.order_kwargs(book, use_map)?;
+          step.order_kwargs(book, use_map)?;
+          base.order_kwargs(book, use_map)?;
+          if let Some(nxt) = nxt {
+            nxt.order_kwargs(book, use_map)?;
+          }
         }
-      }
-      Stmt::Open { typ: _, var: _, nxt } => {
-        nxt.order_kwargs(book, use_map)?;
-      }
-      Stmt::Use { nam, val: bod, nxt } => {
-        if let Expr::Var { nam: bod } = bod.as_ref() {
-          use_map.insert(nam.clone(), bod.clone());
-          nxt.order_kwargs(book, use_map)?;
-          use_map.pop();
-        } else {
+        Stmt::With { typ: _, bod, nxt } => {
           bod.order_kwargs(book, use_map)?;
+          if let Some(nxt) = nxt {
+            nxt.order_kwargs(book, use_map)?;
+          }
+        }
+        Stmt::Open { typ: _, var: _, nxt } => {
           nxt.order_kwargs(book, use_map)?;
         }
+        Stmt::Use { nam, val: bod, nxt } => {
+          if let Expr::Var { nam: bod } = bod.as_ref() {
+            use_map.insert(nam.clone(), bod.clone());
+            nxt.order_kwargs(book, use_map)?;
+            use_map.pop();
+          } else {
+            bod.order_kwargs(book, use_map)?;
+            nxt.order_kwargs(book, use_map)?;
+          }
+        }
+        Stmt::Return { term } => term.order_kwargs(book, use_map)?,
+        Stmt::Err => {}
       }
-      Stmt::Return { term } => term.order_kwargs(book, use_map)?,
-      Stmt::Err => {}
-    }
-    Ok(())
+      Ok(())
+    })
   }
 }
 
 impl Expr {
   fn order_kwargs(&mut self, book: &ParseBook, use_map: &mut IndexMap<Name, Name>) -> Result<(), String> {
-    match self {
-      // Named arguments are only allowed when directly calling a named function.
-      Expr::Call { fun, args, kwargs } => {
-        if !kwargs.is_empty() {
-          if let Expr::Var { nam } = fun.as_ref() {
-            if let Some(names) = get_args_def_or_ctr(nam, book, use_map) {
-              go_order_kwargs(&names, args, kwargs)?;
+    // Uses an explicit stack so that deeply nested expressions don't overflow the native stack.
+    let mut to_visit = vec![self];
+    while let Some(expr) = to_visit.pop() {
+      match expr {
+        // Named arguments are only allowed when directly calling a named function.
+        Expr::Call { fun, args, kwargs } => {
+          if !kwargs.is_empty() {
+            if let Expr::Var { nam } = fun.as_ref() {
+              if let Some(names) = get_args_def_or_ctr(nam, book, use_map) {
+                go_order_kwargs(&names, args, kwargs)?;
+              } else {
+                return Err(format!(
+                  "Named args are only allowed when calling a named function, not when calling variable '{nam}'."
+                ));
+              }
             } else {
-              return Err(format!(
-                "Named args are only allowed when calling a named function, not when calling variable '{nam}'."
-              ));
+              // TODO: Print expression
+              return Err(
+                "Named args are only allowed when calling a named function, not when calling an expression."
+                  .to_string(),
+              );
             }
-          } else {
-            // TODO: Print expression
-            return Err(
-              "Named args are only allowed when calling a named function, not when calling an expression."
-                .to_string(),
-            );
           }
+          to_visit.extend(kwargs.iter_mut().rev().map(|(_, arg)| arg));
+          to_visit.extend(args.iter_mut().rev());
+          to_visit.push(fun);
         }
-        fun.order_kwargs(book, use_map)?;
-        for arg in args {
-          arg.order_kwargs(book, use_map)?;
-        }
-        for (_, arg) in kwargs {
-          arg.order_kwargs(book, use_map)?;
+        Expr::Lam { bod, .. } => to_visit.push(bod),
+        Expr::Opr { lhs, rhs, .. } | Expr::Logic { lhs, rhs, .. } => {
+          to_visit.push(rhs);
+          to_visit.push(lhs);
         }
-      }
-      Expr::Lam { bod, .. } => bod.order_kwargs(book, use_map)?,
-      Expr::Opr { lhs, rhs, .. } => {
-        lhs.order_kwargs(book, use_map)?;
-        rhs.order_kwargs(book, use_map)?;
-      }
-      Expr::Lst { els } | Expr::Tup { els } | Expr::Sup { els } => {
-        for el in els {
-          el.order_kwargs(book, use_map)?;
+        Expr::Lst { els } | Expr::Tup { els } | Expr::Sup { els } => {
+          to_visit.extend(els.iter_mut().rev());
         }
-      }
-      Expr::LstMap { term, iter, cond, .. } => {
-        term.order_kwargs(book, use_map)?;
-        iter.order_kwargs(book, use_map)?;
-        if let Some(cond) = cond {
-          cond.order_kwargs(book, use_map)?;
+        Expr::LstMap { term, iter, cond, .. } => {
+          if let Some(cond) = cond {
+            to_visit.push(cond);
+          }
+          to_visit.push(iter);
+          to_visit.push(term);
         }
-      }
-      Expr::Ctr { name, args, kwargs } => match get_args_def_or_ctr(name, book, use_map) {
-        Some(names) => {
-          go_order_kwargs(&names, args, kwargs)?;
-          for arg in args {
-            arg.order_kwargs(book, use_map)?;
+        Expr::Ctr { name, args, kwargs } => match get_args_def_or_ctr(name, book, use_map) {
+          Some(names) => {
+            go_order_kwargs(&names, args, kwargs)?;
+            to_visit.extend(args.iter_mut().rev());
           }
+          _ => return Err(format!("Constructor '{name}' not found.")),
+        },
+        Expr::Map { entries } => {
+          to_visit.extend(entries.iter_mut().rev().map(|entry| &mut entry.1));
         }
-        _ => return Err(format!("Constructor '{name}' not found.")),
-      },
-      Expr::Map { entries } => {
-        for entry in entries {
-          entry.1.order_kwargs(book, use_map)?;
+        Expr::MapGet { nam: _, key } => {
+          to_visit.push(key);
         }
+        Expr::TreeNode { left, right } => {
+          to_visit.push(right);
+          to_visit.push(left);
+        }
+        Expr::TreeLeaf { val } => {
+          to_visit.push(val);
+        }
+        Expr::Era | Expr::Var { .. } | Expr::Chn { .. } | Expr::Num { .. } | Expr::Str { .. } => {}
       }
-      Expr::MapGet { nam: _, key } => {
-        key.order_kwargs(book, use_map)?;
-      }
-      Expr::TreeNode { left, right } => {
-        left.order_kwargs(book, use_map)?;
-        right.order_kwargs(book, use_map)?;
-      }
-      Expr::TreeLeaf { val } => {
-        val.order_kwargs(book, use_map)?;
-      }
-      Expr::Era | Expr::Var { .. } | Expr::Chn { .. } | Expr::Num { .. } | Expr::Str { .. } => {}
     }
     Ok(())
   }
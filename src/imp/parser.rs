@@ -1,11 +1,13 @@
 use crate::{
   fun::{
-    parser::{is_num_char, make_ctr_type, make_fn_type, Indent, ParseResult, ParserCommons},
-    Adt, AdtCtr, CtrField, HvmDefinition, Name, Num, Op, Source, SourceKind, Type, STRINGS,
+    parser::{assert_location, is_num_char, make_ctr_type, make_fn_type, Indent, ParseResult, ParserCommons},
+    transform::desugar_assert::ASSERT_FAILED,
+    Adt, AdtCtr, CtrField, HvmDefinition, Name, Num, Op, RuleNames, Source, SourceKind, Type, STRINGS,
   },
-  imp::{AssignPattern, Definition, Expr, InPlaceOp, MatchArm, Stmt},
+  imp::{AssignPattern, Definition, Expr, InPlaceOp, LogicOp, MatchArm, Stmt},
   maybe_grow,
 };
+use std::ops::Range;
 use TSPL::Parser;
 
 pub struct ImpParser<'i> {
@@ -13,11 +15,15 @@ pub struct ImpParser<'i> {
   pub input: &'i str,
   pub index: usize,
   pub builtin: bool,
+  /// The binders parsed, with their byte spans, for the parser of the book to give them to the definitions.
+  pub binders: Vec<(Name, Range<usize>)>,
+  /// The variables parsed, like the binders.
+  pub vars: Vec<(Name, Range<usize>)>,
 }
 
 impl<'a> ImpParser<'a> {
   pub fn new(file: Name, input: &'a str, builtin: bool) -> Self {
-    Self { file, input, index: 0, builtin }
+    Self { file, input, index: 0, builtin, binders: vec![], vars: vec![] }
   }
 
   pub fn parse_function_def(&mut self, indent: Indent) -> ParseResult<(Definition, Indent)> {
@@ -193,9 +199,9 @@ impl<'a> ImpParser<'a> {
       fn parse_lam_var(p: &mut ImpParser) -> ParseResult<(Name, bool)> {
         if p.starts_with("$") {
           p.advance_one();
-          Ok((p.parse_var_name()?, true))
+          Ok((p.parse_binder_name()?, true))
         } else {
-          Ok((p.parse_var_name()?, false))
+          Ok((p.parse_binder_name()?, false))
         }
       }
       let names = self.list_like(|p| parse_lam_var(p), "", ":", ",", false, 1)?;
@@ -221,9 +227,9 @@ impl<'a> ImpParser<'a> {
     } else if self.starts_with("`") {
       // Symbol
       Ok(Expr::Num { val: Num::U24(self.parse_quoted_symbol()?) })
-    } else if self.starts_with("\"") {
+    } else if self.starts_with_string() {
       // String
-      Ok(Expr::Str { val: STRINGS.get(self.parse_quoted_string()?) })
+      Ok(Expr::Str { val: STRINGS.get(self.parse_string()?) })
     } else if self.starts_with("'") {
       // Char
       Ok(Expr::Num { val: Num::U24(self.parse_quoted_char()? as u32 & 0x00ff_ffff) })
@@ -235,13 +241,23 @@ impl<'a> ImpParser<'a> {
       // Era
       self.advance_one();
       Ok(Expr::Era)
+    } else if self.starts_with("-")
+      && !self.input()[*self.index() + 1..].starts_with(|c: char| c.is_ascii_digit())
+    {
+      // Negation, compiled to a subtraction from 0, which is 0 for every type of number.
+      self.advance_one();
+      let expr = self.call_or_postfix(inline)?;
+      Ok(Expr::Opr { op: Op::SUB, lhs: Box::new(Expr::Num { val: Num::U24(0) }), rhs: Box::new(expr) })
     } else if let Some(c) = self.peek_one() {
       if is_num_char(c) {
         // Number
         Ok(Expr::Num { val: self.parse_number()? })
       } else {
         // Var
+        let ini_idx = *self.index();
         let nam = self.labelled(|p| p.parse_var_name(), "expression")?;
+        let end_idx = *self.index();
+        self.add_var(&nam, ini_idx..end_idx);
         Ok(Expr::Var { nam })
       }
     } else {
@@ -401,7 +417,7 @@ impl<'a> ImpParser<'a> {
     if self.try_parse_keyword("for") {
       // Comprehension
       self.skip_trivia();
-      let bind = self.parse_var_name()?;
+      let bind = self.parse_binder_name()?;
       self.skip_trivia();
       self.parse_keyword("in")?;
       let iter = self.parse_expr(false, false)?;
@@ -435,7 +451,7 @@ impl<'a> ImpParser<'a> {
       self.skip_trivia();
     }
 
-    let base = self.parse_infix_expr(0, inline)?;
+    let base = self.parse_or_expr(inline)?;
     if !tup {
       return Ok(base);
     }
@@ -486,6 +502,27 @@ impl<'a> ImpParser<'a> {
     }
   }
 
+  /// Short-circuiting logical expression, `&&` binds tighter than `||`.
+  /// <and> ("||" <and>)*
+  fn parse_or_expr(&mut self, inline: bool) -> ParseResult<Expr> {
+    let mut lhs = self.parse_and_expr(inline)?;
+    while self.try_consume_exactly("||") {
+      let rhs = self.parse_and_expr(inline)?;
+      lhs = Expr::Logic { op: LogicOp::Or, lhs: Box::new(lhs), rhs: Box::new(rhs) };
+    }
+    Ok(lhs)
+  }
+
+  /// <infix> ("&&" <infix>)*
+  fn parse_and_expr(&mut self, inline: bool) -> ParseResult<Expr> {
+    let mut lhs = self.parse_infix_expr(0, inline)?;
+    while self.try_consume_exactly("&&") {
+      let rhs = self.parse_infix_expr(0, inline)?;
+      lhs = Expr::Logic { op: LogicOp::And, lhs: Box::new(lhs), rhs: Box::new(rhs) };
+    }
+    Ok(lhs)
+  }
+
   /// Infix expression.
   /// <simple> (<infix_op> <infix>)?
   fn parse_infix_expr(&mut self, prec: usize, inline: bool) -> ParseResult<Expr> {
@@ -505,6 +542,10 @@ impl<'a> ImpParser<'a> {
         self.skip_trivia();
       }
       while let Some(op) = self.peek_oper() {
+        // The logical operators have lower precedence than all the others.
+        if self.starts_with("&&") || self.starts_with("||") {
+          break;
+        }
         if op.precedence() == prec {
           self.try_parse_oper().unwrap();
           let rhs = self.parse_infix_expr(prec + 1, inline)?;
@@ -559,6 +600,8 @@ impl<'a> ImpParser<'a> {
         self.parse_open(indent)
       } else if self.starts_with_keyword("use") {
         self.parse_use(indent)
+      } else if self.starts_with_assert() {
+        self.parse_assert(indent)
       } else {
         self.parse_assign(indent)
       }
@@ -782,6 +825,8 @@ impl<'a> ImpParser<'a> {
     match (arg, self.starts_with("=")) {
       (Expr::Var { nam }, true) => {
         self.advance_one();
+        // The name binds the value instead of using a variable.
+        self.binders.extend(self.vars.pop());
         Ok((Some(nam), self.parse_expr(true, false)?))
       }
       (_, true) => self.expected_spanned("argument name", ini_idx..end_idx),
@@ -822,6 +867,8 @@ impl<'a> ImpParser<'a> {
       Some(nam)
     };
     self.skip_trivia_inline()?;
+    let guard = if self.try_parse_keyword("if") { Some(self.parse_expr(true, true)?) } else { None };
+    self.skip_trivia_inline()?;
     self.consume_exactly(":")?;
     self.consume_new_line()?;
     indent.enter_level();
@@ -830,7 +877,7 @@ impl<'a> ImpParser<'a> {
     let (body, nxt_indent) = self.parse_statement(indent)?;
     indent.exit_level();
 
-    let stmt = MatchArm { lft: pat, rgt: body };
+    let stmt = MatchArm { lft: pat, guard, rgt: body };
     Ok((stmt, nxt_indent))
   }
 
@@ -1089,7 +1136,7 @@ impl<'a> ImpParser<'a> {
     } else if self.starts_with("$") {
       self.advance_one();
       self.skip_trivia_inline()?;
-      let nam = self.parse_var_name()?;
+      let nam = self.parse_binder_name()?;
       Ok(AssignPattern::Chn(nam))
     } else if self.starts_with("(") {
       self.advance_one();
@@ -1097,7 +1144,7 @@ impl<'a> ImpParser<'a> {
       self.consume(")")?;
       Ok(assign)
     } else {
-      Ok(AssignPattern::Var(self.parse_var_name()?))
+      Ok(AssignPattern::Var(self.parse_binder_name()?))
     }
   }
 
@@ -1129,7 +1176,7 @@ impl<'a> ImpParser<'a> {
     self.parse_keyword("use")?;
     self.skip_trivia_inline()?;
 
-    let nam = self.parse_var_name()?;
+    let nam = self.parse_binder_name()?;
     self.skip_trivia_inline()?;
 
     self.consume_exactly("=")?;
@@ -1148,6 +1195,46 @@ impl<'a> ImpParser<'a> {
     Ok((stmt, nxt_indent))
   }
 
+  /// If the statement is an `assert`, and not an assignment to a variable named `assert`.
+  fn starts_with_assert(&mut self) -> bool {
+    if !self.starts_with_keyword("assert") {
+      return false;
+    }
+    let rest = self.input[*self.index() + "assert".len()..].trim_start_matches([' ', '\t']);
+    let is_assign = (rest.starts_with('=') && !rest.starts_with("=="))
+      || [",", "[", "<-", "+=", "-=", "*=", "/=", "&=", "|=", "^=", "@="]
+        .iter()
+        .any(|op| rest.starts_with(op));
+    !is_assign
+  }
+
+  /// "assert" <cond> ";"? <nxt>
+  /// The statements after it are only run if the condition is not 0,
+  /// otherwise the function returns a `Test/AssertError/Failed` with the location of the assert.
+  fn parse_assert(&mut self, indent: &mut Indent) -> ParseResult<(Stmt, Indent)> {
+    let loc = assert_location(&self.file, self.input, self.index);
+    self.parse_keyword("assert")?;
+    self.skip_trivia_inline()?;
+
+    let cond = self.parse_expr(true, true)?;
+    self.skip_trivia_inline()?;
+
+    self.try_consume_exactly(";");
+    self.consume_new_line()?;
+    self.consume_indent_exactly(*indent)?;
+
+    let (nxt, nxt_indent) = self.parse_statement(indent)?;
+
+    let failed = Expr::Call {
+      fun: Box::new(Expr::Var { nam: Name::new(ASSERT_FAILED) }),
+      args: vec![Expr::Str { val: STRINGS.get(loc) }],
+      kwargs: vec![],
+    };
+    let failed = Stmt::Return { term: Box::new(failed) };
+    let stmt = Stmt::If { cond: Box::new(cond), then: Box::new(nxt), otherwise: Box::new(failed), nxt: None };
+    Ok((stmt, nxt_indent))
+  }
+
   fn parse_local_def(&mut self, indent: &mut Indent) -> ParseResult<(Stmt, Indent)> {
     // TODO: checked vs unchecked functions
     let (mut def, mut nxt_indent) = self.parse_def_aux(*indent)?;
@@ -1279,12 +1366,12 @@ impl<'a> ImpParser<'a> {
 
     // Note: The source kind gets replaced later (generated if a local def, user otherwise)
     let source = Source::from_file_span(&self.file, self.input, ini_idx..self.index, self.builtin);
-    let def = Definition { name, args, typ, check, body, source };
+    let def = Definition { name, args, typ, check, body, source, names: RuleNames::default() };
     Ok((def, nxt_indent))
   }
 
   fn parse_def_arg(&mut self) -> ParseResult<(Name, Option<Type>)> {
-    let name = self.parse_var_name()?;
+    let name = self.parse_binder_name()?;
     self.skip_trivia_inline()?;
     if self.try_consume_exactly(":") {
       let typ = self.parse_type_expr()?;
@@ -1328,13 +1415,29 @@ impl<'a> ImpParser<'a> {
   }
 }
 
-impl<'a> ParserCommons<'a> for ImpParser<'a> {}
+impl<'a> ParserCommons<'a> for ImpParser<'a> {
+  fn add_binder(&mut self, name: &Name, span: Range<usize>) {
+    if !self.builtin {
+      self.binders.push((name.clone(), span));
+    }
+  }
+
+  fn add_var(&mut self, name: &Name, span: Range<usize>) {
+    if !self.builtin {
+      self.vars.push((name.clone(), span));
+    }
+  }
+}
 
 impl<'a> Parser<'a> for ImpParser<'a> {
   fn input(&mut self) -> &'a str {
     self.input
   }
 
+  fn parse_char(&mut self) -> ParseResult<char> {
+    self.parse_text_char()
+  }
+
   fn index(&mut self) -> &mut usize {
     &mut self.index
   }
@@ -1368,6 +1471,15 @@ impl<'a> Parser<'a> for ImpParser<'a> {
         continue;
       }
       if c == '#' {
+        if self.starts_with_directive() {
+          break;
+        }
+        self.advance_one();
+        if let Some('{') = self.peek_one() {
+          self.advance_one();
+          self.skip_block_comment();
+          continue;
+        }
         while let Some(c) = self.peek_one() {
           if c != '\n' {
             self.advance_one();
@@ -25,6 +25,7 @@ impl ParseBook {
   /// 3. Adding binds for aliases and old names in their respective definitions.
   /// 4. Converting the ParseBook into its functional form.
   /// 5. Perform any necessary post-processing.
+  #[tracing::instrument(level = "debug", skip_all)]
   pub fn load_imports(
     self,
     mut loader: impl PackageLoader,
@@ -87,7 +88,10 @@ impl ParseBook {
       package.apply_adts(&src, main_imports);
       package.apply_defs(&src, main_imports);
 
-      let Book { defs, hvm_defs, adts, .. } = package.to_fun()?;
+      let Book { defs, hvm_defs, adts, macros, deprecated, inline, .. } = package.to_fun()?;
+      self.macros.extend(macros);
+      self.deprecated.extend(deprecated);
+      self.inline.extend(inline);
 
       // Add the ADTs to the importing book,
       // saving the constructors names to be used when applying ADTs binds.
@@ -245,6 +249,16 @@ impl ParseBook {
       def.apply_binds(false, &canonical_map);
       def.source_mut().kind = SourceKind::Imported;
     }
+
+    self.macros = self.macros.iter().map(|nam| canonical_map.get(nam).unwrap_or(nam).clone()).collect();
+    self.deprecated = std::mem::take(&mut self.deprecated)
+      .into_iter()
+      .map(|(nam, msg)| (canonical_map.get(&nam).cloned().unwrap_or(nam), msg))
+      .collect();
+    self.inline = std::mem::take(&mut self.inline)
+      .into_iter()
+      .map(|(nam, inline)| (canonical_map.get(&nam).cloned().unwrap_or(nam), inline))
+      .collect();
   }
 }
 
@@ -423,7 +437,7 @@ impl Def for imp::Definition {
           }
         }
         Stmt::Match { arms, nxt, .. } => {
-          for MatchArm { lft: _, rgt } in arms {
+          for MatchArm { rgt, .. } in arms {
             subst_type_ctrs_stmt(rgt, from, to);
           }
           if let Some(nxt) = nxt {
@@ -446,7 +460,7 @@ impl Def for imp::Definition {
           }
         }
         Stmt::Fold { arms, nxt, .. } => {
-          for MatchArm { lft: _, rgt } in arms {
+          for MatchArm { rgt, .. } in arms {
             subst_type_ctrs_stmt(rgt, from, to);
           }
           if let Some(nxt) = nxt {
@@ -1,7 +1,11 @@
 use super::{loader::PackageLoader, normalize_path, BoundSource, ImportCtx, ImportType, ImportsMap};
 use crate::{
-  diagnostics::Diagnostics,
-  fun::{load_book::do_parse_book, parser::ParseBook, Name},
+  diagnostics::{Diagnostics, Severity, WarningType},
+  fun::{
+    load_book::do_parse_book,
+    parser::{OutOfOrderRules, ParseBook},
+    Name,
+  },
 };
 use indexmap::{IndexMap, IndexSet};
 use std::{cell::RefCell, collections::VecDeque, path::PathBuf};
@@ -15,11 +19,14 @@ pub struct Packages {
   pub loaded_adts: IndexMap<Name, IndexMap<Name, Vec<Name>>>,
   /// Queue of books indexes that still needs to load its imports.
   load_queue: VecDeque<usize>,
+  /// The `cfg` flags of the main book, that also apply to the imported ones.
+  cfg: IndexSet<Name>,
 }
 
 impl Packages {
   pub fn new(book: ParseBook) -> Self {
     Self {
+      cfg: book.cfg.clone(),
       books: IndexMap::from([(book.source.clone(), book.into())]),
       load_queue: VecDeque::new(),
       loaded_adts: IndexMap::new(),
@@ -28,22 +35,32 @@ impl Packages {
 
   /// Loads each import statement recursively into a Source -> ParseBook map.
   /// Inserts into the ImportsMap of each book all the imported names.
+  ///
+  /// The imports are loaded one level at a time, and the files of each level are parsed in parallel.
   pub fn load_imports(
     &mut self,
     loader: &mut impl PackageLoader,
     diag: &mut Diagnostics,
   ) -> Result<ParseBook, Diagnostics> {
-    self.load_imports_go(0, None, loader)?;
-    while let Some(idx) = self.load_queue.pop_front() {
-      let parent_dir = {
-        let book = self.books[idx].borrow();
-        book.source.rsplit_once('/').map(|(s, _)| Name::new(s))
-      };
-      self.load_imports_go(idx, parent_dir, loader)?;
+    let sources = self.load_imports_go(0, None, loader)?;
+    self.parse_sources(sources)?;
+    while !self.load_queue.is_empty() {
+      let mut sources = IndexMap::new();
+      while let Some(idx) = self.load_queue.pop_front() {
+        let parent_dir = {
+          let book = self.books[idx].borrow();
+          book.source.rsplit_once('/').map(|(s, _)| Name::new(s))
+        };
+        sources.extend(self.load_imports_go(idx, parent_dir, loader)?);
+      }
+      self.parse_sources(sources)?;
     }
 
     for idx in 0..self.books.len() {
       self.load_binds(idx, diag);
+      self.report_rules_out_of_order(idx, diag);
+      self.report_redefinitions(idx, diag);
+      self.report_keyword_names(idx, diag);
     }
 
     let (_, book) = self.books.swap_remove_index(0).unwrap();
@@ -51,34 +68,55 @@ impl Packages {
     diag.fatal(book.into_inner())
   }
 
+  /// Loads the sources of the imports of a book, without parsing them.
   fn load_imports_go(
     &mut self,
     idx: usize,
     dir: Option<Name>,
     loader: &mut impl PackageLoader,
-  ) -> Result<(), Diagnostics> {
+  ) -> Result<IndexMap<Name, String>, Diagnostics> {
     let mut sources = IndexMap::new();
 
-    {
-      let mut book = self.books[idx].borrow_mut();
-      let names = &mut book.import_ctx.imports;
-
-      for import in names {
-        if import.relative {
-          if let Some(ref dir) = dir {
-            let path = format!("{}/{}", dir, import.path);
-            let normalized = normalize_path(&PathBuf::from(path));
-            import.path = Name::new(normalized.to_string_lossy());
-          }
-        }
+    let mut book = self.books[idx].borrow_mut();
+    let names = &mut book.import_ctx.imports;
 
-        let loaded = loader.load(import)?;
-        sources.extend(loaded);
+    for import in names {
+      if import.relative {
+        if let Some(ref dir) = dir {
+          let path = format!("{}/{}", dir, import.path);
+          let normalized = normalize_path(&PathBuf::from(path));
+          import.path = Name::new(normalized.to_string_lossy());
+        }
       }
+
+      let loaded = loader.load(import)?;
+      sources.extend(loaded);
     }
 
-    for (psrc, code) in sources {
-      let module = do_parse_book(&code, &PathBuf::from(psrc.as_ref()), ParseBook::default())?;
+    Ok(sources)
+  }
+
+  /// Parses the loaded sources, each file in its own thread,
+  /// and queues the new books to have their imports loaded.
+  #[tracing::instrument(level = "debug", skip_all)]
+  fn parse_sources(&mut self, sources: IndexMap<Name, String>) -> Result<(), Diagnostics> {
+    let parse = |psrc: &Name, code: &str| {
+      let book = ParseBook { cfg: self.cfg.clone(), ..Default::default() };
+      do_parse_book(code, &PathBuf::from(psrc.as_ref()), book)
+    };
+
+    let modules = if sources.len() > 1 {
+      std::thread::scope(|s| {
+        let handles = sources.iter().map(|(psrc, code)| s.spawn(|| parse(psrc, code))).collect::<Vec<_>>();
+        handles.into_iter().map(|handle| handle.join().unwrap()).collect::<Vec<_>>()
+      })
+    } else {
+      sources.iter().map(|(psrc, code)| parse(psrc, code)).collect()
+    };
+
+    // Insert in the order the imports were found, so the result doesn't depend on the scheduling.
+    for (psrc, module) in sources.into_keys().zip(modules) {
+      let module = module?;
       self.load_queue.push_back(self.books.len());
       self.books.insert(psrc, module.into());
     }
@@ -86,6 +124,48 @@ impl Packages {
     Ok(())
   }
 
+  /// Reports the rules of the functions of a book that were found after other definitions.
+  ///
+  /// The rules are gathered into the function, unless only redefinitions are allowed,
+  /// in which case they're a redefinition that replaces the function.
+  fn report_rules_out_of_order(&mut self, idx: usize, diag: &mut Diagnostics) {
+    let book = &mut self.books[idx].borrow_mut();
+    let is_denied = |warn| diag.config.warning_severity(warn) == Severity::Error;
+    let is_redefinition = is_denied(WarningType::RulesOutOfOrder) && !is_denied(WarningType::Redefinition);
+    for OutOfOrderRules { def, out_of_order_msg, redefinition_msg } in
+      std::mem::take(&mut book.rules_out_of_order)
+    {
+      let (name, source) = (def.name.clone(), def.source.clone());
+      if is_redefinition {
+        book.fun_defs.shift_remove(&name);
+        book.fun_defs.insert(name.clone(), def);
+        diag.add_function_warning(redefinition_msg, WarningType::Redefinition, name, source);
+      } else {
+        if let Some(prev) = book.fun_defs.get_mut(&name) {
+          prev.rules.extend(def.rules);
+          prev.names.extend(def.names);
+        }
+        diag.add_function_warning(out_of_order_msg, WarningType::RulesOutOfOrder, name, source);
+      }
+    }
+  }
+
+  /// Reports the functions of a book that were defined more than once.
+  fn report_redefinitions(&mut self, idx: usize, diag: &mut Diagnostics) {
+    let book = &mut self.books[idx].borrow_mut();
+    for (name, msg, source) in std::mem::take(&mut book.redefinitions) {
+      diag.add_function_warning(msg, WarningType::Redefinition, name, source);
+    }
+  }
+
+  /// Reports the names of a book that are reserved keywords.
+  fn report_keyword_names(&mut self, idx: usize, diag: &mut Diagnostics) {
+    let book = &mut self.books[idx].borrow_mut();
+    for (msg, source) in std::mem::take(&mut book.keyword_names) {
+      diag.add_parsing_warning(msg, WarningType::KeywordName, source);
+    }
+  }
+
   /// Maps the `ImportType` of each import to the top level names it relates,
   /// checks if it is valid, resolves `BoundSource::Either`, and adds to the book ImportMap.
   fn load_binds(&mut self, idx: usize, diag: &mut Diagnostics) {
@@ -111,7 +191,7 @@ impl Packages {
 
             if !added {
               if !self.unique_top_level_names(src).contains(name) {
-                let err = format!("Package '{src}' does not contain the top level name '{name}'");
+                let err = self.missing_name_err(src, name);
                 diag.add_book_error(err);
                 continue;
               }
@@ -153,7 +233,7 @@ impl Packages {
 
         (BoundSource::File(src), ImportType::Single(name, alias)) => {
           if !self.unique_top_level_names(src).contains(name) {
-            let err = format!("Package '{src}' does not contain the top level name '{name}'");
+            let err = self.missing_name_err(src, name);
             diag.add_book_error(err);
             continue;
           }
@@ -167,7 +247,7 @@ impl Packages {
 
           for (sub, _) in names {
             if !src_names.contains(sub) {
-              let err = format!("Package '{src}' does not contain the top level name '{sub}'");
+              let err = self.missing_name_err(src, sub);
               diag.add_book_error(err);
               error = true;
             }
@@ -248,8 +328,17 @@ impl Packages {
     }
   }
 
+  /// The names that other books can import from a book, which are all its non-private top level names.
   fn unique_top_level_names(&self, src: &Name) -> IndexSet<Name> {
     let bound_book = self.books.get(src).unwrap().borrow();
-    bound_book.top_level_names().cloned().collect()
+    bound_book.top_level_names().filter(|name| !bound_book.private_defs.contains(*name)).cloned().collect()
+  }
+
+  fn missing_name_err(&self, src: &Name, name: &Name) -> String {
+    if self.books.get(src).unwrap().borrow().private_defs.contains(name) {
+      format!("Package '{src}' does not export the private definition '{name}'")
+    } else {
+      format!("Package '{src}' does not contain the top level name '{name}'")
+    }
   }
 }
@@ -1,10 +1,13 @@
 use crate::{
-  fun::{book_to_hvm, net_to_term::net_to_term, term_to_net::Labels, Book, Ctx, Term},
+  fun::{
+    book_to_hvm, net_to_term::net_to_term, term_to_net::Labels, transform::desugar_assert::ASSERT_FAILED_CTR,
+    Book, Ctx, Definition, Name, Rule, Term,
+  },
   hvm::{
     add_recursive_priority::add_recursive_priority,
-    check_net_size::{check_net_sizes, MAX_NET_SIZE_CUDA},
+    check_net_size::{check_net_sizes, count_nodes, MAX_NET_SIZE_CUDA},
     eta_reduce::eta_reduce_hvm_net,
-    hvm_book_show_pretty,
+    hvm_book_show_pretty, hvm_def_show_pretty,
     inline::inline_hvm_book,
     mutual_recursion,
     prune::prune_hvm_book,
@@ -12,6 +15,7 @@ use crate::{
 };
 use diagnostics::{Diagnostics, DiagnosticsConfig, ERR_INDENT_SIZE};
 use net::hvm_to_net::hvm_to_net;
+use std::{collections::HashMap, path::PathBuf, time::Duration};
 
 pub mod diagnostics;
 // `Name` triggers this warning, but it's safe because we're not using its internal mutability.
@@ -23,12 +27,34 @@ pub mod imports;
 pub mod net;
 mod utils;
 
-pub use fun::load_book::{load_file_to_book, load_to_book};
+pub use fun::load_book::{
+  load_file_to_book, load_file_to_book_with_opts, load_to_book, load_to_book_with_opts,
+};
 
 pub const ENTRY_POINT: &str = "main";
 pub const HVM1_ENTRY_POINT: &str = "Main";
 pub const HVM_OUTPUT_END_MARKER: &str = "Result: ";
 
+/// Runs every check of the compiler on a book, without running it.
+///
+/// This is the single entry point for the checks, so the CLI, editors and embedders all report the same problems.
+/// The checks of the imports and of the syntax are done when loading the book.
+/// Returns the warnings, or all the diagnostics if there was an error.
+///
+/// ```
+/// # use bend::{check_book, fun::Name, load_to_book, imports::DefaultLoader, CompileOpts};
+/// # use bend::diagnostics::{DiagnosticsConfig, DiagnosticOrigin, Severity};
+/// # use std::path::Path;
+/// let path = Path::new("program.bend");
+/// let code = "def unused():\n  return 0\n\ndef main():\n  return 1\n";
+/// let cfg = DiagnosticsConfig::default();
+/// let mut book = load_to_book(path, code, DefaultLoader::new(path), cfg).unwrap();
+///
+/// let diagnostics = check_book(&mut book, cfg, CompileOpts::default()).unwrap();
+/// let (origin, diagnostic) = diagnostics.iter().next().unwrap();
+/// assert_eq!(origin, &DiagnosticOrigin::Function(Name::new("unused")));
+/// assert_eq!(diagnostic.severity, Severity::Warning);
+/// ```
 pub fn check_book(
   book: &mut Book,
   diagnostics_cfg: DiagnosticsConfig,
@@ -39,6 +65,7 @@ pub fn check_book(
   Ok(res.diagnostics)
 }
 
+#[tracing::instrument(level = "info", skip_all)]
 pub fn compile_book(
   book: &mut Book,
   opts: CompileOpts,
@@ -49,37 +76,90 @@ pub fn compile_book(
 
   let (mut hvm_book, labels) = book_to_hvm(book, &mut diagnostics)?;
 
+  tracing::debug!(defs = hvm_book.defs.len(), nodes = count_book_nodes(&hvm_book), "generated hvm book");
+
   if opts.eta {
+    let _span = tracing::debug_span!("eta_reduce").entered();
     hvm_book.defs.values_mut().for_each(eta_reduce_hvm_net);
   }
 
   mutual_recursion::check_cycles(&hvm_book, &mut diagnostics)?;
 
   if opts.eta {
+    let _span = tracing::debug_span!("eta_reduce").entered();
     hvm_book.defs.values_mut().for_each(eta_reduce_hvm_net);
   }
 
   if opts.inline {
-    if let Err(e) = inline_hvm_book(&mut hvm_book) {
+    let attrs = book.inline.iter().map(|(name, inline)| (name.to_string(), *inline)).collect();
+    if let Err(e) = inline_hvm_book(&mut hvm_book, &attrs) {
       diagnostics.add_book_error(format!("During inlining:\n{:ERR_INDENT_SIZE$}{}", "", e));
     }
     diagnostics.fatal(())?;
   }
 
   if opts.prune {
-    let prune_entrypoints = vec![book.hvm_entrypoint().to_string()];
+    let prune_entrypoints = vec![ENTRY_POINT.to_string()];
     prune_hvm_book(&mut hvm_book, &prune_entrypoints);
   }
 
   if opts.check_net_size {
-    check_net_sizes(&hvm_book, &mut diagnostics, &opts.target_architecture)?;
+    check_net_sizes(&hvm_book, &mut diagnostics, &opts.target_architecture, opts.limits.max_net_size)?;
   }
 
   add_recursive_priority(&mut hvm_book);
 
+  tracing::debug!(defs = hvm_book.defs.len(), nodes = count_book_nodes(&hvm_book), "optimized hvm book");
+
   Ok(CompileResult { hvm_book, labels, diagnostics })
 }
 
+/// Compiles a book and shows the HVM nets of one of its definitions and of the definitions generated from it,
+/// like the combinators floated out of it.
+///
+/// Each net is preceded by the location of its definition and by the term that was compiled to it, after desugaring.
+/// The annotations are per definition, the nodes of a net aren't traced back to the parts of the term they come from.
+pub fn disassemble_def(
+  book: &mut Book,
+  def_name: &Name,
+  opts: CompileOpts,
+  diagnostics_cfg: DiagnosticsConfig,
+) -> Result<(String, Diagnostics), Diagnostics> {
+  if !book.defs.contains_key(def_name) && !book.hvm_defs.contains_key(def_name) {
+    return Err(format!("There's no definition named '{def_name}'.").into());
+  }
+
+  let CompileResult { hvm_book, diagnostics, .. } = compile_book(book, opts, diagnostics_cfg, None)?;
+
+  let parent = book.defs.get(def_name).map(|def| def.source.clone());
+  let mut out = String::new();
+  for (nam, net) in hvm_book.defs.iter() {
+    let def = book.defs.get(&Name::new(nam));
+    let generated =
+      matches!((def, &parent), (Some(def), Some(parent)) if def.source.is_generated_from(parent));
+    if nam != def_name.as_ref() && !generated {
+      continue;
+    }
+    if let Some(def) = def {
+      if let (Some(file), Some(span)) = (&def.source.file, &def.source.span) {
+        out.push_str(&format!("// {}:{}:{}\n", file, span.start.line + 1, span.start.char + 1));
+      }
+      for rule in def.rules.iter() {
+        out.push_str(&format!("// {}\n", rule.display(&def.name)));
+      }
+    }
+    out.push_str(&hvm_def_show_pretty(nam, net));
+  }
+
+  if out.is_empty() {
+    return Err(
+      format!("Definition '{def_name}' was removed during compilation, since it's unused or inlined.").into(),
+    );
+  }
+  Ok((out, diagnostics))
+}
+
+#[tracing::instrument(level = "info", skip_all, fields(defs = book.defs.len()))]
 pub fn desugar_book(
   book: &mut Book,
   opts: CompileOpts,
@@ -88,29 +168,55 @@ pub fn desugar_book(
 ) -> Result<Diagnostics, Diagnostics> {
   let mut ctx = Ctx::new(book, diagnostics_cfg);
 
+  ctx.check_limits(&opts.limits)?;
+
   ctx.check_shared_names();
 
   ctx.set_entrypoint();
 
+  if opts.prune_imports {
+    ctx.book.prune_imports(args.as_deref());
+  }
+
   ctx.book.encode_adts(opts.adt_encoding);
 
+  ctx.derive_equality()?;
+
   ctx.fix_match_defs()?;
 
   ctx.apply_args(args)?;
 
   ctx.desugar_open()?;
 
+  ctx.expand_macros(opts.limits.max_macro_depth)?;
+
+  ctx.desugar_binder_patterns()?;
+
   ctx.book.encode_builtins();
 
   ctx.resolve_refs()?;
 
-  ctx.desugar_match_defs()?;
+  ctx.check_deprecated_refs();
+
+  ctx.check_division_by_zero()?;
+
+  ctx.check_bool_operands()?;
+
+  match opts.pattern_compilation {
+    PatternCompilation::DecisionTree => ctx.desugar_match_defs()?,
+    PatternCompilation::Backtracking => ctx.desugar_match_defs_backtracking()?,
+  }
 
   ctx.fix_match_terms()?;
 
   ctx.book.lift_local_defs();
 
+  ctx.desugar_assert();
+
+  ctx.desugar_quote()?;
+
   ctx.desugar_bend()?;
+  ctx.desugar_fix()?;
   ctx.desugar_fold()?;
   ctx.desugar_with_blocks()?;
 
@@ -132,6 +238,18 @@ pub fn desugar_book(
     type_check_book(&mut ctx)?;
   }
 
+  if opts.division_by_zero == DivisionByZero::Zero {
+    ctx.book.guard_division_by_zero();
+  }
+
+  if opts.fold_constants {
+    ctx.book.fold_constants();
+  }
+
+  if opts.coverage {
+    ctx.book.lift_match_arms();
+  }
+
   ctx.book.encode_matches(opts.adt_encoding);
 
   // sanity check
@@ -140,6 +258,10 @@ pub fn desugar_book(
   ctx.book.make_var_names_unique();
   ctx.book.desugar_use();
 
+  if opts.collapse_redexes {
+    ctx.book.collapse_redexes();
+  }
+
   ctx.book.make_var_names_unique();
   ctx.book.linearize_vars();
 
@@ -154,14 +276,22 @@ pub fn desugar_book(
 
   // Optimizing passes
   ctx.prune(opts.prune);
+  // Measuring coverage needs every definition to be called by its own name.
+  if !opts.coverage {
+    ctx.book.resolve_aliases();
+  }
   if opts.merge {
     ctx.book.merge_definitions();
   }
 
-  ctx.book.expand_main();
+  if !opts.coverage {
+    ctx.book.expand_main();
+  }
 
   ctx.book.make_var_names_unique();
 
+  tracing::debug!(defs = ctx.book.defs.len(), "desugared book");
+
   if !ctx.info.has_errors() {
     Ok(ctx.info)
   } else {
@@ -177,13 +307,26 @@ pub fn type_check_book(ctx: &mut Ctx) -> Result<(), Diagnostics> {
 }
 
 pub fn run_book(
-  mut book: Book,
+  book: Book,
   run_opts: RunOpts,
   compile_opts: CompileOpts,
   diagnostics_cfg: DiagnosticsConfig,
   args: Option<Vec<Term>>,
   cmd: &str,
 ) -> Result<Option<(Term, String, Diagnostics)>, Diagnostics> {
+  let res = run_book_with_net(book, run_opts, compile_opts, diagnostics_cfg, args, cmd)?;
+  Ok(res.map(|(term, _, stats, diags)| (term, stats, diags)))
+}
+
+/// Runs a book like [run_book], also returning the net that HVM returned, before it was read back.
+pub fn run_book_with_net(
+  mut book: Book,
+  run_opts: RunOpts,
+  compile_opts: CompileOpts,
+  diagnostics_cfg: DiagnosticsConfig,
+  args: Option<Vec<Term>>,
+  cmd: &str,
+) -> Result<Option<(Term, ::hvm::ast::Net, String, Diagnostics)>, Diagnostics> {
   let CompileResult { hvm_book: core_book, labels, diagnostics } =
     compile_book(&mut book, compile_opts.clone(), diagnostics_cfg, args)?;
 
@@ -194,10 +337,207 @@ pub fn run_book(
 
   let out = run_hvm(&core_book, cmd, &run_opts)?;
   let (net, stats) = parse_hvm_output(&out)?;
-  let (term, diags) =
-    readback_hvm_net(&net, &book, &labels, run_opts.linear_readback, compile_opts.adt_encoding);
+  let (term, diags) = readback_hvm_net_with_opts(&net, &book, &labels, &run_opts, compile_opts.adt_encoding);
+
+  Ok(Some((term, net, stats, diags)))
+}
+
+/// Runs a program with the Rust runtime of HVM, measuring which of its definitions and match arms were executed.
+///
+/// The match arms are moved to their own definitions, see [fun::transform::lift_match_arms],
+/// so an arm is counted as taken when the runtime expands its definition.
+/// The HVM binary isn't used, so programs that do IO can't be measured.
+pub fn coverage_book(
+  mut book: Book,
+  run_opts: RunOpts,
+  compile_opts: CompileOpts,
+  diagnostics_cfg: DiagnosticsConfig,
+  args: Option<Vec<Term>>,
+) -> Result<(Term, Coverage, Diagnostics), Diagnostics> {
+  let user_defs =
+    book.defs.values().filter(|def| def.source.is_local()).map(|def| def.name.clone()).collect::<Vec<_>>();
+
+  // Merging or inlining definitions would hide which of them were called.
+  let compile_opts = compile_opts.set_coverage(true).set_merge(false).set_inline(false);
+  let CompileResult { hvm_book: core_book, labels, diagnostics } =
+    compile_book(&mut book, compile_opts.clone(), diagnostics_cfg, args)?;
+  eprint!("{diagnostics}");
+
+  let (net, calls) = hvm::coverage::run_with_coverage(&core_book, ENTRY_POINT)?;
+  let (term, diags) = readback_hvm_net_with_opts(&net, &book, &labels, &run_opts, compile_opts.adt_encoding);
+
+  let calls = calls.into_iter().collect::<HashMap<String, u64>>();
+  let n_calls = |name: &Name| calls.get(name.as_ref()).copied().unwrap_or(0);
+  let defs = user_defs
+    .into_iter()
+    .map(|name| {
+      let arms = book
+        .lifted_arms
+        .iter()
+        .filter(|(_, arm)| arm.def == name)
+        .map(|(arm_def, arm)| (arm.desc.clone(), n_calls(arm_def)))
+        .collect();
+      DefCoverage { calls: n_calls(&name), name, arms }
+    })
+    .collect();
+
+  Ok((term, Coverage { defs }, diags))
+}
+
+/// Which definitions and match arms of a program were executed by a run of [coverage_book].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Coverage {
+  /// The definitions of the program, in the order they were defined.
+  pub defs: Vec<DefCoverage>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DefCoverage {
+  pub name: Name,
+  /// How many times the definition was called.
+  pub calls: u64,
+  /// The arms of the matches of the definition, with how many times each was taken.
+  pub arms: Vec<(String, u64)>,
+}
+
+impl std::fmt::Display for Coverage {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    writeln!(f, "Coverage:")?;
+    for def in &self.defs {
+      if def.calls == 0 {
+        writeln!(f, "- {}: never called", def.name)?;
+        continue;
+      }
+      let times = if def.calls == 1 { "time" } else { "times" };
+      write!(f, "- {}: called {} {times}", def.name, def.calls)?;
+      if !def.arms.is_empty() {
+        let taken = def.arms.iter().filter(|(_, n)| *n > 0).count();
+        write!(f, ", {taken} of {} match arms taken", def.arms.len())?;
+      }
+      writeln!(f)?;
+      for (arm, _) in def.arms.iter().filter(|(_, n)| *n == 0) {
+        writeln!(f, "  not taken: {arm}")?;
+      }
+    }
+    Ok(())
+  }
+}
 
-  Ok(Some((term, stats, diags)))
+/// The outcome of running a definition marked with `#[test]`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TestResult {
+  Pass,
+  /// The test failed, with a message describing its result.
+  Fail(String),
+}
+
+/// Runs every definition marked with `#[test]` in the book, in the order they were defined.
+///
+/// A test passes if its result is a number other than 0, or a `Test/Expect` whose `actual` and `expected` fields are equal.
+/// Tests can't have parameters, and since each test is run as the `main` function, the `main` of the book isn't loaded.
+/// A test that makes the runtime fail is reported as failed, with the error of the runtime as its message.
+/// Each result comes with the time it took to compile and run the test.
+pub fn test_book(
+  book: Book,
+  run_opts: RunOpts,
+  compile_opts: CompileOpts,
+  diagnostics_cfg: DiagnosticsConfig,
+  cmd: &str,
+) -> Result<Vec<(Name, TestResult, Duration)>, Diagnostics> {
+  let mut diagnostics = Diagnostics::new(diagnostics_cfg);
+  for test in book.tests.iter() {
+    let def = &book.defs[test];
+    if def.rules.iter().any(|rule| !rule.pats.is_empty()) {
+      diagnostics.add_function_error(
+        "Test definitions can't have parameters.",
+        test.clone(),
+        def.source.clone(),
+      );
+    }
+  }
+  diagnostics.fatal(())?;
+
+  let mut results = vec![];
+  for test in book.tests.iter() {
+    // HVM can only run the `main` definition, so it's replaced by one that returns the test.
+    let mut test_book = book.clone();
+    test_book.entrypoint = None;
+    test_book.defs.shift_remove(&Name::new(HVM1_ENTRY_POINT));
+    if test != ENTRY_POINT {
+      let rule = Rule { pats: vec![], body: Term::Ref { nam: test.clone() } };
+      let main =
+        Definition::new_gen(Name::new(ENTRY_POINT), vec![rule], book.defs[test].source.clone(), false);
+      test_book.defs.insert(main.name.clone(), main);
+    }
+    let start = std::time::Instant::now();
+    let result = run_test(test_book, &run_opts, compile_opts.clone(), diagnostics_cfg, cmd)?;
+    results.push((test.clone(), result, start.elapsed()));
+  }
+  Ok(results)
+}
+
+/// Compiles and runs the book of a single test.
+///
+/// An error of the runtime, like HVM crashing, fails only this test, while a compilation error stops all of them.
+fn run_test(
+  mut book: Book,
+  run_opts: &RunOpts,
+  compile_opts: CompileOpts,
+  diagnostics_cfg: DiagnosticsConfig,
+  cmd: &str,
+) -> Result<TestResult, Diagnostics> {
+  let CompileResult { hvm_book: core_book, labels, diagnostics } =
+    compile_book(&mut book, compile_opts.clone(), diagnostics_cfg, None)?;
+  eprint!("{diagnostics}");
+
+  let net = match run_hvm(&core_book, cmd, run_opts).and_then(|out| parse_hvm_output(&out)) {
+    Ok((net, _)) => net,
+    Err(msg) => return Ok(TestResult::Fail(msg)),
+  };
+  let (term, _) = readback_hvm_net_with_opts(&net, &book, &labels, run_opts, compile_opts.adt_encoding);
+  Ok(judge_test(&term, &book))
+}
+
+/// Judges the result of a test, which must be a truthy number or a `Test/Expect` of two equal values.
+fn judge_test(term: &Term, book: &Book) -> TestResult {
+  if let Some(msg) = find_failed_assert(term, book) {
+    return TestResult::Fail(msg);
+  }
+  match term {
+    Term::Num { val: fun::Num::U24(0) | fun::Num::I24(0) } => TestResult::Fail("Returned 0.".to_string()),
+    Term::Num { val: fun::Num::F24(val) } if *val == 0.0 => TestResult::Fail("Returned 0.0.".to_string()),
+    Term::Num { .. } => TestResult::Pass,
+    _ => match term.as_num_scott_ctr(book) {
+      Some((ctr, fields)) if ctr == "Test/Expect" => {
+        let (actual, expected) = (fields[0].1, fields[1].1);
+        if actual.to_string() == expected.to_string() {
+          TestResult::Pass
+        } else {
+          TestResult::Fail(format!("Expected {expected}, but got {actual}."))
+        }
+      }
+      _ => TestResult::Fail(format!("Returned {term}, which is not a number or a `Test/Expect`.")),
+    },
+  }
+}
+
+/// Finds a `Test/AssertError/Failed` value in the result of a program, returning a message that describes it.
+///
+/// Only values of the num-scott encoding can be found, since they're the ones that carry their constructor.
+pub fn find_failed_assert(term: &Term, book: &Book) -> Option<String> {
+  maybe_grow(|| {
+    if let Some((ctr, fields)) = term.as_num_scott_ctr(book) {
+      if ctr == ASSERT_FAILED_CTR {
+        let (def, loc) = (fields[0].1, fields[1].1);
+        let show = |term: &Term| match term {
+          Term::Str { val } => val.to_string(),
+          term => term.to_string(),
+        };
+        return Some(format!("Assertion failed in definition '{}', at {}.", show(def), show(loc)));
+      }
+    }
+    term.children().find_map(|child| find_failed_assert(child, book))
+  })
 }
 
 pub fn readback_hvm_net(
@@ -206,19 +546,38 @@ pub fn readback_hvm_net(
   labels: &Labels,
   linear: bool,
   adt_encoding: AdtEncoding,
+) -> (Term, Diagnostics) {
+  let run_opts = RunOpts::default().set_linear_readback(linear);
+  readback_hvm_net_with_opts(net, book, labels, &run_opts, adt_encoding)
+}
+
+/// Reads back a net like [readback_hvm_net], with how the references are expanded
+/// and whether the data is resugared taken from the options.
+#[tracing::instrument(level = "info", skip_all)]
+pub fn readback_hvm_net_with_opts(
+  net: &::hvm::ast::Net,
+  book: &Book,
+  labels: &Labels,
+  run_opts: &RunOpts,
+  adt_encoding: AdtEncoding,
 ) -> (Term, Diagnostics) {
   let mut diags = Diagnostics::default();
   let net = hvm_to_net(net);
-  let mut term = net_to_term(&net, book, labels, linear, &mut diags);
+  let mut term = net_to_term(&net, book, labels, run_opts.linear_readback, &mut diags);
   #[allow(clippy::mutable_key_type)] // Safe to allow, we know how `Name` works.
   let recursive_defs = book.recursive_defs();
+  term.expand_refs(book, run_opts.expand_refs);
   term.expand_generated(book, &recursive_defs);
-  term.resugar_strings(adt_encoding);
-  term.resugar_lists(adt_encoding);
+  term.resugar_failed_asserts(book, adt_encoding);
+  if run_opts.output != OutputFormat::Term {
+    term.resugar_strings(adt_encoding);
+    term.resugar_lists(adt_encoding);
+  }
   (term, diags)
 }
 
 /// Runs an HVM book by invoking HVM as a subprocess.
+#[tracing::instrument(level = "info", skip_all, fields(cmd = %cmd))]
 fn run_hvm(book: &::hvm::ast::Book, cmd: &str, run_opts: &RunOpts) -> Result<String, String> {
   let out_path = ".out.hvm";
   std::fs::write(out_path, hvm_book_show_pretty(book)).map_err(|x| x.to_string())?;
@@ -314,11 +673,175 @@ pub struct RunOpts {
   pub linear_readback: bool,
   pub pretty: bool,
   pub hvm_path: String,
+  pub output: OutputFormat,
+  /// How many nested levels of references to definitions are expanded in the result.
+  pub expand_refs: usize,
+}
+
+/// How the result of running a program is shown.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+  /// The net returned by HVM, without reading it back.
+  RawNet,
+  /// The lambda term read back from the net, without resugaring the data in it.
+  Term,
+  /// The term read back from the net, with strings and lists resugared.
+  #[default]
+  Data,
+  /// The resugared term as a JSON value, also showing the constructors of num-scott encoded data.
+  Json,
+}
+
+/// Builder methods, so the options can be set one by one starting from the defaults.
+///
+/// ```
+/// # use bend::RunOpts;
+/// let opts = RunOpts::default().set_pretty(true).set_hvm_path("./hvm".to_string());
+/// assert!(opts.pretty);
+/// ```
+impl RunOpts {
+  /// Enables or disables the linear readback of the result, which doesn't expand duplications.
+  #[must_use]
+  pub fn set_linear_readback(self, linear_readback: bool) -> Self {
+    Self { linear_readback, ..self }
+  }
+
+  /// Enables or disables pretty printing the result.
+  #[must_use]
+  pub fn set_pretty(self, pretty: bool) -> Self {
+    Self { pretty, ..self }
+  }
+
+  /// Sets the HVM command or path to the HVM binary used to run the program.
+  #[must_use]
+  pub fn set_hvm_path(self, hvm_path: String) -> Self {
+    Self { hvm_path, ..self }
+  }
+
+  /// Sets how the result of the program is shown.
+  #[must_use]
+  pub fn set_output(self, output: OutputFormat) -> Self {
+    Self { output, ..self }
+  }
+
+  /// Sets how many nested levels of references to definitions are expanded in the result,
+  /// 0 to show them all as references.
+  #[must_use]
+  pub fn set_expand_refs(self, expand_refs: usize) -> Self {
+    Self { expand_refs, ..self }
+  }
 }
 
 impl Default for RunOpts {
   fn default() -> Self {
-    RunOpts { linear_readback: false, pretty: false, hvm_path: "hvm".to_string() }
+    RunOpts {
+      linear_readback: false,
+      pretty: false,
+      hvm_path: "hvm".to_string(),
+      output: OutputFormat::Data,
+      expand_refs: 0,
+    }
+  }
+}
+
+/// Options for loading a program from its files.
+#[derive(Clone, Debug, Default)]
+pub struct LoadOpts {
+  /// The flags that enable the definitions marked with `#[cfg(...)]`.
+  pub cfg: Vec<Name>,
+  /// The definitions that are loaded before the program.
+  pub prelude: Prelude,
+}
+
+/// The standard environment of a program, with the builtin types and functions.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum Prelude {
+  /// The builtins shipped with Bend.
+  #[default]
+  Builtin,
+  /// No builtins, only the definitions of the program.
+  None,
+  /// The definitions of the given file, which replace the builtins shipped with Bend.
+  File(PathBuf),
+}
+
+/// Builder methods, so the options can be set one by one starting from the defaults.
+///
+/// ```
+/// # use bend::{LoadOpts, Prelude, fun::Name};
+/// let opts = LoadOpts::default().set_cfg(vec![Name::new("debug")]).set_prelude(Prelude::None);
+/// assert_eq!(opts.prelude, Prelude::None);
+/// ```
+impl LoadOpts {
+  /// Sets the flags that enable the definitions marked with `#[cfg(...)]`.
+  #[must_use]
+  pub fn set_cfg(self, cfg: Vec<Name>) -> Self {
+    Self { cfg, ..self }
+  }
+
+  /// Sets the definitions that are loaded before the program.
+  #[must_use]
+  pub fn set_prelude(self, prelude: Prelude) -> Self {
+    Self { prelude, ..self }
+  }
+}
+
+/// Structural limits on the programs accepted by the compiler.
+///
+/// A program that goes over a limit is rejected with an error diagnostic,
+/// which makes it possible to bound the work done when compiling untrusted programs.
+/// A limit of `None` means no limit, except for the net size, which defaults to the maximum of the target,
+/// and the macro depth, which defaults to 256.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Limits {
+  /// Maximum nesting depth of the body of a definition.
+  pub max_term_depth: Option<usize>,
+  /// Maximum number of definitions in the program, including the builtin and imported ones.
+  pub max_book_size: Option<usize>,
+  /// Maximum nesting depth of the patterns of rules, lambdas and lets.
+  pub max_pattern_depth: Option<usize>,
+  /// Maximum number of nodes of each generated HVM definition. Checked when `check_net_size` is enabled.
+  pub max_net_size: Option<usize>,
+  /// Maximum number of macro calls nested inside the expansion of another one.
+  pub max_macro_depth: Option<usize>,
+}
+
+/// Builder methods, so the limits can be set one by one starting from no limits.
+///
+/// ```
+/// # use bend::Limits;
+/// let limits = Limits::default().set_max_term_depth(Some(1000)).set_max_book_size(Some(500));
+/// assert_eq!(limits.max_pattern_depth, None);
+/// ```
+impl Limits {
+  /// Sets the maximum nesting depth of the body of a definition.
+  #[must_use]
+  pub fn set_max_term_depth(self, max_term_depth: Option<usize>) -> Self {
+    Self { max_term_depth, ..self }
+  }
+
+  /// Sets the maximum number of definitions in the program.
+  #[must_use]
+  pub fn set_max_book_size(self, max_book_size: Option<usize>) -> Self {
+    Self { max_book_size, ..self }
+  }
+
+  /// Sets the maximum nesting depth of patterns.
+  #[must_use]
+  pub fn set_max_pattern_depth(self, max_pattern_depth: Option<usize>) -> Self {
+    Self { max_pattern_depth, ..self }
+  }
+
+  /// Sets the maximum number of nodes of each generated HVM definition, instead of the maximum of the target.
+  #[must_use]
+  pub fn set_max_net_size(self, max_net_size: Option<usize>) -> Self {
+    Self { max_net_size, ..self }
+  }
+
+  /// Sets the maximum number of macro calls nested inside the expansion of another one, instead of 256.
+  #[must_use]
+  pub fn set_max_macro_depth(self, max_macro_depth: Option<usize>) -> Self {
+    Self { max_macro_depth, ..self }
   }
 }
 
@@ -340,6 +863,26 @@ impl OptLevel {
   }
 }
 
+/// How pattern matching function definitions are compiled.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PatternCompilation {
+  /// Tests each argument only once, duplicating rule bodies when needed. Faster, but may generate bigger nets.
+  #[default]
+  DecisionTree,
+  /// Tests the rules one by one, falling back to the next rule on failure. Each rule body appears only once.
+  Backtracking,
+}
+
+/// What the integer divisions and remainders by zero do.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DivisionByZero {
+  /// Uses the native operation of HVM, which stops the program.
+  #[default]
+  Error,
+  /// Returns `0`, with [fun::transform::guard_division_by_zero].
+  Zero,
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum CompilerTarget {
   C,
@@ -358,12 +901,21 @@ pub struct CompileOpts {
   /// Enables [fun::transform::definition_pruning] and [hvm::prune].
   pub prune: bool,
 
+  /// Enables [fun::transform::prune_imports].
+  pub prune_imports: bool,
+
   /// Enables [fun::transform::linearize_matches].
   pub linearize_matches: OptLevel,
 
   /// Enables [fun::transform::float_combinators].
   pub float_combinators: bool,
 
+  /// Enables [fun::transform::fold_constants].
+  pub fold_constants: bool,
+
+  /// Enables [fun::transform::collapse_redexes].
+  pub collapse_redexes: bool,
+
   /// Enables [fun::transform::definition_merge]
   pub merge: bool,
 
@@ -378,8 +930,30 @@ pub struct CompileOpts {
 
   /// Determines the encoding of constructors and matches.
   pub adt_encoding: AdtEncoding,
+
+  /// Determines how [fun::transform::desugar_match_defs] compiles pattern matching rules.
+  pub pattern_compilation: PatternCompilation,
+
+  /// Determines what the integer divisions by zero return.
+  pub division_by_zero: DivisionByZero,
+
+  /// The structural limits that the program must respect.
+  pub limits: Limits,
+
+  /// Enables [fun::transform::lift_match_arms], so that the arms taken by a run can be measured.
+  pub coverage: bool,
 }
 
+/// Besides setting the fields directly, the options can be built one by one starting from the defaults.
+///
+/// ```
+/// # use bend::{AdtEncoding, CompileOpts};
+/// let opts = CompileOpts::default().set_all().set_adt_encoding(AdtEncoding::Scott).set_type_check(false);
+/// assert!(opts.merge && !opts.type_check);
+///
+/// let opts = CompileOpts::default().set_opt_level(2);
+/// assert!(opts.collapse_redexes && !opts.merge);
+/// ```
 impl CompileOpts {
   /// Set all optimizing options as true
   #[must_use]
@@ -388,13 +962,20 @@ impl CompileOpts {
       target_architecture: self.target_architecture,
       eta: true,
       prune: true,
+      prune_imports: true,
       float_combinators: true,
+      fold_constants: true,
+      collapse_redexes: true,
       merge: true,
       linearize_matches: OptLevel::Enabled,
       type_check: true,
       inline: true,
       check_net_size: self.check_net_size,
       adt_encoding: self.adt_encoding,
+      pattern_compilation: self.pattern_compilation,
+      division_by_zero: self.division_by_zero,
+      limits: self.limits,
+      coverage: self.coverage,
     }
   }
 
@@ -405,16 +986,149 @@ impl CompileOpts {
       target_architecture: self.target_architecture,
       eta: false,
       prune: false,
+      prune_imports: false,
       linearize_matches: OptLevel::Disabled,
       float_combinators: false,
+      fold_constants: false,
+      collapse_redexes: false,
       merge: false,
       inline: false,
       type_check: self.type_check,
       check_net_size: self.check_net_size,
       adt_encoding: self.adt_encoding,
+      pattern_compilation: self.pattern_compilation,
+      division_by_zero: self.division_by_zero,
+      limits: self.limits,
+      coverage: self.coverage,
+    }
+  }
+
+  /// Sets the optimizing options by level, keeping the other options:
+  /// - `0` disables all of them, like [Self::set_no_all].
+  /// - `1` enables only the ones enabled by default.
+  /// - `2` also enables [fun::transform::collapse_redexes], which simplifies the terms before compiling them.
+  /// - `3` or more enables all of them, like [Self::set_all].
+  #[must_use]
+  pub fn set_opt_level(self, level: u8) -> Self {
+    match level {
+      0 => self.set_no_all(),
+      1 | 2 => {
+        let default = Self::default();
+        Self {
+          eta: default.eta,
+          linearize_matches: default.linearize_matches,
+          float_combinators: default.float_combinators,
+          fold_constants: default.fold_constants,
+          collapse_redexes: level == 2,
+          ..self.set_no_all()
+        }
+      }
+      _ => self.set_all(),
     }
   }
 
+  /// Sets the compiler target architecture.
+  #[must_use]
+  pub fn set_target_architecture(self, target_architecture: CompilerTarget) -> Self {
+    Self { target_architecture, ..self }
+  }
+
+  /// Enables or disables [hvm::eta_reduce].
+  #[must_use]
+  pub fn set_eta(self, eta: bool) -> Self {
+    Self { eta, ..self }
+  }
+
+  /// Enables or disables [fun::transform::definition_pruning] and [hvm::prune].
+  #[must_use]
+  pub fn set_prune(self, prune: bool) -> Self {
+    Self { prune, ..self }
+  }
+
+  /// Enables or disables [fun::transform::prune_imports].
+  #[must_use]
+  pub fn set_prune_imports(self, prune_imports: bool) -> Self {
+    Self { prune_imports, ..self }
+  }
+
+  /// Sets the level of [fun::transform::linearize_matches].
+  #[must_use]
+  pub fn set_linearize_matches(self, linearize_matches: OptLevel) -> Self {
+    Self { linearize_matches, ..self }
+  }
+
+  /// Enables or disables [fun::transform::float_combinators].
+  #[must_use]
+  pub fn set_float_combinators(self, float_combinators: bool) -> Self {
+    Self { float_combinators, ..self }
+  }
+
+  /// Enables or disables [fun::transform::fold_constants].
+  #[must_use]
+  pub fn set_fold_constants(self, fold_constants: bool) -> Self {
+    Self { fold_constants, ..self }
+  }
+
+  /// Enables or disables [fun::transform::collapse_redexes].
+  #[must_use]
+  pub fn set_collapse_redexes(self, collapse_redexes: bool) -> Self {
+    Self { collapse_redexes, ..self }
+  }
+
+  /// Enables or disables [fun::transform::definition_merge].
+  #[must_use]
+  pub fn set_merge(self, merge: bool) -> Self {
+    Self { merge, ..self }
+  }
+
+  /// Enables or disables [hvm::inline].
+  #[must_use]
+  pub fn set_inline(self, inline: bool) -> Self {
+    Self { inline, ..self }
+  }
+
+  /// Enables or disables [hvm::check_net_size].
+  #[must_use]
+  pub fn set_check_net_size(self, check_net_size: bool) -> Self {
+    Self { check_net_size, ..self }
+  }
+
+  /// Enables or disables [type_check_book].
+  #[must_use]
+  pub fn set_type_check(self, type_check: bool) -> Self {
+    Self { type_check, ..self }
+  }
+
+  /// Sets the encoding of constructors and matches.
+  #[must_use]
+  pub fn set_adt_encoding(self, adt_encoding: AdtEncoding) -> Self {
+    Self { adt_encoding, ..self }
+  }
+
+  /// Sets how pattern matching rules are compiled.
+  #[must_use]
+  pub fn set_pattern_compilation(self, pattern_compilation: PatternCompilation) -> Self {
+    Self { pattern_compilation, ..self }
+  }
+
+  /// Sets what the integer divisions by zero return.
+  #[must_use]
+  pub fn set_division_by_zero(self, division_by_zero: DivisionByZero) -> Self {
+    Self { division_by_zero, ..self }
+  }
+
+  /// Sets the structural limits that the program must respect.
+  #[must_use]
+  pub fn set_limits(self, limits: Limits) -> Self {
+    Self { limits, ..self }
+  }
+
+  /// Enables or disables [fun::transform::lift_match_arms].
+  #[must_use]
+  pub fn set_coverage(self, coverage: bool) -> Self {
+    Self { coverage, ..self }
+  }
+
   pub fn check_for_strict(&self) {
     if !self.float_combinators {
       println!(
@@ -430,20 +1144,27 @@ impl CompileOpts {
 }
 
 impl Default for CompileOpts {
-  /// Enables eta, linearize_matches, float_combinators.
+  /// Enables eta, linearize_matches, float_combinators, fold_constants.
   /// Uses num-scott ADT encoding.
   fn default() -> Self {
     Self {
       target_architecture: CompilerTarget::Unknown,
       eta: true,
       prune: false,
+      prune_imports: false,
       linearize_matches: OptLevel::Enabled,
       float_combinators: true,
+      fold_constants: true,
+      collapse_redexes: false,
       merge: false,
       inline: false,
       check_net_size: true,
       type_check: true,
       adt_encoding: AdtEncoding::NumScott,
+      pattern_compilation: PatternCompilation::DecisionTree,
+      division_by_zero: DivisionByZero::Error,
+      limits: Limits::default(),
+      coverage: false,
     }
   }
 }
@@ -469,6 +1190,11 @@ pub struct CompileResult {
   pub labels: Labels,
 }
 
+/// The total number of nodes of the nets in an HVM book.
+fn count_book_nodes(book: &::hvm::ast::Book) -> usize {
+  book.defs.values().map(count_nodes).sum()
+}
+
 fn maybe_grow<R, F>(f: F) -> R
 where
   F: FnOnce() -> R,
@@ -1,16 +1,19 @@
 use bend::{
-  check_book, compile_book, desugar_book,
+  check_book, compile_book, coverage_book, desugar_book,
   diagnostics::{Diagnostics, DiagnosticsConfig, Severity},
-  fun::{Book, Name},
+  disassemble_def, find_failed_assert,
+  fun::{parser::FunParser, Book, Name, Term},
   hvm::hvm_book_show_pretty,
   imports::DefaultLoader,
-  load_file_to_book, run_book, AdtEncoding, CompileOpts, CompilerTarget, OptLevel, RunOpts,
+  load_file_to_book_with_opts, run_book_with_net, test_book, AdtEncoding, CompileOpts, CompilerTarget,
+  DivisionByZero, Limits, LoadOpts, OptLevel, OutputFormat, PatternCompilation, Prelude, RunOpts, TestResult,
 };
 use clap::{Args, CommandFactory, Parser, Subcommand};
 use std::{
   path::{Path, PathBuf},
   process::ExitCode,
 };
+use TSPL::Parser as _;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
@@ -26,6 +29,40 @@ struct Cli {
 
   #[arg(short = 'e', long, global = true, help = "Use other entrypoint rather than main or Main")]
   pub entrypoint: Option<String>,
+
+  #[arg(long, global = true, help = "Print the time and memory allocated by each compiler phase")]
+  pub timings: bool,
+
+  #[arg(
+    long = "cfg",
+    value_name = "NAME",
+    global = true,
+    action = clap::ArgAction::Append,
+    help = "Enable the definitions marked with a #[cfg(...)] that depends on the given flag"
+  )]
+  pub cfg: Vec<String>,
+
+  #[arg(long, global = true, help = "Don't load the builtin definitions")]
+  pub no_prelude: bool,
+
+  #[arg(
+    long,
+    value_name = "PATH",
+    global = true,
+    conflicts_with = "no_prelude",
+    help = "Load the definitions of the given file instead of the builtin ones"
+  )]
+  pub prelude: Option<PathBuf>,
+
+  #[arg(
+    long = "limit",
+    value_name = "LIMIT=N",
+    global = true,
+    action = clap::ArgAction::Append,
+    value_parser = parse_limit,
+    help = "Reject the programs over a structural limit: term-depth, book-size, pattern-depth or net-size"
+  )]
+  pub limits: Vec<(String, usize)>,
 }
 
 #[derive(Subcommand, Clone, Debug)]
@@ -44,6 +81,9 @@ enum Mode {
     #[command(flatten)]
     warn_opts: CliWarnOpts,
 
+    #[arg(long, help = "Print the type of each definition, as inferred by the type checker")]
+    types: bool,
+
     #[arg(help = "Path to the input file")]
     path: PathBuf,
   },
@@ -54,6 +94,10 @@ enum Mode {
   RunC(RunArgs),
   /// Compiles the program and runs it with the Cuda HVM implementation.
   RunCu(RunArgs),
+  /// Runs the definitions marked with #[test] with the C HVM implementation and reports which ones failed.
+  Test(GenArgs),
+  /// Runs the program with the Rust HVM implementation built into Bend and reports which definitions and match arms were executed.
+  Coverage(RunArgs),
   /// Compiles the program to hvm and prints to stdout.
   GenHvm(GenArgs),
   /// Compiles the program to standalone C and prints to stdout.
@@ -80,6 +124,35 @@ enum Mode {
     #[arg(help = "Path to the input file")]
     path: PathBuf,
   },
+  /// Compiles the program and prints the HVM nets of one definition, with the terms they were compiled from.
+  Disasm {
+    #[arg(
+      short = 'O',
+      value_delimiter = ' ',
+      action = clap::ArgAction::Append,
+      long_help = r#"Enables or disables the given optimizations
+      float_combinators is enabled by default on strict mode."#,
+    )]
+    comp_opts: Vec<OptArgs>,
+
+    #[command(flatten)]
+    warn_opts: CliWarnOpts,
+
+    #[arg(help = "Path to the input file")]
+    path: PathBuf,
+
+    #[arg(help = "Name of the definition to show")]
+    def: String,
+  },
+  /// Compares two versions of a program and reports the definitions that were added, removed or changed,
+  /// ignoring formatting and variable names.
+  Diff {
+    #[arg(help = "Path to the old version of the program")]
+    old: PathBuf,
+
+    #[arg(help = "Path to the new version of the program")]
+    new: PathBuf,
+  },
 }
 
 #[derive(Args, Clone, Debug)]
@@ -105,8 +178,8 @@ struct RunArgs {
   #[arg(help = "Path to the input file")]
   path: PathBuf,
 
-  #[arg(value_parser = |arg: &str| bend::fun::parser::FunParser::new(Name::new(""), arg, false).parse_term())]
-  arguments: Option<Vec<bend::fun::Term>>,
+  #[arg(value_parser = parse_cli_arg)]
+  arguments: Option<Vec<Term>>,
 }
 
 #[derive(Args, Clone, Debug)]
@@ -134,6 +207,43 @@ struct CliRunOpts {
 
   #[arg(short = 's', long = "stats", help = "Shows runtime stats and rewrite counts")]
   print_stats: bool,
+
+  #[arg(long = "output", value_enum, default_value = "data", help = "How the result is shown")]
+  output: OutputArgs,
+
+  #[arg(
+    long = "expand-refs",
+    value_name = "DEPTH",
+    num_args = 0..=1,
+    require_equals = true,
+    default_value = "0",
+    default_missing_value = "1",
+    help = "Expands the references to definitions in the result, up to the given depth"
+  )]
+  expand_refs: usize,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+pub enum OutputArgs {
+  /// The net returned by HVM
+  RawNet,
+  /// The lambda term, without resugaring any data
+  Term,
+  /// The term with strings and lists resugared
+  Data,
+  /// The term as a JSON value
+  Json,
+}
+
+impl From<OutputArgs> for OutputFormat {
+  fn from(args: OutputArgs) -> Self {
+    match args {
+      OutputArgs::RawNet => OutputFormat::RawNet,
+      OutputArgs::Term => OutputFormat::Term,
+      OutputArgs::Data => OutputFormat::Data,
+      OutputArgs::Json => OutputFormat::Json,
+    }
+  }
 }
 
 #[derive(Args, Debug, Clone)]
@@ -175,11 +285,17 @@ pub enum OptArgs {
   NoEta,
   Prune,
   NoPrune,
+  PruneImports,
+  NoPruneImports,
   LinearizeMatches,
   LinearizeMatchesAlt,
   NoLinearizeMatches,
   FloatCombinators,
   NoFloatCombinators,
+  FoldConstants,
+  NoFoldConstants,
+  CollapseRedexes,
+  NoCollapseRedexes,
   Merge,
   NoMerge,
   Inline,
@@ -190,11 +306,19 @@ pub enum OptArgs {
   AdtNumScott,
   TypeCheck,
   NoTypeCheck,
+  PatternDecisionTree,
+  PatternBacktracking,
+  DivByZeroError,
+  DivByZeroZero,
 }
 
-fn compile_opts_from_cli(args: &Vec<OptArgs>, compiler_target: CompilerTarget) -> CompileOpts {
+fn compile_opts_from_cli(
+  args: &Vec<OptArgs>,
+  compiler_target: CompilerTarget,
+  limits: Limits,
+) -> CompileOpts {
   use OptArgs::*;
-  let mut opts = CompileOpts { target_architecture: compiler_target, ..CompileOpts::default() };
+  let mut opts = CompileOpts::default().set_target_architecture(compiler_target).set_limits(limits);
   if opts.target_architecture != CompilerTarget::C {
     opts.eta = false;
   }
@@ -207,8 +331,14 @@ fn compile_opts_from_cli(args: &Vec<OptArgs>, compiler_target: CompilerTarget) -
       NoEta => opts.eta = false,
       Prune => opts.prune = true,
       NoPrune => opts.prune = false,
+      PruneImports => opts.prune_imports = true,
+      NoPruneImports => opts.prune_imports = false,
       FloatCombinators => opts.float_combinators = true,
       NoFloatCombinators => opts.float_combinators = false,
+      FoldConstants => opts.fold_constants = true,
+      NoFoldConstants => opts.fold_constants = false,
+      CollapseRedexes => opts.collapse_redexes = true,
+      NoCollapseRedexes => opts.collapse_redexes = false,
       Merge => opts.merge = true,
       NoMerge => opts.merge = false,
       Inline => opts.inline = true,
@@ -224,6 +354,12 @@ fn compile_opts_from_cli(args: &Vec<OptArgs>, compiler_target: CompilerTarget) -
 
       AdtScott => opts.adt_encoding = AdtEncoding::Scott,
       AdtNumScott => opts.adt_encoding = AdtEncoding::NumScott,
+
+      PatternDecisionTree => opts.pattern_compilation = PatternCompilation::DecisionTree,
+      PatternBacktracking => opts.pattern_compilation = PatternCompilation::Backtracking,
+
+      DivByZeroError => opts.division_by_zero = DivisionByZero::Error,
+      DivByZeroZero => opts.division_by_zero = DivisionByZero::Zero,
     }
   }
 
@@ -240,7 +376,100 @@ pub enum WarningArgs {
   RepeatedBind,
   RecursionCycle,
   ImportShadow,
+  SelfApplication,
+  Deprecated,
+  KeywordName,
   MissingMain,
+  NonExhaustiveMatch,
+  RulesOutOfOrder,
+  Redefinition,
+  DivisionByZero,
+}
+
+/// The limits that can be set with `--limit`.
+const LIMITS: [&str; 5] = ["term-depth", "book-size", "pattern-depth", "net-size", "macro-depth"];
+
+/// Parses a `--limit` argument, like `term-depth=1000`.
+fn parse_limit(arg: &str) -> Result<(String, usize), String> {
+  let Some((name, val)) = arg.split_once('=') else {
+    return Err(format!("expected a limit like 'term-depth=1000', found '{arg}'"));
+  };
+  if !LIMITS.contains(&name) {
+    return Err(format!("unknown limit '{name}', expected one of {}", LIMITS.join(", ")));
+  }
+  let val = val.parse().map_err(|e| format!("invalid value for limit '{name}': {e}"))?;
+  Ok((name.to_string(), val))
+}
+
+fn limits_from_cli(args: &[(String, usize)]) -> Limits {
+  let mut limits = Limits::default();
+  for (name, val) in args {
+    match name.as_str() {
+      "term-depth" => limits.max_term_depth = Some(*val),
+      "book-size" => limits.max_book_size = Some(*val),
+      "pattern-depth" => limits.max_pattern_depth = Some(*val),
+      "net-size" => limits.max_net_size = Some(*val),
+      "macro-depth" => limits.max_macro_depth = Some(*val),
+      _ => unreachable!(),
+    }
+  }
+  limits
+}
+
+/// Parses a CLI argument passed to the program as a term, like `+5` or `(1, "a")`.
+/// Arguments that aren't a complete term are passed as strings.
+/// A single name is kept as a variable, to be resolved by [`resolve_cli_args`] once the program is loaded.
+fn parse_cli_arg(arg: &str) -> Result<Term, String> {
+  let mut parser = FunParser::new(Name::new(""), arg, false);
+  match parser.parse_term() {
+    Err(_) => Ok(Term::str(arg)),
+    Ok(term) => {
+      parser.skip_trivia();
+      if parser.is_eof() {
+        Ok(term)
+      } else {
+        Ok(Term::str(arg))
+      }
+    }
+  }
+}
+
+/// Resolves the CLI arguments that are a single name, like `Nat/Zero`, to the definition or constructor of the book with that name.
+/// The names that aren't in the book are passed as strings.
+fn resolve_cli_args(args: Option<Vec<Term>>, book: &Book) -> Option<Vec<Term>> {
+  let resolve = |arg: Term| match &arg {
+    Term::Var { nam }
+      if !book.ctrs.contains_key(nam) && !book.defs.contains_key(nam) && !book.hvm_defs.contains_key(nam) =>
+    {
+      Term::str(nam)
+    }
+    _ => arg,
+  };
+  args.map(|args| args.into_iter().map(resolve).collect())
+}
+
+#[global_allocator]
+static ALLOCATOR: timings::CountingAllocator = timings::CountingAllocator;
+
+/// Logs the spans of the compiler phases to stderr, filtered by the `RUST_LOG` environment variable.
+/// For example, `RUST_LOG=bend=debug` shows how long each compiler pass takes.
+///
+/// With `--timings`, also measures all the phases and passes to print a report at the end.
+fn init_tracing(timings: Option<&timings::Timings>) {
+  use tracing_subscriber::{
+    filter::{LevelFilter, Targets},
+    fmt::format::FmtSpan,
+    layer::SubscriberExt,
+    util::SubscriberInitExt,
+    EnvFilter, Layer,
+  };
+  let fmt = tracing_subscriber::fmt::layer()
+    .with_span_events(FmtSpan::CLOSE)
+    .with_writer(std::io::stderr)
+    .with_filter(EnvFilter::from_default_env());
+  let timings = timings
+    .map(|timings| timings.layer().with_filter(Targets::new().with_target("bend", LevelFilter::DEBUG)));
+  tracing_subscriber::registry().with(fmt).with(timings).init();
 }
 
 fn main() -> ExitCode {
@@ -249,7 +478,15 @@ fn main() -> ExitCode {
 
   let cli = Cli::parse();
 
-  if let Err(diagnostics) = execute_cli_mode(cli) {
+  let timings = cli.timings.then(timings::Timings::start);
+  init_tracing(timings.as_ref());
+
+  let res = execute_cli_mode(cli);
+  if let Some(timings) = timings {
+    eprint!("{timings}");
+  }
+
+  if let Err(diagnostics) = res {
     eprint!("{diagnostics}");
     return ExitCode::FAILURE;
   }
@@ -259,11 +496,20 @@ fn main() -> ExitCode {
 fn execute_cli_mode(mut cli: Cli) -> Result<(), Diagnostics> {
   let arg_verbose = cli.verbose;
   let entrypoint = cli.entrypoint.take();
+  let prelude = match (cli.no_prelude, cli.prelude.take()) {
+    (true, _) => Prelude::None,
+    (false, Some(path)) => Prelude::File(path),
+    (false, None) => Prelude::Builtin,
+  };
+  let load_opts = LoadOpts::default().set_cfg(cli.cfg.iter().map(Name::new).collect()).set_prelude(prelude);
+  let limits = limits_from_cli(&cli.limits);
 
   let load_book = |path: &Path, diag: DiagnosticsConfig| -> Result<Book, Diagnostics> {
     let package_loader = DefaultLoader::new(path);
-    let mut book = load_file_to_book(path, package_loader, diag)?;
-    book.entrypoint = entrypoint.map(Name::new);
+    let mut book = load_file_to_book_with_opts(path, package_loader, diag, load_opts.clone())?;
+    if let Some(entrypoint) = &entrypoint {
+      book.entrypoint = Some(Name::new(entrypoint));
+    }
 
     if arg_verbose {
       println!("{book}");
@@ -298,24 +544,28 @@ fn execute_cli_mode(mut cli: Cli) -> Result<(), Diagnostics> {
   let compiler_target = match &cli.mode {
     Mode::RunC(..) => CompilerTarget::C,
     Mode::GenC(..) => CompilerTarget::C,
+    Mode::Test(..) => CompilerTarget::C,
     Mode::RunCu(..) => CompilerTarget::Cuda,
     Mode::GenCu(..) => CompilerTarget::Cuda,
     _ => CompilerTarget::Unknown,
   };
 
   match cli.mode {
-    Mode::Check { comp_opts, warn_opts, path } => {
+    Mode::Check { comp_opts, warn_opts, types, path } => {
       let diagnostics_cfg = set_warning_cfg_from_cli(DiagnosticsConfig::default(), warn_opts);
-      let compile_opts = compile_opts_from_cli(&comp_opts, compiler_target);
+      let compile_opts = compile_opts_from_cli(&comp_opts, compiler_target, limits);
 
       let mut book = load_book(&path, diagnostics_cfg)?;
       let diagnostics = check_book(&mut book, diagnostics_cfg, compile_opts)?;
       eprintln!("{}", diagnostics);
+      if types {
+        println!("{}", book.display_types());
+      }
     }
 
     Mode::GenHvm(GenArgs { comp_opts, warn_opts, path, .. }) => {
       let diagnostics_cfg = set_warning_cfg_from_cli(DiagnosticsConfig::default(), warn_opts);
-      let opts = compile_opts_from_cli(&comp_opts, compiler_target);
+      let opts = compile_opts_from_cli(&comp_opts, compiler_target, limits);
 
       let mut book = load_book(&path, diagnostics_cfg)?;
       let compile_res = compile_book(&mut book, opts, diagnostics_cfg, None)?;
@@ -327,26 +577,42 @@ fn execute_cli_mode(mut cli: Cli) -> Result<(), Diagnostics> {
     Mode::RunC(RunArgs { pretty, run_opts, comp_opts, warn_opts, path, arguments })
     | Mode::RunCu(RunArgs { pretty, run_opts, comp_opts, warn_opts, path, arguments })
     | Mode::RunRs(RunArgs { pretty, run_opts, comp_opts, warn_opts, path, arguments }) => {
-      let CliRunOpts { linear, print_stats } = run_opts;
+      let CliRunOpts { linear, print_stats, output, expand_refs } = run_opts;
+      let output = OutputFormat::from(output);
 
       let diagnostics_cfg =
         set_warning_cfg_from_cli(DiagnosticsConfig::new(Severity::Allow, arg_verbose), warn_opts);
 
-      let compile_opts = compile_opts_from_cli(&comp_opts, compiler_target);
+      let compile_opts = compile_opts_from_cli(&comp_opts, compiler_target, limits);
 
       compile_opts.check_for_strict();
+      check_output_encoding(output, &compile_opts)?;
 
-      let run_opts = RunOpts { linear_readback: linear, pretty, hvm_path: hvm_bin };
+      let run_opts = RunOpts::default()
+        .set_linear_readback(linear)
+        .set_pretty(pretty)
+        .set_hvm_path(hvm_bin)
+        .set_output(output)
+        .set_expand_refs(expand_refs);
 
       let book = load_book(&path, diagnostics_cfg)?;
-      if let Some((term, stats, diags)) =
-        run_book(book, run_opts, compile_opts, diagnostics_cfg, arguments, run_cmd)?
+      let arguments = resolve_cli_args(arguments, &book);
+      // The constructors of the program are needed to find failed asserts and to show the result as JSON.
+      let ctrs_book = Book { adts: book.adts.clone(), ctrs: book.ctrs.clone(), ..Default::default() };
+      if let Some((term, net, stats, diags)) =
+        run_book_with_net(book, run_opts, compile_opts, diagnostics_cfg, arguments, run_cmd)?
       {
         eprint!("{diags}");
-        if pretty {
-          println!("Result:\n{}", term.display_pretty(0));
-        } else {
-          println!("Result: {}", term);
+        if let Some(msg) = find_failed_assert(&term, &ctrs_book) {
+          return Err(msg.into());
+        }
+        match output {
+          OutputFormat::RawNet => println!("Result: {}", net.show()),
+          OutputFormat::Json => println!("{}", term.display_json(&ctrs_book)),
+          OutputFormat::Term | OutputFormat::Data if pretty => {
+            println!("Result:\n{}", term.display_pretty(0))
+          }
+          OutputFormat::Term | OutputFormat::Data => println!("Result: {}", term),
         }
         if print_stats {
           println!("{stats}");
@@ -354,10 +620,64 @@ fn execute_cli_mode(mut cli: Cli) -> Result<(), Diagnostics> {
       }
     }
 
+    Mode::Test(GenArgs { comp_opts, warn_opts, path }) => {
+      let diagnostics_cfg =
+        set_warning_cfg_from_cli(DiagnosticsConfig::new(Severity::Allow, arg_verbose), warn_opts);
+      let compile_opts = compile_opts_from_cli(&comp_opts, compiler_target, limits);
+      let run_opts = RunOpts::default().set_hvm_path(hvm_bin);
+
+      let book = load_book(&path, diagnostics_cfg)?;
+      let results = test_book(book, run_opts, compile_opts, diagnostics_cfg, run_cmd)?;
+
+      let mut failed = 0;
+      for (test, result, time) in results.iter() {
+        let time = time.as_secs_f64();
+        match result {
+          TestResult::Pass => println!("test {test} ... ok ({time:.2}s)"),
+          TestResult::Fail(msg) => {
+            failed += 1;
+            println!("test {test} ... FAILED ({time:.2}s)\n  {msg}");
+          }
+        }
+      }
+      let total = results.iter().map(|(_, _, time)| time.as_secs_f64()).sum::<f64>();
+      println!("\n{} passed; {failed} failed; finished in {total:.2}s", results.len() - failed);
+      if failed > 0 {
+        return Err(format!("{failed} of {} tests failed.", results.len()).into());
+      }
+    }
+
+    Mode::Coverage(RunArgs { pretty, run_opts, comp_opts, warn_opts, path, arguments }) => {
+      let CliRunOpts { linear, output, expand_refs, .. } = run_opts;
+      let output = OutputFormat::from(output);
+
+      let diagnostics_cfg =
+        set_warning_cfg_from_cli(DiagnosticsConfig::new(Severity::Allow, arg_verbose), warn_opts);
+      let compile_opts = compile_opts_from_cli(&comp_opts, compiler_target, limits);
+      check_output_encoding(output, &compile_opts)?;
+      let run_opts = RunOpts::default()
+        .set_linear_readback(linear)
+        .set_pretty(pretty)
+        .set_output(output)
+        .set_expand_refs(expand_refs);
+
+      let book = load_book(&path, diagnostics_cfg)?;
+      let arguments = resolve_cli_args(arguments, &book);
+      let ctrs_book = Book { adts: book.adts.clone(), ctrs: book.ctrs.clone(), ..Default::default() };
+      let (term, coverage, diags) = coverage_book(book, run_opts, compile_opts, diagnostics_cfg, arguments)?;
+      eprint!("{diags}");
+      match output {
+        OutputFormat::Json => println!("{}", term.display_json(&ctrs_book)),
+        _ if pretty => println!("Result:\n{}", term.display_pretty(0)),
+        _ => println!("Result: {}", term),
+      }
+      print!("{coverage}");
+    }
+
     Mode::GenC(GenArgs { comp_opts, warn_opts, path })
     | Mode::GenCu(GenArgs { comp_opts, warn_opts, path }) => {
       let diagnostics_cfg = set_warning_cfg_from_cli(DiagnosticsConfig::default(), warn_opts);
-      let opts = compile_opts_from_cli(&comp_opts, compiler_target);
+      let opts = compile_opts_from_cli(&comp_opts, compiler_target, limits);
 
       let mut book = load_book(&path, diagnostics_cfg)?;
       let compile_res = compile_book(&mut book, opts, diagnostics_cfg, None)?;
@@ -388,7 +708,7 @@ fn execute_cli_mode(mut cli: Cli) -> Result<(), Diagnostics> {
     Mode::Desugar { path, comp_opts, warn_opts, pretty } => {
       let diagnostics_cfg = set_warning_cfg_from_cli(DiagnosticsConfig::default(), warn_opts);
 
-      let opts = compile_opts_from_cli(&comp_opts, compiler_target);
+      let opts = compile_opts_from_cli(&comp_opts, compiler_target, limits);
 
       let mut book = load_book(&path, diagnostics_cfg)?;
       let diagnostics = desugar_book(&mut book, opts, diagnostics_cfg, None)?;
@@ -400,10 +720,38 @@ fn execute_cli_mode(mut cli: Cli) -> Result<(), Diagnostics> {
         println!("{book}");
       }
     }
+
+    Mode::Disasm { comp_opts, warn_opts, path, def } => {
+      let diagnostics_cfg = set_warning_cfg_from_cli(DiagnosticsConfig::default(), warn_opts);
+      let opts = compile_opts_from_cli(&comp_opts, compiler_target, limits);
+
+      let mut book = load_book(&path, diagnostics_cfg)?;
+      let (nets, diagnostics) = disassemble_def(&mut book, &Name::new(def), opts, diagnostics_cfg)?;
+
+      eprint!("{diagnostics}");
+      print!("{nets}");
+    }
+
+    Mode::Diff { old, new } => {
+      let diagnostics_cfg = DiagnosticsConfig::new(Severity::Allow, arg_verbose);
+
+      let old = load_book(&old, diagnostics_cfg)?;
+      let new = load_book(&new, diagnostics_cfg)?;
+      print!("{}", old.diff(&new, diagnostics_cfg)?);
+    }
   };
   Ok(())
 }
 
+/// The JSON output reads the constructors of the num-scott encoding, so the data of other encodings can't be shown.
+fn check_output_encoding(output: OutputFormat, compile_opts: &CompileOpts) -> Result<(), Diagnostics> {
+  if output == OutputFormat::Json && !matches!(compile_opts.adt_encoding, AdtEncoding::NumScott) {
+    let msg = "The JSON output can only show the data of the num-scott encoding, it can't be used with '-Oadt-scott'.";
+    return Err(msg.to_string().into());
+  }
+  Ok(())
+}
+
 fn set_warning_cfg_from_cli(mut cfg: DiagnosticsConfig, warn_opts: CliWarnOpts) -> DiagnosticsConfig {
   fn set(cfg: &mut DiagnosticsConfig, severity: Severity, cli_val: WarningArgs) {
     match cli_val {
@@ -415,6 +763,9 @@ fn set_warning_cfg_from_cli(mut cfg: DiagnosticsConfig, warn_opts: CliWarnOpts)
         cfg.repeated_bind = severity;
         cfg.recursion_cycle = severity;
         cfg.import_shadow = severity;
+        cfg.self_application = severity;
+        cfg.deprecated = severity;
+        cfg.keyword_name = severity;
       }
       WarningArgs::IrrefutableMatch => cfg.irrefutable_match = severity,
       WarningArgs::RedundantMatch => cfg.redundant_match = severity,
@@ -423,7 +774,14 @@ fn set_warning_cfg_from_cli(mut cfg: DiagnosticsConfig, warn_opts: CliWarnOpts)
       WarningArgs::RepeatedBind => cfg.repeated_bind = severity,
       WarningArgs::RecursionCycle => cfg.recursion_cycle = severity,
       WarningArgs::ImportShadow => cfg.import_shadow = severity,
+      WarningArgs::SelfApplication => cfg.self_application = severity,
+      WarningArgs::Deprecated => cfg.deprecated = severity,
+      WarningArgs::KeywordName => cfg.keyword_name = severity,
       WarningArgs::MissingMain => cfg.missing_main = severity, // TODO: Should `WarningArgs::All` modify this as well?
+      WarningArgs::NonExhaustiveMatch => cfg.non_exhaustive_match = severity,
+      WarningArgs::RulesOutOfOrder => cfg.rules_out_of_order = severity,
+      WarningArgs::Redefinition => cfg.redefinition = severity,
+      WarningArgs::DivisionByZero => cfg.division_by_zero = severity,
     }
   }
 
@@ -447,3 +805,135 @@ fn set_warning_cfg_from_cli(mut cfg: DiagnosticsConfig, warn_opts: CliWarnOpts)
   }
   cfg
 }
+
+/// The `--timings` report, built from the tracing spans of the compiler phases.
+mod timings {
+  use indexmap::IndexMap;
+  use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    fmt,
+    sync::{
+      atomic::{AtomicBool, AtomicUsize, Ordering},
+      Arc, Mutex,
+    },
+    time::{Duration, Instant},
+  };
+  use tracing::{span, Subscriber};
+  use tracing_subscriber::{layer::Context, registry::LookupSpan, Layer};
+
+  /// The total number of bytes allocated by the program since the timings started.
+  static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+  /// If the allocations are being counted, which is only done with `--timings`.
+  static COUNTING: AtomicBool = AtomicBool::new(false);
+
+  /// The system allocator, but counting the allocated bytes.
+  pub struct CountingAllocator;
+
+  impl CountingAllocator {
+    fn count(bytes: usize) {
+      if COUNTING.load(Ordering::Relaxed) {
+        ALLOCATED.fetch_add(bytes, Ordering::Relaxed);
+      }
+    }
+  }
+
+  unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+      Self::count(layout.size());
+      System.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+      Self::count(layout.size());
+      System.alloc_zeroed(layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+      // Only the growth is new memory, shrinking doesn't allocate.
+      Self::count(new_size.saturating_sub(layout.size()));
+      System.realloc(ptr, layout, new_size)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+      System.dealloc(ptr, layout)
+    }
+  }
+
+  /// The accumulated measures of each phase, in the order they first started.
+  #[derive(Default)]
+  pub struct Timings {
+    phases: Arc<Mutex<IndexMap<&'static str, Phase>>>,
+  }
+
+  #[derive(Default)]
+  struct Phase {
+    depth: usize,
+    calls: usize,
+    time: Duration,
+    allocated: usize,
+  }
+
+  /// Stored in the span when it's created.
+  struct Start {
+    time: Instant,
+    allocated: usize,
+  }
+
+  pub struct TimingsLayer {
+    phases: Arc<Mutex<IndexMap<&'static str, Phase>>>,
+  }
+
+  impl Timings {
+    /// Starts the timings, counting the allocations from now on.
+    pub fn start() -> Self {
+      COUNTING.store(true, Ordering::Relaxed);
+      Self::default()
+    }
+
+    pub fn layer(&self) -> TimingsLayer {
+      TimingsLayer { phases: self.phases.clone() }
+    }
+  }
+
+  impl<S: Subscriber + for<'a> LookupSpan<'a>> Layer<S> for TimingsLayer {
+    fn on_new_span(&self, _: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+      let Some(span) = ctx.span(id) else { return };
+      let depth = span.scope().skip(1).count();
+      self.phases.lock().unwrap().entry(span.name()).or_insert(Phase { depth, ..Default::default() });
+      span
+        .extensions_mut()
+        .insert(Start { time: Instant::now(), allocated: ALLOCATED.load(Ordering::Relaxed) });
+    }
+
+    fn on_close(&self, id: span::Id, ctx: Context<'_, S>) {
+      let Some(span) = ctx.span(&id) else { return };
+      let Some(start) = span.extensions_mut().remove::<Start>() else { return };
+      let mut phases = self.phases.lock().unwrap();
+      let phase = phases.entry(span.name()).or_default();
+      phase.calls += 1;
+      phase.time += start.time.elapsed();
+      phase.allocated += ALLOCATED.load(Ordering::Relaxed) - start.allocated;
+    }
+  }
+
+  impl fmt::Display for Timings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+      writeln!(f, "{:<40} {:>6} {:>12} {:>12}", "Phase", "Calls", "Time", "Allocated")?;
+      for (name, phase) in self.phases.lock().unwrap().iter() {
+        let name = format!("{:indent$}{name}", "", indent = phase.depth * 2);
+        let time = format!("{:.2?}", phase.time);
+        writeln!(f, "{name:<40} {:>6} {time:>12} {:>12}", phase.calls, show_bytes(phase.allocated))?;
+      }
+      Ok(())
+    }
+  }
+
+  fn show_bytes(bytes: usize) -> String {
+    match bytes {
+      0..=999 => format!("{bytes} B"),
+      1_000..=999_999 => format!("{:.1} KB", bytes as f64 / 1e3),
+      _ => format!("{:.1} MB", bytes as f64 / 1e6),
+    }
+  }
+}
@@ -12,8 +12,11 @@ use bend::{
   check_book, compile_book, desugar_book,
   diagnostics::{Diagnostics, DiagnosticsConfig, Severity},
   fun::{
-    load_book::do_parse_book, net_to_term::net_to_term, parser::ParseBook, term_to_net::Labels, Book, Ctx,
-    Name,
+    load_book::{do_parse_book, do_parse_book_recovering},
+    net_to_term::net_to_term,
+    parser::ParseBook,
+    term_to_net::Labels,
+    Book, Ctx, Name,
   },
   hvm::hvm_book_show_pretty,
   imports::DefaultLoader,
@@ -336,6 +339,15 @@ fn parse_file() {
   })
 }
 
+/// Parses a file with syntax errors, showing the errors with the definitions that could be parsed.
+#[test]
+fn parse_file_recovering() {
+  run_golden_test_dir(function_name!(), &|code, path| {
+    let (book, diagnostics) = do_parse_book_recovering(code, path, ParseBook::default());
+    Ok(format!("{diagnostics}\n{}", book.to_fun()?))
+  })
+}
+
 /// Runs the check command on a file.
 #[test]
 fn check_file() {
@@ -407,7 +419,6 @@ fn compile_entrypoint() {
 
 /// Runs a file with a custom entrypoint.
 #[test]
-#[ignore = "while execution with different entrypoints is not implemented for hvm32"]
 fn run_entrypoint() {
   run_golden_test_dir(function_name!(), &|code, path| {
     let _guard = RUN_MUTEX.lock().unwrap();
@@ -443,6 +454,41 @@ fn cli() {
   })
 }
 
+/// Runs the tests of a file with the `bend test` command, showing its exit code.
+#[test]
+fn test_file() {
+  run_golden_test_dir(function_name!(), &|_code, path| {
+    let _guard = RUN_MUTEX.lock().unwrap();
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_bend"))
+      .arg("test")
+      .arg(path)
+      .output()
+      .expect("Run command");
+    let stdout = hide_test_timings(&String::from_utf8_lossy(&output.stdout));
+    let code = output.status.code().map_or("none".to_string(), |code| code.to_string());
+    Ok(format!("{}{stdout}\nExit code: {code}", String::from_utf8_lossy(&output.stderr)))
+  })
+}
+
+/// Replaces the times printed by `bend test`, like `(0.25s)` and `finished in 0.74s`, since they change on every run.
+fn hide_test_timings(out: &str) -> String {
+  let is_time = |time: &str| time.strip_suffix('s').is_some_and(|time| time.parse::<f64>().is_ok());
+  out
+    .lines()
+    .map(|line| {
+      if let Some((ini, _)) =
+        line.rsplit_once(" (").filter(|(_, end)| end.strip_suffix(')').is_some_and(is_time))
+      {
+        format!("{ini} (<time>)")
+      } else if let Some((ini, _)) = line.rsplit_once("finished in ").filter(|(_, end)| is_time(end)) {
+        format!("{ini}finished in <time>")
+      } else {
+        line.to_string()
+      }
+    })
+    .join("\n")
+}
+
 /// Compiles a file to check for mutual recursion.
 #[test]
 fn mutual_recursion() {
@@ -540,16 +586,34 @@ fn scott_triggers_unused() {
 /// Only outputs if compilation worked without errors.
 #[test]
 fn compile_long() {
-  run_golden_test_dir(function_name!(), &|code, path| {
-    let mut book = parse_book_single_file(code, path)?;
-    let opts = CompileOpts::default().set_all();
-    let diagnostics_cfg = DiagnosticsConfig {
-      recursion_cycle: Severity::Warning,
-      unused_definition: Severity::Allow,
-      ..Default::default()
-    };
+  run_golden_test_dir(function_name!(), &compile_long_file)
+}
 
-    compile_book(&mut book, opts.clone(), diagnostics_cfg, None)?;
-    Ok("Compiled".to_string())
-  })
+/// Compiles a generated file with thousands of statements in a row,
+/// each one nested in the continuation of the previous one.
+/// The definition is too large for HVM, so this checks that the passes get to the end of the compilation.
+#[test]
+fn compile_long_deep_imp_stmts() {
+  let mut code = String::from("def main():\n  x = 1\n");
+  code.push_str(&"  if x:\n    x = x + 1\n  else:\n    x = 0\n".repeat(5000));
+  code.push_str("  return x\n");
+  let res = compile_long_file(&code, Path::new("deep_imp_stmts.bend")).unwrap_or_else(|e| e.to_string());
+
+  let mut settings = insta::Settings::clone_current();
+  settings.set_prepend_module_to_snapshot(false);
+  settings.set_omit_expression(true);
+  settings.bind(|| assert_snapshot!("compile_long__deep_imp_stmts", res));
+}
+
+fn compile_long_file(code: &str, path: &Path) -> Result<String, Diagnostics> {
+  let mut book = parse_book_single_file(code, path)?;
+  let opts = CompileOpts::default().set_all();
+  let diagnostics_cfg = DiagnosticsConfig {
+    recursion_cycle: Severity::Warning,
+    unused_definition: Severity::Allow,
+    ..Default::default()
+  };
+
+  compile_book(&mut book, opts, diagnostics_cfg, None)?;
+  Ok("Compiled".to_string())
 }